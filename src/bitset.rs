@@ -1,4 +1,14 @@
-#[derive(Clone, PartialEq, Eq, Debug)]
+//! A compact set of bits, used for classical registers.
+//!
+//! When converted to integers, the bit at index $i$ has weight $2^i$. When converted to
+//! strings, the bits are written from the highest index to the lowest, so that the
+//! string reads as the binary representation of the integer: the bit at index 0 is
+//! the rightmost character.
+
+use std::fmt;
+use std::ops::BitXorAssign;
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct BitSet {
     size: usize,
     data: Box<[u8]>,
@@ -21,7 +31,7 @@ impl BitSet {
     pub fn new(size: usize) -> Self {
         Self {
             size,
-            data: vec![0; word(size)].into_boxed_slice(),
+            data: vec![0; word(size + 7)].into_boxed_slice(),
         }
     }
 
@@ -30,9 +40,14 @@ impl BitSet {
         self.size
     }
 
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
     #[inline]
     pub fn get(&self, index: usize) -> Option<bool> {
-        (index < self.size).then(|| self.data[word(index)] & mask(index) == 1)
+        (index < self.size).then(|| self.data[word(index)] & mask(index) != 0)
     }
 
     #[inline]
@@ -43,4 +58,114 @@ impl BitSet {
             self.data[word(index)] &= !mask(index)
         })
     }
-}
\ No newline at end of file
+
+    /// Sets all bits to `false`.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.data.fill(0);
+    }
+
+    /// Returns the number of bits set to `true`.
+    #[inline]
+    pub fn count_ones(&self) -> usize {
+        self.data.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// Returns true if no bit is set to `true`.
+    #[inline]
+    pub fn is_zero(&self) -> bool {
+        self.data.iter().all(|&word| word == 0)
+    }
+
+    /// Returns the packed representation of the set, the `i`th bit being the
+    /// `i % 8`th least significant bit of the `i / 8`th byte. Bits past the
+    /// length of the set are always zero.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the set as a string of `'0'` and `'1'`, the bit at index 0 being the
+    /// rightmost character.
+    #[inline]
+    pub fn to_bitstring(&self) -> String {
+        (0..self.size).rev().map(|i| if self.data[word(i)] & mask(i) != 0 { '1' } else { '0' }).collect()
+    }
+
+    /// Parses a string of `'0'` and `'1'`, the rightmost character being the bit at index 0.
+    /// Returns `None` if the string contains any other character.
+    pub fn from_bitstring(s: &str) -> Option<Self> {
+        let mut res = Self::new(s.len());
+
+        for (i, c) in s.bytes().rev().enumerate() {
+            match c {
+                b'0' => (),
+                b'1' => res.data[word(i)] |= mask(i),
+                _ => return None,
+            }
+        }
+
+        Some(res)
+    }
+
+    /// Returns the set as an integer, the bit at index $i$ having weight $2^i$.
+    /// Returns `None` if a bit of weight greater than $2^{63}$ is set.
+    pub fn to_integer(&self) -> Option<u64> {
+        let (low, high) = self.data.split_at(self.data.len().min(8));
+
+        high.iter().all(|&word| word == 0).then(|| {
+            low.iter().rev().fold(0, |acc, &word| acc << 8 | word as u64)
+        })
+    }
+
+    /// Returns the set of the given size, whose bits are those of the integer `n`,
+    /// the bit at index $i$ having weight $2^i$. Bits of `n` past `size` are ignored.
+    pub fn from_integer(size: usize, n: u64) -> Self {
+        let mut res = Self::new(size);
+
+        for i in 0..size.min(64) {
+            if n >> i & 1 == 1 {
+                res.data[word(i)] |= mask(i);
+            }
+        }
+
+        res
+    }
+
+    /// Returns an iterator over the bits of the set.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.size).map(|i| self.data[word(i)] & mask(i) != 0)
+    }
+}
+
+impl fmt::Display for BitSet {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_bitstring())
+    }
+}
+
+impl BitXorAssign<&BitSet> for BitSet {
+    /// Xors the bits of `rhs` into `self`, one word at a time.
+    /// Panics if the sets are not of the same length.
+    #[inline]
+    fn bitxor_assign(&mut self, rhs: &BitSet) {
+        assert_eq!(self.size, rhs.size, "bitsets are not of the same length");
+        self.data.iter_mut().zip(rhs.data.iter()).for_each(|(x, y)| *x ^= y);
+    }
+}
+
+impl FromIterator<bool> for BitSet {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> Self {
+        let bits: Vec<bool> = iter.into_iter().collect();
+        let mut res = Self::new(bits.len());
+
+        for (i, bit) in bits.into_iter().enumerate() {
+            res.set(i, bit);
+        }
+
+        res
+    }
+}
@@ -0,0 +1,135 @@
+use super::instruction::{Instr, Modifier};
+use super::parameter::Parameter;
+use super::symbol::{FormalParameter, Symbol};
+use super::{QuantumCircuit, QuantumCircuitError};
+
+impl QuantumCircuit {
+    /// Rewrites the instructions, replacing the formal parameter of id `i` by `values[i]`
+    /// when it is `Some`. Expects `values` to have one entry per formal parameter.
+    fn substitute(&self, values: &[Option<f32>]) -> Result<Self, QuantumCircuitError> {
+        let values: Vec<_> = values.iter().map(|v| v.map(f64::from)).collect();
+
+        let mut res = Self {
+            instrs: Vec::with_capacity(self.instrs.len()),
+            expressions: Vec::new(),
+            ..*self
+        };
+
+        // Expressions are substituted into new expressions, which may become constants, and
+        // are interned again in the result.
+        fn bind<'id>(circ: &QuantumCircuit, res: &mut QuantumCircuit, parameter: Parameter<'id>, values: &[Option<f64>]) -> Result<Parameter<'id>, QuantumCircuitError> {
+            match circ.expression(parameter) {
+                Some(expr) => res.intern(expr.substitute(values)),
+                None => Ok(match parameter.as_formal().and_then(|formal| values[formal.id() as usize]) {
+                    Some(value) => Parameter::from(value as f32),
+                    None => parameter,
+                }),
+            }
+        }
+
+        let mut instr = Instr::default();
+        let mut src = self.instrs.as_slice();
+
+        while !src.is_empty() {
+            instr.read(&mut src);
+
+            let parameters = instr.parameters.iter().map(|&p| bind(self, &mut res, p, &values)).collect::<Result<Vec<_>, _>>()?;
+            let modifier = match instr.modifier.take() {
+                Some(Modifier::Pow(exponent)) => Some(Modifier::Pow(bind(self, &mut res, exponent, &values)?)),
+                modifier => modifier,
+            };
+
+            Instr { parameters: &parameters, modifier, ..instr.clone() }.write(&mut res.instrs);
+        }
+
+        Ok(res)
+    }
+
+    /// Returns the circuit where the given formal parameters are replaced by their value.
+    /// The other formal parameters are left unbound, so that the circuit can be bound in
+    /// several steps. If a parameter is bound several times, the last value is used.
+    ///
+    /// The formal parameters keep their ids, and the parameter count is unchanged.
+    ///
+    /// Fails if a formal parameter was not allocated in this circuit.
+    pub fn bind(&self, bindings: &[(FormalParameter<'_>, f32)]) -> Result<Self, QuantumCircuitError> {
+        let mut values = vec![None; self.parameter_count()];
+
+        for &(formal, value) in bindings {
+            let slot = values.get_mut(formal.id() as usize).ok_or(QuantumCircuitError::UnknownParameter(formal.id()))?;
+            *slot = Some(value);
+        }
+
+        self.substitute(&values)
+    }
+
+    /// Returns the circuit where each formal parameter is replaced by the value at the index
+    /// of it's id in `values`.
+    ///
+    /// Fails if the number of values is not the number of formal parameters.
+    pub fn bind_all(&self, values: &[f32]) -> Result<Self, QuantumCircuitError> {
+        if values.len() != self.parameter_count() {
+            return Err(QuantumCircuitError::BindingCount {
+                expected: self.parameter_count(),
+                found: values.len(),
+            });
+        }
+
+        let values: Vec<_> = values.iter().copied().map(Some).collect();
+        self.substitute(&values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::operation::OpKind;
+
+    use super::*;
+
+    fn circuit() -> QuantumCircuit {
+        QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            let [theta, phi]: [FormalParameter; 2] = circ.alloc_n()?;
+            circ.rx(theta, q).u3(phi, 0.5, theta, q);
+            circ.push(Instr { op: OpKind::H, qubits: &[q], modifier: Some(Modifier::Pow(phi.into())), ..Default::default() })?;
+            Ok(())
+        }).unwrap()
+    }
+
+    /// Returns the parameters of the instructions, including the exponents of powers.
+    fn parameters(circ: &QuantumCircuit) -> Vec<Option<f32>> {
+        let mut res = Vec::new();
+        let mut iter = circ.instructions();
+
+        while let Some(instr) = iter.next() {
+            res.extend(instr.parameters.iter().map(|p| p.as_value()));
+
+            if let Some(Modifier::Pow(exponent)) = &instr.modifier {
+                res.push(exponent.as_value());
+            }
+        }
+
+        res
+    }
+
+    #[test]
+    fn bind() {
+        let circ = circuit();
+        let partial = circ.bind(&[(FormalParameter::new_unchecked(0), 1.5)]).unwrap();
+
+        assert_eq!(parameters(&partial), [Some(1.5), None, Some(0.5), Some(1.5), None]);
+        assert_eq!(partial.parameter_count(), 2);
+
+        let full = partial.bind(&[(FormalParameter::new_unchecked(1), 0.25)]).unwrap();
+        assert_eq!(parameters(&full), [Some(1.5), Some(0.25), Some(0.5), Some(1.5), Some(0.25)]);
+        assert_eq!(parameters(&circ.bind_all(&[1.5, 0.25]).unwrap()), parameters(&full));
+    }
+
+    #[test]
+    fn errors() {
+        let circ = circuit();
+
+        assert_eq!(circ.bind(&[(FormalParameter::new_unchecked(2), 1.0)]).err(), Some(QuantumCircuitError::UnknownParameter(2)));
+        assert_eq!(circ.bind_all(&[1.0]).err(), Some(QuantumCircuitError::BindingCount { expected: 2, found: 1 }));
+    }
+}
@@ -0,0 +1,519 @@
+//! Decomposition of the quantum modifiers into basic gates.
+//!
+//! Operations are first lowered to single-qubit unitaries, CX and CCX gates, which are
+//! exact including their global phase so that they can be controlled. Controlled
+//! unitaries are then built with the constructions of Barenco et al. [9], see
+//! `doc/biblio.md`. The resulting gates are exact up to a global phase.
+
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use crate::complex::Complex;
+use crate::matrix::Matrix;
+
+use super::instruction::{Instr, Modifier};
+use super::inverse::adjoint;
+use super::operation::OpKind;
+use super::parameter::Parameter;
+use super::symbol::{List, Qubit, Symbol};
+use super::unitary;
+use super::{QuantumCircuit, QuantumCircuitError};
+
+/// The tolerance under which matrix entries are considered equal.
+const EPS: f64 = 1e-9;
+
+/// A basic gate, acting on qubit indices with resolved parameters.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Gate<'a, 'id> {
+    pub op: OpKind<'a, 'id>,
+    pub qubits: Vec<usize>,
+    pub parameters: Vec<f64>,
+}
+
+impl<'a, 'id> Gate<'a, 'id> {
+    #[inline]
+    fn new(op: OpKind<'a, 'id>, qubits: &[usize], parameters: &[f64]) -> Self {
+        Self { op, qubits: qubits.to_vec(), parameters: parameters.to_vec() }
+    }
+}
+
+/// A quantum modifier whose qubits and exponent are resolved.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Quantum {
+    Control(Vec<usize>),
+    NegControl(Vec<usize>),
+    Pow(f64),
+    Inverse,
+}
+
+impl Quantum {
+    /// Returns the resolved quantum modifier, or `None` if the modifier is classical.
+    pub(crate) fn new<'id, E>(
+        modifier: &Modifier<'_, 'id>,
+        resolve: impl FnOnce(Parameter<'id>) -> Result<f64, E>,
+    ) -> Result<Option<Self>, E> {
+        let ids = |qubits: &List<Qubit<'_>>| qubits.iter().map(|q| q.id() as usize).collect();
+
+        Ok(Some(match modifier {
+            Modifier::Control(qubits) => Self::Control(ids(qubits)),
+            Modifier::NegControl(qubits) => Self::NegControl(ids(qubits)),
+            Modifier::Pow(exponent) => Self::Pow(resolve(*exponent)?),
+            Modifier::Inverse => Self::Inverse,
+            _ => return Ok(None),
+        }))
+    }
+}
+
+/// The elementary operations controlled unitaries are built from. Unlike gates, the
+/// global phase of single-qubit unitaries matters.
+#[derive(Clone, Debug)]
+enum Elem {
+    U(usize, Matrix),
+    CX(usize, usize),
+    Toffoli(usize, usize, usize),
+}
+
+/// Returns the square root of a complex number, on the principal branch.
+#[inline]
+fn sqrt(z: Complex) -> Complex {
+    Complex::cis(z.arg() / 2.0) * z.abs().sqrt()
+}
+
+/// Returns the power of a 2x2 unitary, whose eigenphases in $(-\pi, \pi]$ are scaled.
+fn power(m: &Matrix, x: f64) -> Matrix {
+    let (a, b, c, d) = (m[(0, 0)], m[(0, 1)], m[(1, 0)], m[(1, 1)]);
+    let (tr, det) = (a + d, a * d - b * c);
+    let root = sqrt(tr * tr - det * 4.0);
+    let (l1, l2) = ((tr + root) / 2.0, (tr - root) / 2.0);
+    let pow = |l: Complex| Complex::cis(l.arg() * x);
+
+    if (l1 - l2).abs() < EPS {
+        return Matrix::identity(2).scale(pow(l1));
+    }
+
+    // m = l1 P1 + l2 P2, where P1 = (m - l2) / (l1 - l2) and P2 = (m - l1) / (l2 - l1).
+    let projector = |l: Complex, other: Complex| {
+        let data = [a - other, b, c, d - other].map(|z| z / (l - other));
+        Matrix::from_vec(2, data.to_vec()).unwrap()
+    };
+
+    let (p1, p2) = (projector(l1, l2), projector(l2, l1));
+    let data = p1.as_slice().iter().zip(p2.as_slice()).map(|(&u, &v)| pow(l1) * u + pow(l2) * v).collect();
+
+    Matrix::from_vec(2, data).unwrap()
+}
+
+/// Returns $(\alpha, \beta, \gamma, \delta)$ such that the 2x2 unitary is
+/// $e^{i \alpha} R_Z(\beta) R_Y(\gamma) R_Z(\delta)$.
+fn zyz(m: &Matrix) -> (f64, f64, f64, f64) {
+    let det = m[(0, 0)] * m[(1, 1)] - m[(0, 1)] * m[(1, 0)];
+    let alpha = det.arg() / 2.0;
+
+    // The matrix of determinant 1 is [[e^{-is} c, -e^{-id} s], [e^{id} s, e^{is} c]], where
+    // s and d are the half sum and difference of beta and delta.
+    let v = m.scale(Complex::cis(-alpha));
+    let gamma = 2.0 * v[(1, 0)].abs().atan2(v[(1, 1)].abs());
+    let sum = if v[(1, 1)].abs() > EPS { v[(1, 1)].arg() } else { 0.0 };
+    let diff = if v[(1, 0)].abs() > EPS { v[(1, 0)].arg() } else { 0.0 };
+
+    (alpha, sum + diff, gamma, sum - diff)
+}
+
+/// Returns true if the 2x2 unitary is the X gate.
+#[inline]
+fn is_x(m: &Matrix) -> bool {
+    m.approx_eq(&unitary::x(), EPS)
+}
+
+/// Appends a unitary on `target` controlled by a single qubit, see [9, Lemma 5.1].
+fn ctrl_one(control: usize, target: usize, m: &Matrix, out: &mut Vec<Elem>) {
+    let (alpha, beta, gamma, delta) = zyz(m);
+
+    out.extend([
+        Elem::U(target, unitary::rz((delta - beta) / 2.0)),
+        Elem::CX(control, target),
+        Elem::U(target, &unitary::ry(-gamma / 2.0) * &unitary::rz(-(delta + beta) / 2.0)),
+        Elem::CX(control, target),
+        Elem::U(target, &unitary::rz(beta) * &unitary::ry(gamma / 2.0)),
+        Elem::U(control, unitary::phase(alpha)),
+    ]);
+}
+
+/// Appends a unitary on `target` controlled by all the `controls`, see [9, Lemma 7.5].
+fn ctrl(controls: &[usize], target: usize, m: &Matrix, out: &mut Vec<Elem>) {
+    match controls {
+        [] => out.push(Elem::U(target, m.clone())),
+        &[c] if is_x(m) => out.push(Elem::CX(c, target)),
+        &[a, b] if is_x(m) => out.push(Elem::Toffoli(a, b, target)),
+        &[c] => ctrl_one(c, target, m, out),
+        &[ref rest @ .., last] => {
+            let v = power(m, 0.5);
+            let x = unitary::x();
+
+            ctrl_one(last, target, &v, out);
+            ctrl(rest, last, &x, out);
+            ctrl_one(last, target, &v.adjoint(), out);
+            ctrl(rest, last, &x, out);
+            ctrl(rest, target, &v, out);
+        }
+    }
+}
+
+/// Lowers a unitary operation to elementary operations, exactly.
+fn elems(op: &OpKind<'_, '_>, q: &[usize], p: &[f64]) -> Vec<Elem> {
+    use Elem::*;
+
+    let mut out = Vec::new();
+
+    match op {
+        OpKind::CX => out.push(CX(q[0], q[1])),
+        OpKind::CY => out.extend([U(q[1], unitary::sdg()), CX(q[0], q[1]), U(q[1], unitary::s())]),
+        OpKind::CZ => out.extend([U(q[1], unitary::h()), CX(q[0], q[1]), U(q[1], unitary::h())]),
+        OpKind::CH => ctrl_one(q[0], q[1], &unitary::h(), &mut out),
+        OpKind::Swap => out.extend([CX(q[0], q[1]), CX(q[1], q[0]), CX(q[0], q[1])]),
+        // iSWAP = SWAP CZ (S ⊗ S).
+        OpKind::ISwap => {
+            out.extend([U(q[0], unitary::s()), U(q[1], unitary::s())]);
+            out.extend(elems(&OpKind::CZ, q, &[]));
+            out.extend(elems(&OpKind::Swap, q, &[]));
+        }
+        OpKind::CRZ => ctrl_one(q[0], q[1], &unitary::rz(p[0]), &mut out),
+        OpKind::CP => ctrl_one(q[0], q[1], &unitary::phase(p[0]), &mut out),
+        OpKind::RXX => {
+            let h = [U(q[0], unitary::h()), U(q[1], unitary::h())];
+            out.extend(h.clone());
+            out.extend(elems(&OpKind::RZZ, q, p));
+            out.extend(h);
+        }
+        OpKind::RZZ => out.extend([CX(q[0], q[1]), U(q[1], unitary::rz(p[0])), CX(q[0], q[1])]),
+        OpKind::CCX => out.push(Toffoli(q[0], q[1], q[2])),
+        OpKind::CSwap => out.extend([CX(q[2], q[1]), Toffoli(q[0], q[1], q[2]), CX(q[2], q[1])]),
+        op => out.push(U(q[0], op.matrix(p).expect("operation is not a single-qubit unitary"))),
+    }
+
+    out
+}
+
+/// Converts elementary operations to gates, up to a global phase.
+fn lower<'a, 'id>(elems: Vec<Elem>, out: &mut Vec<Gate<'a, 'id>>) {
+    for elem in elems {
+        let (q, m) = match elem {
+            Elem::CX(c, t) => {
+                out.push(Gate::new(OpKind::CX, &[c, t], &[]));
+                continue;
+            }
+            Elem::Toffoli(a, b, t) => {
+                out.push(Gate::new(OpKind::CCX, &[a, b, t], &[]));
+                continue;
+            }
+            Elem::U(q, m) => (q, m),
+        };
+
+        let (a, b, c, d) = (m[(0, 0)], m[(0, 1)], m[(1, 0)], m[(1, 1)]);
+
+        if b.abs() < EPS && c.abs() < EPS {
+            let lambda = (d / a).arg();
+
+            if lambda.abs() > EPS {
+                out.push(Gate::new(OpKind::Phase, &[q], &[lambda]));
+            }
+        } else if a.abs() < EPS {
+            if (b - c).abs() < EPS {
+                out.push(Gate::new(OpKind::X, &[q], &[]));
+            } else {
+                out.push(Gate::new(OpKind::U3, &[q], &[PI, c.arg() - (-b).arg(), 0.0]));
+            }
+        } else {
+            let theta = 2.0 * c.abs().atan2(a.abs());
+            out.push(Gate::new(OpKind::U3, &[q], &[theta, c.arg() - a.arg(), (-b).arg() - a.arg()]));
+        }
+    }
+}
+
+/// Appends the gates of a unitary operation controlled by all the `controls`.
+fn control<'a, 'id>(op: &OpKind<'a, 'id>, q: &[usize], p: &[f64], controls: &[usize], out: &mut Vec<Gate<'a, 'id>>) {
+    let shortcut = match (controls.len(), op) {
+        (1, OpKind::X) => Some(OpKind::CX),
+        (1, OpKind::Y) => Some(OpKind::CY),
+        (1, OpKind::Z) => Some(OpKind::CZ),
+        (1, OpKind::H) => Some(OpKind::CH),
+        (1, OpKind::RZ) => Some(OpKind::CRZ),
+        (1, OpKind::Phase) => Some(OpKind::CP),
+        (1, OpKind::Swap) => Some(OpKind::CSwap),
+        (2, OpKind::X) => Some(OpKind::CCX),
+        _ => None,
+    };
+
+    if let Some(op) = shortcut {
+        out.push(Gate::new(op, &[controls, q].concat(), p));
+        return;
+    }
+
+    let mut res = Vec::new();
+
+    for elem in elems(op, q, p) {
+        match elem {
+            Elem::U(t, m) => ctrl(controls, t, &m, &mut res),
+            Elem::CX(c, t) => ctrl(&[controls, &[c]].concat(), t, &unitary::x(), &mut res),
+            Elem::Toffoli(a, b, t) => ctrl(&[controls, &[a, b]].concat(), t, &unitary::x(), &mut res),
+        }
+    }
+
+    lower(res, out);
+}
+
+/// Appends the gates of a unitary operation raised to the power `x`.
+fn pow<'a, 'id>(op: &OpKind<'a, 'id>, q: &[usize], p: &[f64], x: f64, out: &mut Vec<Gate<'a, 'id>>) {
+    if x == x.round() {
+        let mut base = Vec::new();
+
+        if x >= 0.0 {
+            base.push(Gate::new(op.clone(), q, p));
+        } else {
+            let negated: Vec<_> = p.iter().map(|&p| -p).collect();
+            adjoint(op, q, &negated, |op, q, p| base.push(Gate::new(op, q, p)));
+        }
+
+        for _ in 0..x.abs() as usize {
+            out.extend(base.iter().cloned());
+        }
+
+        return;
+    }
+
+    let x_pow = || power(&unitary::x(), x);
+    let mut res = Vec::new();
+
+    match op {
+        OpKind::RX | OpKind::RY | OpKind::RZ | OpKind::Phase | OpKind::CRZ | OpKind::CP | OpKind::RXX | OpKind::RZZ => {
+            out.push(Gate::new(op.clone(), q, &[p[0] * x]));
+            return;
+        }
+        // iSWAP = exp(iπ/4 (XX + YY)), where XX and YY commute, and YY = (S ⊗ S) XX (S ⊗ S)†.
+        OpKind::ISwap => {
+            let rxx = Gate::new(OpKind::RXX, q, &[-FRAC_PI_2 * x]);
+
+            out.extend([Gate::new(OpKind::Sdg, &q[..1], &[]), Gate::new(OpKind::Sdg, &q[1..], &[]), rxx.clone()]);
+            out.extend([Gate::new(OpKind::S, &q[..1], &[]), Gate::new(OpKind::S, &q[1..], &[]), rxx]);
+            return;
+        }
+        OpKind::CX => ctrl(&q[..1], q[1], &x_pow(), &mut res),
+        OpKind::CY => ctrl(&q[..1], q[1], &power(&unitary::y(), x), &mut res),
+        OpKind::CZ => ctrl(&q[..1], q[1], &power(&unitary::z(), x), &mut res),
+        OpKind::CH => ctrl(&q[..1], q[1], &power(&unitary::h(), x), &mut res),
+        OpKind::CCX => ctrl(&q[..2], q[2], &x_pow(), &mut res),
+        // SWAP = CX(b, a) CX(a, b) CX(b, a), where CX(b, a) is it's own inverse.
+        OpKind::Swap => {
+            res.push(Elem::CX(q[1], q[0]));
+            ctrl(&q[..1], q[1], &x_pow(), &mut res);
+            res.push(Elem::CX(q[1], q[0]));
+        }
+        OpKind::CSwap => {
+            res.push(Elem::CX(q[2], q[1]));
+            ctrl(&q[..2], q[2], &x_pow(), &mut res);
+            res.push(Elem::CX(q[2], q[1]));
+        }
+        op => {
+            let m = op.matrix(p).expect("operation is not a single-qubit unitary");
+            res.push(Elem::U(q[0], power(&m, x)));
+        }
+    }
+
+    lower(res, out);
+}
+
+/// Returns the basic gates implementing a unitary operation with a quantum modifier,
+/// up to a global phase.
+pub(crate) fn expand<'a, 'id>(op: &OpKind<'a, 'id>, qubits: &[usize], parameters: &[f64], modifier: &Quantum) -> Vec<Gate<'a, 'id>> {
+    let mut out = Vec::new();
+
+    match modifier {
+        Quantum::Control(controls) => control(op, qubits, parameters, controls, &mut out),
+        Quantum::NegControl(controls) => {
+            let flips: Vec<_> = controls.iter().map(|&c| Gate::new(OpKind::X, &[c], &[])).collect();
+
+            out.extend(flips.iter().cloned());
+            control(op, qubits, parameters, controls, &mut out);
+            out.extend(flips);
+        }
+        Quantum::Pow(x) => pow(op, qubits, parameters, *x, &mut out),
+        Quantum::Inverse => {
+            let negated: Vec<_> = parameters.iter().map(|&p| -p).collect();
+            adjoint(op, qubits, &negated, |op, q, p| out.push(Gate::new(op, q, p)));
+        }
+    }
+
+    out
+}
+
+impl QuantumCircuit {
+    /// Returns an equivalent circuit, up to a global phase, where the instructions with
+    /// a control, power or inverse modifier are replaced by basic gates. Classically
+    /// controlled instructions are left untouched.
+    ///
+    /// Fails if a modified instruction depends on a formal parameter.
+    pub fn expand_modifiers(&self) -> Result<Self, QuantumCircuitError> {
+        let mut res = Self {
+            instrs: Vec::with_capacity(self.instrs.len()),
+            ..self.clone()
+        };
+
+        let value = |parameter| self.evaluate(parameter, &[]).map_err(QuantumCircuitError::FormalParameter);
+        let mut instr = Instr::default();
+        let mut src = self.instrs.as_slice();
+
+        while !src.is_empty() {
+            instr.read(&mut src);
+
+            let Some(quantum) = instr.modifier.as_ref().map(|m| Quantum::new(m, value)).transpose()?.flatten() else {
+                instr.write(&mut res.instrs);
+                continue;
+            };
+
+            let qubits: Vec<_> = instr.qubits.iter().map(|q| q.id() as usize).collect();
+            let parameters = instr.parameters.iter().map(|&p| value(p)).collect::<Result<Vec<_>, _>>()?;
+
+            for gate in expand(&instr.op, &qubits, &parameters, &quantum) {
+                let qubits: Vec<Qubit> = gate.qubits.iter().map(|&q| Qubit::new_unchecked(q as u32)).collect();
+                let parameters: Vec<Parameter> = gate.parameters.iter().map(|&p| Parameter::from(p as f32)).collect();
+
+                Instr { op: gate.op, qubits: &qubits, parameters: &parameters, ..Default::default() }.write(&mut res.instrs);
+            }
+        }
+
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::CircuitBuilder;
+    use crate::circuit::symbol::FormalParameter;
+
+    use super::*;
+
+    /// A gate on a number of qubits, written with the circuit builder.
+    type Case = (usize, for<'id> fn(&mut CircuitBuilder<'id>, &[Qubit<'id>]));
+
+    const CASES: [Case; 10] = [
+        (1, |circ, q| _ = circ.x(q[0])),
+        (1, |circ, q| _ = circ.h(q[0])),
+        (1, |circ, q| _ = circ.sx(q[0])),
+        (1, |circ, q| _ = circ.ry(0.7, q[0])),
+        (1, |circ, q| _ = circ.u3(0.3, -1.2, 2.0, q[0])),
+        (2, |circ, q| _ = circ.cy(q[1], q[0])),
+        (2, |circ, q| _ = circ.swap(q[0], q[1])),
+        (2, |circ, q| _ = circ.iswap(q[0], q[1])),
+        (2, |circ, q| _ = circ.rxx(-0.4, q[0], q[1])),
+        (3, |circ, q| _ = circ.ccx(q[0], q[1], q[2])),
+    ];
+
+    /// Returns the circuit applying the gate `repeat` times, with `extra` unused qubits.
+    fn circuit(&(n, gate): &Case, extra: usize, repeat: usize) -> QuantumCircuit {
+        QuantumCircuit::new(|circ| {
+            let qubits: Vec<Qubit> = circ.alloc_list(n + extra)?.into_iter().collect();
+            (0..repeat).for_each(|_| gate(circ, &qubits[..n]));
+            Ok(())
+        }).unwrap()
+    }
+
+    /// Returns the circuit with the modifier set on all it's instructions.
+    fn modify<'a, 'id>(circ: &'a QuantumCircuit, modifier: Modifier<'a, 'id>) -> QuantumCircuit {
+        let mut res = QuantumCircuit { instrs: Vec::new(), ..circ.clone() };
+        let mut instr: Instr<'a, 'id> = Instr::default();
+        let mut src = circ.instrs.as_slice();
+
+        while !src.is_empty() {
+            instr.read(&mut src);
+            instr.modifier = Some(modifier.clone());
+            instr.write(&mut res.instrs);
+        }
+
+        res
+    }
+
+    /// Asserts that the expanded circuit has the same unitary, up to a global phase.
+    fn check(circ: &QuantumCircuit) {
+        let expanded = circ.expand_modifiers().unwrap();
+        assert!(expanded.unitary().unwrap().approx_eq_up_to_phase(&circ.unitary().unwrap(), 1e-5));
+    }
+
+    #[test]
+    fn power() {
+        for m in [unitary::x(), unitary::h(), unitary::t(), unitary::u3(0.3, -1.2, 2.0), unitary::rz(3.0)] {
+            let root = super::power(&m, 0.5);
+
+            assert!((&root * &root).approx_eq(&m, EPS));
+            assert!(super::power(&m, -1.0).approx_eq(&m.adjoint(), EPS));
+        }
+
+        // The phase of the eigenvalue -1 of Z is π, so it's square root is S.
+        assert!(super::power(&unitary::z(), 0.5).approx_eq(&unitary::s(), EPS));
+    }
+
+    #[test]
+    fn control() {
+        for case @ &(n, _) in &CASES {
+            for controls in 1..=3 {
+                let base = circuit(case, controls, 1);
+                let range = n as u32..(n + controls) as u32;
+
+                check(&modify(&base, Modifier::Control(List::from_range_unchecked(range.clone()))));
+                check(&modify(&base, Modifier::NegControl(List::from_range_unchecked(range))));
+            }
+        }
+
+        let cx = QuantumCircuit::new(|circ| {
+            let [a, b] = circ.alloc_n()?;
+            circ.cx(b, a);
+            Ok(())
+        }).unwrap();
+
+        let base = circuit(&CASES[0], 1, 1);
+        let controlled = modify(&base, Modifier::Control(List::from_range_unchecked(1..2)));
+
+        assert!(controlled.unitary().unwrap().approx_eq(&cx.unitary().unwrap(), EPS));
+        assert_eq!(controlled.expand_modifiers().unwrap().instructions().next().unwrap().op, OpKind::CX);
+    }
+
+    #[test]
+    fn pow() {
+        for case in &CASES {
+            let expected = circuit(case, 0, 1).unitary().unwrap();
+
+            for (x, repeat) in [(0.5, 2), (0.25, 4), (-1.0 / 3.0, 3)] {
+                let root = circuit(case, 0, repeat);
+                let unitary = modify(&root, Modifier::Pow(Parameter::from(x as f32))).expand_modifiers().unwrap().unitary().unwrap();
+                let expected = if x < 0.0 { expected.adjoint() } else { expected.clone() };
+
+                assert!(unitary.approx_eq_up_to_phase(&expected, 1e-5));
+            }
+
+            let base = circuit(case, 0, 1);
+            let inverse = modify(&base, Modifier::Pow(Parameter::from(-2.0)));
+
+            assert!(inverse.unitary().unwrap().approx_eq(&(&expected * &expected).adjoint(), 1e-6));
+            check(&inverse);
+            check(&modify(&base, Modifier::Pow(Parameter::from(0.3))));
+        }
+    }
+
+    #[test]
+    fn inverse() {
+        for case in &CASES {
+            let base = circuit(case, 0, 1);
+            let inverse = modify(&base, Modifier::Inverse);
+
+            assert!(inverse.unitary().unwrap().approx_eq(&base.unitary().unwrap().adjoint(), 1e-6));
+            check(&inverse);
+        }
+
+        let circ = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            let theta: FormalParameter = circ.alloc()?;
+            circ.push(Instr { op: OpKind::RX, qubits: &[q], parameters: &[theta.into()], modifier: Some(Modifier::Inverse), ..Default::default() })?;
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(circ.expand_modifiers().err(), Some(QuantumCircuitError::FormalParameter(0)));
+    }
+}
@@ -0,0 +1,447 @@
+//! Symbolic expressions over formal parameters, such as `2 * theta + phi - pi / 4`.
+//!
+//! Expressions are built with the arithmetic operators, [`Expr::sin`] and [`Expr::cos`],
+//! and are turned into a [`Parameter`] with [`CircuitBuilder::expr`]. They are stored in
+//! a side table of the circuit, that the parameter refers to by index.
+
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use super::parameter::Parameter;
+use super::symbol::{FormalParameter, Symbol};
+use super::symbol::private::SymbolPrivate;
+use super::{CircuitBuilder, QuantumCircuit, QuantumCircuitError};
+
+/// An expression over formal parameters, identified by their id. Constants are folded
+/// when the expression is built.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Expr {
+    Value(f64),
+    Formal(u32),
+    Neg(Box<Expr>),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Div(Box<Expr>, Box<Expr>),
+    Sin(Box<Expr>),
+    Cos(Box<Expr>),
+}
+
+impl Expr {
+    /// Returns the sine of the expression.
+    #[inline]
+    pub fn sin(self) -> Self {
+        match self {
+            Self::Value(x) => Self::Value(x.sin()),
+            e => Self::Sin(Box::new(e)),
+        }
+    }
+
+    /// Returns the cosine of the expression.
+    #[inline]
+    pub fn cos(self) -> Self {
+        match self {
+            Self::Value(x) => Self::Value(x.cos()),
+            e => Self::Cos(Box::new(e)),
+        }
+    }
+
+    /// Returns the value of the expression if it is constant.
+    #[inline]
+    pub fn as_value(&self) -> Option<f64> {
+        match self {
+            Self::Value(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    /// Returns the ids of the formal parameters the expression depends on, sorted and
+    /// without duplicates.
+    pub fn formals(&self) -> Vec<u32> {
+        fn visit(e: &Expr, out: &mut Vec<u32>) {
+            match e {
+                Expr::Value(_) => (),
+                Expr::Formal(id) => out.push(*id),
+                Expr::Neg(a) | Expr::Sin(a) | Expr::Cos(a) => visit(a, out),
+                Expr::Add(a, b) | Expr::Sub(a, b) | Expr::Mul(a, b) | Expr::Div(a, b) => {
+                    visit(a, out);
+                    visit(b, out);
+                }
+            }
+        }
+
+        let mut res = Vec::new();
+        visit(self, &mut res);
+        res.sort_unstable();
+        res.dedup();
+        res
+    }
+
+    /// Evaluates the expression, the formal parameter of id `i` taking the value `values[i]`.
+    /// Fails with the id of the first formal parameter that has no value.
+    pub fn eval(&self, values: &[f64]) -> Result<f64, u32> {
+        Ok(match self {
+            Self::Value(x) => *x,
+            Self::Formal(id) => return values.get(*id as usize).copied().ok_or(*id),
+            Self::Neg(a) => -a.eval(values)?,
+            Self::Add(a, b) => a.eval(values)? + b.eval(values)?,
+            Self::Sub(a, b) => a.eval(values)? - b.eval(values)?,
+            Self::Mul(a, b) => a.eval(values)? * b.eval(values)?,
+            Self::Div(a, b) => a.eval(values)? / b.eval(values)?,
+            Self::Sin(a) => a.eval(values)?.sin(),
+            Self::Cos(a) => a.eval(values)?.cos(),
+        })
+    }
+
+    /// Returns the expression where the formal parameter of id `i` is replaced by `values[i]`
+    /// when it is `Some`. Ids out of the bounds of `values` are left untouched.
+    pub fn substitute(&self, values: &[Option<f64>]) -> Self {
+        match self {
+            Self::Value(x) => Self::Value(*x),
+            Self::Formal(id) => match values.get(*id as usize) {
+                Some(&Some(x)) => Self::Value(x),
+                _ => Self::Formal(*id),
+            },
+            Self::Neg(a) => -a.substitute(values),
+            Self::Add(a, b) => a.substitute(values) + b.substitute(values),
+            Self::Sub(a, b) => a.substitute(values) - b.substitute(values),
+            Self::Mul(a, b) => a.substitute(values) * b.substitute(values),
+            Self::Div(a, b) => a.substitute(values) / b.substitute(values),
+            Self::Sin(a) => a.substitute(values).sin(),
+            Self::Cos(a) => a.substitute(values).cos(),
+        }
+    }
+
+    /// Returns the partial derivative of the expression with respect to the formal
+    /// parameter of id `id`.
+    pub fn derivative(&self, id: u32) -> Self {
+        match self {
+            Self::Value(_) => Self::Value(0.0),
+            Self::Formal(i) => Self::Value(if *i == id { 1.0 } else { 0.0 }),
+            Self::Neg(a) => -a.derivative(id),
+            Self::Add(a, b) => a.derivative(id) + b.derivative(id),
+            Self::Sub(a, b) => a.derivative(id) - b.derivative(id),
+            Self::Mul(a, b) => a.derivative(id) * (**b).clone() + (**a).clone() * b.derivative(id),
+            Self::Div(a, b) => {
+                let (a, b) = (&**a, &**b);
+                a.derivative(id) / b.clone() - a.clone() * b.derivative(id) / (b.clone() * b.clone())
+            }
+            Self::Sin(a) => a.derivative(id) * (**a).clone().cos(),
+            Self::Cos(a) => -(a.derivative(id) * (**a).clone().sin()),
+        }
+    }
+
+    /// Returns the precedence of the root of the expression, higher binding tighter.
+    #[inline]
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::Add(..) | Self::Sub(..) => 1,
+            Self::Mul(..) | Self::Div(..) => 2,
+            Self::Neg(_) => 3,
+            Self::Value(x) if *x < 0.0 => 3,
+            _ => 4,
+        }
+    }
+
+    /// Writes the expression with the usual infix notation, naming formal parameters with
+    /// `formal` and the sine and cosine functions with `functions`.
+    pub(crate) fn write(&self, out: &mut String, formal: &dyn Fn(u32) -> String, functions: [&str; 2]) {
+        let child = |out: &mut String, e: &Self, min: u8| {
+            if e.precedence() < min {
+                out.push('(');
+                e.write(out, formal, functions);
+                out.push(')');
+            } else {
+                e.write(out, formal, functions);
+            }
+        };
+
+        let binary = |out: &mut String, a: &Self, op: &str, b: &Self, prec: u8| {
+            // Operators are left associative, so the right operand binds tighter.
+            child(out, a, prec);
+            out.push_str(op);
+            child(out, b, prec + 1);
+        };
+
+        match self {
+            Self::Value(x) => out.push_str(&x.to_string()),
+            Self::Formal(id) => out.push_str(&formal(*id)),
+            Self::Neg(a) => {
+                out.push('-');
+                child(out, a, 4);
+            }
+            Self::Add(a, b) => binary(out, a, " + ", b, 1),
+            Self::Sub(a, b) => binary(out, a, " - ", b, 1),
+            Self::Mul(a, b) => binary(out, a, " * ", b, 2),
+            Self::Div(a, b) => binary(out, a, " / ", b, 2),
+            Self::Sin(a) | Self::Cos(a) => {
+                out.push_str(functions[matches!(self, Self::Cos(_)) as usize]);
+                out.push('(');
+                a.write(out, formal, functions);
+                out.push(')');
+            }
+        }
+    }
+}
+
+/// Formal parameters are written `thetai`.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        self.write(&mut out, &|id| format!("theta{}", id), ["sin", "cos"]);
+        f.write_str(&out)
+    }
+}
+
+impl From<f64> for Expr {
+    #[inline]
+    fn from(value: f64) -> Self {
+        Self::Value(value)
+    }
+}
+
+impl From<FormalParameter<'_>> for Expr {
+    #[inline]
+    fn from(formal: FormalParameter<'_>) -> Self {
+        Self::Formal(formal.id())
+    }
+}
+
+impl Neg for Expr {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self {
+        match self {
+            Self::Value(x) => Self::Value(-x),
+            Self::Neg(a) => *a,
+            e => Self::Neg(Box::new(e)),
+        }
+    }
+}
+
+impl Add for Expr {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        match (self, rhs) {
+            (Self::Value(x), Self::Value(y)) => Self::Value(x + y),
+            (Self::Value(0.0), e) | (e, Self::Value(0.0)) => e,
+            (a, b) => Self::Add(Box::new(a), Box::new(b)),
+        }
+    }
+}
+
+impl Sub for Expr {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        match (self, rhs) {
+            (Self::Value(x), Self::Value(y)) => Self::Value(x - y),
+            (e, Self::Value(0.0)) => e,
+            (Self::Value(0.0), e) => -e,
+            (a, b) => Self::Sub(Box::new(a), Box::new(b)),
+        }
+    }
+}
+
+impl Mul for Expr {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self {
+        match (self, rhs) {
+            (Self::Value(x), Self::Value(y)) => Self::Value(x * y),
+            (Self::Value(0.0), _) | (_, Self::Value(0.0)) => Self::Value(0.0),
+            (Self::Value(1.0), e) | (e, Self::Value(1.0)) => e,
+            (a, b) => Self::Mul(Box::new(a), Box::new(b)),
+        }
+    }
+}
+
+impl Div for Expr {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: Self) -> Self {
+        match (self, rhs) {
+            (Self::Value(x), Self::Value(y)) if y != 0.0 => Self::Value(x / y),
+            (e, Self::Value(1.0)) => e,
+            (Self::Value(0.0), _) => Self::Value(0.0),
+            (a, b) => Self::Div(Box::new(a), Box::new(b)),
+        }
+    }
+}
+
+/// Implements the operators between expressions and constants, on both sides.
+macro_rules! scalar_ops {
+    ($($trait: ident :: $method: ident),*) => {
+        $(
+            impl $trait<f64> for Expr {
+                type Output = Expr;
+
+                #[inline]
+                fn $method(self, rhs: f64) -> Expr {
+                    self.$method(Expr::Value(rhs))
+                }
+            }
+
+            impl $trait<Expr> for f64 {
+                type Output = Expr;
+
+                #[inline]
+                fn $method(self, rhs: Expr) -> Expr {
+                    Expr::Value(self).$method(rhs)
+                }
+            }
+        )*
+    }
+}
+
+scalar_ops!(Add::add, Sub::sub, Mul::mul, Div::div);
+
+impl QuantumCircuit {
+    /// Returns the expression a parameter refers to, if it is an expression.
+    #[inline]
+    pub fn expression(&self, parameter: Parameter<'_>) -> Option<&Expr> {
+        parameter.as_expression().and_then(|index| self.expressions.get(index as usize))
+    }
+
+    /// Returns the value of a parameter, the formal parameter of id `i` taking the value
+    /// `values[i]`. Fails with the id of the first formal parameter that has no value.
+    pub fn evaluate(&self, parameter: Parameter<'_>, values: &[f64]) -> Result<f64, u32> {
+        if let Some(formal) = parameter.as_formal() {
+            return values.get(formal.id() as usize).copied().ok_or(formal.id());
+        }
+
+        match self.expression(parameter) {
+            Some(expr) => expr.eval(values),
+            None => Ok(parameter.as_f64().unwrap_or_default()),
+        }
+    }
+
+    /// Returns a parameter equal to the expression. Constants and lone formal parameters
+    /// are stored in the parameter itself, other expressions in the side table.
+    pub(crate) fn intern<'id>(&mut self, expr: Expr) -> Result<Parameter<'id>, QuantumCircuitError> {
+        match expr {
+            Expr::Value(x) => Ok(Parameter::from(x as f32)),
+            Expr::Formal(id) => Ok(Parameter::from(FormalParameter::new(id))),
+            expr => {
+                let index = self.expressions.len() as u32;
+
+                if index >= Parameter::MAX_EXPRESSIONS {
+                    return Err(QuantumCircuitError::AllocOverflow);
+                }
+
+                self.expressions.push(expr);
+                Ok(Parameter::expression(index))
+            }
+        }
+    }
+}
+
+impl<'id> CircuitBuilder<'id> {
+    /// Returns a parameter equal to the expression, to be used by the operations of
+    /// this circuit.
+    ///
+    /// Fails if the expression refers to a formal parameter that was not allocated
+    /// in this circuit.
+    pub fn expr(&mut self, expr: Expr) -> Result<Parameter<'id>, QuantumCircuitError> {
+        if expr.formals().last().is_some_and(|&id| id as usize >= self.parameter_count()) {
+            return Err(QuantumCircuitError::UnknownSymbol);
+        }
+
+        self.circ.intern(expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_4;
+
+    use super::*;
+
+    #[test]
+    fn eval() {
+        let (theta, phi) = (Expr::Formal(0), Expr::Formal(1));
+        let expr = 2.0 * theta.clone() + phi.clone() - FRAC_PI_4;
+
+        assert_eq!(expr.eval(&[0.5, 0.25]), Ok(1.25 - FRAC_PI_4));
+        assert_eq!(expr.eval(&[0.5]), Err(1));
+        assert_eq!(expr.formals(), [0, 1]);
+        assert_eq!(expr.substitute(&[None, Some(1.0)]).eval(&[0.5]), Ok(2.0 - FRAC_PI_4));
+        assert_eq!(expr.substitute(&[Some(0.5), Some(0.25)]), Expr::Value(1.25 - FRAC_PI_4));
+        assert_eq!((theta.clone() * 1.0 + 0.0) * (phi / 1.0), Expr::Mul(Box::new(theta.clone()), Box::new(Expr::Formal(1))));
+        assert_eq!(theta * 0.0, Expr::Value(0.0));
+    }
+
+    #[test]
+    fn derivative() {
+        let (theta, phi) = (Expr::Formal(0), Expr::Formal(1));
+        let expr = theta.clone().sin() * phi.clone() / (theta.clone() + 2.0) - phi.cos();
+        let values = [0.7, -0.3];
+
+        for id in 0..2 {
+            // Compares with a central finite difference.
+            let h = 1e-6;
+            let mut shifted = values;
+            shifted[id] += h;
+            let forward = expr.eval(&shifted).unwrap();
+            shifted[id] -= 2.0 * h;
+            let backward = expr.eval(&shifted).unwrap();
+
+            let exact = expr.derivative(id as u32).eval(&values).unwrap();
+            assert!((exact - (forward - backward) / (2.0 * h)).abs() < 1e-6);
+        }
+
+        assert_eq!(theta.derivative(1), Expr::Value(0.0));
+    }
+
+    #[test]
+    fn display() {
+        let (theta, phi) = (Expr::Formal(0), Expr::Formal(1));
+        let expr = 2.0 * (theta.clone() + phi.clone()) - (theta.clone() - phi.clone()) / -theta.sin();
+
+        assert_eq!(expr.to_string(), "2 * (theta0 + theta1) - (theta0 - theta1) / -sin(theta0)");
+        assert_eq!((1.0 - (Expr::Formal(0) - 0.5)).to_string(), "1 - (theta0 - 0.5)");
+    }
+
+    #[test]
+    fn parameters() {
+        let circ = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            let theta: FormalParameter = circ.alloc()?;
+            let double = circ.expr(Expr::from(theta) * 2.0)?;
+            let constant = circ.expr(Expr::from(0.5).cos())?;
+
+            assert!(double.is_expression());
+            assert!(constant.is_value());
+            assert_eq!(circ.expr(Expr::Formal(1)), Err(QuantumCircuitError::UnknownSymbol));
+
+            circ.rx(double, q).rz(constant, q).ry(theta, q);
+            Ok(())
+        }).unwrap();
+
+        let mut iter = circ.instructions();
+        let mut values = Vec::new();
+
+        while let Some(instr) = iter.next() {
+            values.push(circ.evaluate(instr.parameters[0], &[0.25]).unwrap());
+        }
+
+        assert_eq!(values, [0.5, 0.5f32.cos() as f64, 0.25]);
+        assert_eq!(circ.evaluate(Parameter::expression(0), &[]), Err(0));
+        assert!(circ.to_qasm3().unwrap().contains("rx(theta0 * 2) q[0];"));
+
+        let bound = circ.bind_all(&[0.25]).unwrap();
+        let mut iter = bound.instructions();
+
+        while let Some(instr) = iter.next() {
+            assert!(instr.parameters[0].is_value());
+        }
+
+        assert!(bound.expressions.is_empty());
+    }
+}
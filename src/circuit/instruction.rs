@@ -5,7 +5,7 @@ use crate::bitset::BitSet;
 use super::operation::OpKind;
 use super::storage;
 use super::parameter::Parameter;
-use super::symbol::{Qubit, Bit};
+use super::symbol::{Bit, List, Qubit};
 
 bitflags! {
     /// Flags attached to the compact representation of an
@@ -17,13 +17,14 @@ bitflags! {
     }
 }
 
+#[allow(unpredictable_function_pointer_comparisons)]
 #[derive(Clone, PartialEq, Eq, Debug)]
-pub struct Compute<'id, T> {
-    pub bits: &'id [Bit<'id>],
+pub struct Compute<'a, 'id, T> {
+    pub bits: &'a [Bit<'id>],
     pub func: fn(BitSet) -> T,
 }
 
-impl<'id, T> Compute<'id, T> {
+impl<'a, 'id, T> Compute<'a, 'id, T> {
     /// Writes the compute to the destination.
     #[inline]
     pub(crate) fn write(&self, dest: &mut Vec<u32>) {
@@ -34,7 +35,7 @@ impl<'id, T> Compute<'id, T> {
 
     /// Reads the compute from the destination.
     #[inline]
-    pub(crate) fn read(src: &mut &'id [u32]) -> Self {
+    pub(crate) fn read(src: &mut &'a [u32]) -> Self {
         Self {
             bits: {
                 let len = storage::read(src);
@@ -58,14 +59,14 @@ macro_rules! modifiers {
     } => {
         #[non_exhaustive]
         #[derive(Clone, PartialEq, Eq, Debug)]
-        pub enum Modifier<'id> {
+        pub enum Modifier<'a, 'id> {
             $(
                 $(#[doc$($args)*])*
                 $name $(($payload))?,
             )*
         }
 
-        impl<'id> Modifier<'id> {
+        impl<'a, 'id> Modifier<'a, 'id> {
             /// Writes the modifier to the destination.
             #[inline]
             pub(crate) fn write(&self, dest: &mut Vec<u32>) {
@@ -81,7 +82,7 @@ macro_rules! modifiers {
 
             /// Reads the modifier from the destination.
             #[inline]
-            pub(crate) fn read(src: &mut &'id [u32]) -> Self {
+            pub(crate) fn read(src: &mut &'a [u32]) -> Self {
                 match storage::read::<u32>(src) {
                     $(
                         $int => Self::$name $(($read(src)))?,
@@ -97,56 +98,118 @@ modifiers! {
     /// Only perform the instruction if the bit is `true`.
     IfBit = 0 {
         inner: Bit<'id>,
-        write: |dest| storage::write(dest, inner),
+        write: |dest| storage::write(dest, *inner),
         read: storage::read,
     },
     /// Only perform the instruction if the result of the compute is `true`.
     IfCompute = 1 {
-        inner: Compute<'id, bool>,
+        inner: Compute<'a, 'id, bool>,
         write: |dest| inner.write(dest),
         read: Compute::read,
     },
     /// Perform the instruction while the bit is `true`.
     WhileBit = 2 {
         inner: Bit<'id>,
-        write: |dest| storage::write(dest, inner),
+        write: |dest| storage::write(dest, *inner),
         read: storage::read,
     },
     /// Perform the instruction while the result of the compute is `true`.
     WhileCompute = 3 {
-        inner: Compute<'id, bool>,
+        inner: Compute<'a, 'id, bool>,
         write: |dest| inner.write(dest),
         read: Compute::read,
     },
     /// Perform the instruction as many times as the provided integer.
     ForConst = 4 {
         inner: u32,
-        write: |dest| storage::write(dest, inner),
+        write: |dest| storage::write(dest, *inner),
         read: storage::read,
     },
     /// Perform the instruction as many times as the result of the compute.
     ForCompute = 5 {
-        inner: Compute<'id, u32>,
+        inner: Compute<'a, 'id, u32>,
         write: |dest| inner.write(dest),
         read: Compute::read,
     },
+    /// Perform the operation controlled by the qubits, that is only on the states where
+    /// all of them are $|1\rangle$. The qubits must be distinct from those of the operation.
+    Control = 6 {
+        inner: List<Qubit<'id>>,
+        write: |dest| storage::write(dest, inner.as_range()),
+        read: |src| List::from_range_unchecked(storage::read(src)),
+    },
+    /// Perform the operation controlled by the qubits, that is only on the states where
+    /// all of them are $|0\rangle$. The qubits must be distinct from those of the operation.
+    NegControl = 7 {
+        inner: List<Qubit<'id>>,
+        write: |dest| storage::write(dest, inner.as_range()),
+        read: |src| List::from_range_unchecked(storage::read(src)),
+    },
+    /// Perform the operation raised to the given power. Non-integer powers of rotations scale
+    /// their angle, those of other operations scale their eigenphases in $(-\pi, \pi]$.
+    Pow = 8 {
+        inner: Parameter<'id>,
+        write: |dest| storage::write(dest, *inner),
+        read: storage::read,
+    },
+    /// Perform the inverse of the operation.
+    Inverse = 9,
+}
+
+impl Modifier<'_, '_> {
+    /// Returns true if the modifier changes the operation itself, rather than the way it
+    /// is executed depending on the classical register.
+    #[inline]
+    pub fn is_quantum(&self) -> bool {
+        matches!(self, Self::Control(_) | Self::NegControl(_) | Self::Pow(_) | Self::Inverse)
+    }
+
+    /// Returns the name of the modifier, as the keyword of OpenQASM 3.
+    #[inline]
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::IfBit(_) | Self::IfCompute(_) => "if",
+            Self::WhileBit(_) | Self::WhileCompute(_) => "while",
+            Self::ForConst(_) | Self::ForCompute(_) => "for",
+            Self::Control(_) => "ctrl",
+            Self::NegControl(_) => "negctrl",
+            Self::Pow(_) => "pow",
+            Self::Inverse => "inv",
+        }
+    }
 }
 
 #[derive(Clone, PartialEq, Eq, Default, Debug)]
-pub struct Instr<'id> {
+pub struct Instr<'a, 'id> {
     /// The base operation type.
-    pub op: OpKind<'id>,
+    pub op: OpKind<'a, 'id>,
     /// The qubits to apply this operation to.
-    pub qubits: &'id [Qubit<'id>],
+    pub qubits: &'a [Qubit<'id>],
     /// The bits to apply this operation to.
-    pub bits: &'id [Bit<'id>],
+    pub bits: &'a [Bit<'id>],
     /// The parameters this operation depends on.
-    pub parameters: &'id [Parameter<'id>],
+    pub parameters: &'a [Parameter<'id>],
     /// This operation's modifier, if there is one.
-    pub modifier: Option<Modifier<'id>>,
+    pub modifier: Option<Modifier<'a, 'id>>,
 }
 
-impl<'id> Instr<'id> {
+impl<'a, 'id> Instr<'a, 'id> {
+    /// Returns true if the instruction can be simulated efficiently by a stabilizer
+    /// simulator, see [`OpKind::is_clifford`]. Rotations are Clifford operations when
+    /// their angle is an exact multiple of $\pi/2$, see [`Parameter::is_clifford_angle`].
+    pub fn is_clifford(&self) -> bool {
+        if self.modifier.as_ref().is_some_and(Modifier::is_quantum) {
+            return false;
+        }
+
+        match self.op {
+            OpKind::RX | OpKind::RY | OpKind::RZ | OpKind::Phase | OpKind::RXX | OpKind::RZZ => {
+                self.parameters.iter().all(|parameter| parameter.is_clifford_angle())
+            }
+            ref op => op.is_clifford(),
+        }
+    }
+
     /// Writes the instruction to the destination.
     #[inline]
     pub(crate) fn write(&self, dest: &mut Vec<u32>) {
@@ -175,13 +238,16 @@ impl<'id> Instr<'id> {
 
         write_slices!(qubits, bits, parameters);
 
-        self.modifier.as_ref().map(|modifier| modifier.write(dest));
+        if let Some(modifier) = &self.modifier {
+            modifier.write(dest);
+        }
     }
 
     /// Reads the instruction from the source.
     #[inline]
-    pub(crate) fn read(&mut self, src: &mut &'id [u32]) {
+    pub(crate) fn read(&mut self, src: &mut &'a [u32]) {
         let (op, flags) = OpKind::read(src);
+        self.op = op;
 
         macro_rules! read_slices {
             ( $($name: ident),* ) => {
@@ -198,20 +264,22 @@ impl<'id> Instr<'id> {
     }
 }
 
-pub struct InstrIter<'id> {
-    instr: Instr<'id>,
-    src: &'id [u32],
+/// An iterator over the instructions of a quantum circuit, in order.
+pub struct InstrIter<'a> {
+    instr: Instr<'a, 'a>,
+    src: &'a [u32],
 }
 
-impl<'id> InstrIter<'id> {
+impl<'a> InstrIter<'a> {
     /// Creates a new instruction iterator from the given source.
     #[inline]
-    pub(crate) fn new(src: &mut &'id [u32]) -> Self {
+    pub(crate) fn new(src: &'a [u32]) -> Self {
         Self { instr: Instr::default(), src }
     }
     
     #[inline]
-    pub fn next(&mut self) -> Option<&Instr<'id>> {
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<&Instr<'a, 'a>> {
         // Implementing `Iterator` is impossible because of the struct's internal buffer `self.instr`.
         (!self.src.is_empty()).then(|| {
             self.instr.read(&mut self.src);
@@ -0,0 +1,232 @@
+use super::instruction::{Instr, Modifier};
+use super::operation::OpKind;
+use super::parameter::Parameter;
+use super::symbol::{Qubit, Symbol};
+use super::{QuantumCircuit, QuantumCircuitError};
+
+/// Returns the opposite of a parameter of the circuit, which must not depend on a formal
+/// parameter. Exact angles stay exact.
+#[inline]
+fn negate<'id>(circ: &QuantumCircuit, parameter: Parameter<'id>) -> Result<Parameter<'id>, QuantumCircuitError> {
+    if let Some((numerator, denominator)) = parameter.as_angle() {
+        return Ok(Parameter::pi(-(numerator as i32), denominator));
+    }
+
+    circ.evaluate(parameter, &[])
+        .map(|value| Parameter::from(-value as f32))
+        .map_err(QuantumCircuitError::FormalParameter)
+}
+
+/// Checks that an instruction can be inverted.
+fn check(circ: &QuantumCircuit, instr: &Instr<'_, '_>) -> Result<(), QuantumCircuitError> {
+    match &instr.modifier {
+        None | Some(Modifier::IfBit(_) | Modifier::IfCompute(_)) => (),
+        Some(Modifier::Control(_) | Modifier::NegControl(_)) => (),
+        // The power and inverse modifiers are inverted themselves, not the operation.
+        Some(Modifier::Pow(exponent)) => return negate(circ, *exponent).map(|_| ()),
+        Some(Modifier::Inverse) => return Ok(()),
+        Some(Modifier::WhileBit(_) | Modifier::WhileCompute(_)) => return Err(QuantumCircuitError::NotInvertible("while")),
+        Some(Modifier::ForConst(_) | Modifier::ForCompute(_)) => return Err(QuantumCircuitError::NotInvertible("for")),
+    }
+
+    match &instr.op {
+        OpKind::Nop | OpKind::Barrier => Ok(()),
+        op if op.is_unitary() => instr.parameters.iter().try_for_each(|&p| negate(circ, p).map(|_| ())),
+        op => Err(QuantumCircuitError::NotInvertible(op.label())),
+    }
+}
+
+/// Calls `emit` with the operations, in order, whose product is the adjoint of a unitary
+/// operation, given it's negated parameters. Operations with no adjoint in the instruction
+/// set are decomposed.
+pub(crate) fn adjoint<'a, 'id, Q: Copy, P: Copy>(
+    op: &OpKind<'a, 'id>,
+    q: &[Q],
+    negated: &[P],
+    mut emit: impl FnMut(OpKind<'a, 'id>, &[Q], &[P]),
+) {
+    match op {
+        OpKind::S => emit(OpKind::Sdg, q, &[]),
+        OpKind::Sdg => emit(OpKind::S, q, &[]),
+        OpKind::T => emit(OpKind::Tdg, q, &[]),
+        OpKind::Tdg => emit(OpKind::T, q, &[]),
+        // SX = H S H.
+        OpKind::SX => {
+            emit(OpKind::H, q, &[]);
+            emit(OpKind::Sdg, q, &[]);
+            emit(OpKind::H, q, &[]);
+        }
+        OpKind::U3 => emit(OpKind::U3, q, &[negated[0], negated[2], negated[1]]),
+        // iSWAP = SWAP CZ (S ⊗ S).
+        OpKind::ISwap => {
+            emit(OpKind::Swap, q, &[]);
+            emit(OpKind::CZ, q, &[]);
+            emit(OpKind::Sdg, &q[..1], &[]);
+            emit(OpKind::Sdg, &q[1..], &[]);
+        }
+        op => emit(op.clone(), q, negated),
+    }
+}
+
+/// Writes the adjoint of an instruction, which must have been checked, to the destination.
+fn write_adjoint<'a, 'id>(circ: &QuantumCircuit, instr: &Instr<'a, 'id>, dest: &mut Vec<u32>) {
+    let mut emit = |op, qubits: &[Qubit<'id>], parameters: &[Parameter<'id>], modifier| {
+        let adjoint = Instr { op, qubits, bits: &[], parameters, modifier };
+        adjoint.write(dest);
+    };
+
+    match &instr.modifier {
+        Some(Modifier::Inverse) => emit(instr.op.clone(), instr.qubits, instr.parameters, None),
+        Some(Modifier::Pow(exponent)) => {
+            let modifier = Some(Modifier::Pow(negate(circ, *exponent).unwrap()));
+            emit(instr.op.clone(), instr.qubits, instr.parameters, modifier);
+        }
+        modifier => {
+            let negated: Vec<_> = instr.parameters.iter().map(|&p| negate(circ, p).unwrap()).collect();
+            adjoint(&instr.op, instr.qubits, &negated, |op, q, p| emit(op, q, p, modifier.clone()));
+        }
+    }
+}
+
+impl QuantumCircuit {
+    /// Returns the inverse of the circuit, whose instructions are the adjoints of the
+    /// instructions of the circuit, in reverse order. Rotations get opposite angles,
+    /// powers get opposite exponents, and controlled instructions keep their controls.
+    ///
+    /// Fails if the circuit contains non-unitary operations other than barriers, loops,
+    /// or rotations and powers whose angle or exponent is a formal parameter.
+    pub fn inverse(&self) -> Result<Self, QuantumCircuitError> {
+        // Instructions have variable lengths, so their offsets are collected to be able to
+        // read them in reverse order.
+        let mut offsets = Vec::new();
+        let mut instr = Instr::default();
+        let mut src = self.instrs.as_slice();
+
+        while !src.is_empty() {
+            offsets.push(self.instrs.len() - src.len());
+            instr.read(&mut src);
+            check(self, &instr)?;
+        }
+
+        let mut res = Self {
+            instrs: Vec::with_capacity(self.instrs.len()),
+            ..self.clone()
+        };
+
+        for &offset in offsets.iter().rev() {
+            instr.read(&mut &self.instrs[offset..]);
+            write_adjoint(self, &instr, &mut res.instrs);
+        }
+
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::symbol::FormalParameter;
+    use crate::matrix::Matrix;
+    use crate::sim::equivalent;
+
+    use super::*;
+
+    #[test]
+    fn inverse() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [a, b, c] = circ.alloc_n()?;
+            circ.h(a).x(b).y(c).z(a).s(b).sdg(c).t(a).tdg(b).sx(c);
+            circ.rx(0.3, a).ry(-1.2, b).rz(2.5, c).phase(0.7, a).u3(0.1, 0.2, 0.3, b);
+            circ.cx(a, b).cy(b, c).cz(c, a).ch(a, c).swap(a, b).iswap(b, c);
+            circ.crz(0.4, a, c).cp(-0.9, c, b).rxx(1.1, a, b).rzz(0.6, b, c);
+            circ.ccx(a, b, c).cswap(c, a, b).barrier(&[a, b, c]);
+            Ok(())
+        }).unwrap();
+
+        let inverse = circ.inverse().unwrap();
+        let product = &circ.unitary().unwrap() * &inverse.unitary().unwrap();
+
+        assert!(product.approx_eq(&Matrix::identity(8), 1e-6));
+        assert_eq!(equivalent(&inverse.inverse().unwrap(), &circ, false), Ok(true));
+    }
+
+    #[test]
+    fn exact_angles() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [a, b] = circ.alloc_n()?;
+            circ.rz(Parameter::pi(1, 2), a).rzz(Parameter::pi(3, 2), a, b);
+            Ok(())
+        }).unwrap();
+
+        let inverse = circ.inverse().unwrap();
+        let mut iter = inverse.instructions();
+
+        assert_eq!(iter.next().unwrap().parameters[0], Parameter::pi(-3, 2));
+        assert_eq!(iter.next().unwrap().parameters[0], Parameter::pi(-1, 2));
+        assert!(inverse.is_clifford());
+    }
+
+    #[test]
+    fn conditions() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [a, b] = circ.alloc_n()?;
+            let c = circ.alloc()?;
+            circ.h(a).cx(a, b);
+            circ.push(Instr { op: OpKind::S, qubits: &[b], modifier: Some(Modifier::IfBit(c)), ..Default::default() })?;
+            Ok(())
+        }).unwrap();
+
+        let inverse = circ.inverse().unwrap();
+        let mut iter = inverse.instructions();
+        let first = iter.next().unwrap();
+
+        assert_eq!(first.op, OpKind::Sdg);
+        assert!(matches!(first.modifier, Some(Modifier::IfBit(_))));
+        assert_eq!(iter.next().unwrap().op, OpKind::CX);
+        assert_eq!(iter.next().unwrap().op, OpKind::H);
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn modifiers() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [a, b] = circ.alloc_n()?;
+            let c = circ.alloc_list(1)?;
+            circ.push(Instr { op: OpKind::SX, qubits: &[a], modifier: Some(Modifier::Control(c)), ..Default::default() })?
+                .push(Instr { op: OpKind::H, qubits: &[b], modifier: Some(Modifier::Pow(0.3.into())), ..Default::default() })?
+                .push(Instr { op: OpKind::T, qubits: &[a], modifier: Some(Modifier::Inverse), ..Default::default() })?;
+            Ok(())
+        }).unwrap();
+
+        let inverse = circ.inverse().unwrap();
+        let product = &circ.unitary().unwrap() * &inverse.unitary().unwrap();
+
+        assert!(product.approx_eq_up_to_phase(&Matrix::identity(8), 1e-6));
+
+        let mut iter = inverse.instructions();
+        let first = iter.next().unwrap();
+
+        assert_eq!((&first.op, &first.modifier), (&OpKind::T, &None));
+        assert!(matches!(iter.next().unwrap().modifier, Some(Modifier::Pow(p)) if p.as_value() == Some(-0.3)));
+        assert!(matches!(iter.next().unwrap().modifier, Some(Modifier::Control(_))));
+    }
+
+    #[test]
+    fn errors() {
+        let circ = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            circ.push(Instr { op: OpKind::X, qubits: &[q], modifier: Some(Modifier::ForConst(3)), ..Default::default() })?;
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(circ.inverse().err(), Some(QuantumCircuitError::NotInvertible("for")));
+
+        let circ = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            let theta: FormalParameter = circ.alloc()?;
+            circ.rz(theta, q);
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(circ.inverse().err(), Some(QuantumCircuitError::FormalParameter(0)));
+    }
+}
@@ -1,5 +1,11 @@
+mod bind;
+mod inverse;
 mod storage;
+mod unitary;
 
+pub(crate) mod decompose;
+
+pub mod expression;
 pub mod instruction;
 pub mod operation;
 pub mod parameter;
@@ -11,13 +17,19 @@ use thiserror::Error;
 
 use crate::genericity::Id;
 
+use instruction::{Instr, InstrIter, Modifier};
+use operation::OpKind;
 use symbol::{SymbolTuple, Symbol, List};
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct QuantumCircuit {
     qubit_count: u32,
     bit_count: u32,
     parameter_count: u32,
+    /// The word-encoded instruction stream, see `Instr::write`.
+    instrs: Vec<u32>,
+    /// The expressions referred to by the parameters of the instructions.
+    expressions: Vec<expression::Expr>,
 }
 
 pub struct CircuitBuilder<'id> {
@@ -45,6 +57,33 @@ impl DerefMut for CircuitBuilder<'_> {
 pub enum QuantumCircuitError {
     #[error("quantum allocator overflow")]
     AllocOverflow,
+    #[error("wrong number of {kind} for operation `{op}`")]
+    ArityMismatch {
+        op: &'static str,
+        kind: &'static str,
+    },
+    #[error("operation `{0}` is applied more than once to the same qubit")]
+    DuplicateQubit(&'static str),
+    #[error("symbol was not allocated in this circuit")]
+    UnknownSymbol,
+    #[error("operation `{0}` cannot be inverted")]
+    NotInvertible(&'static str),
+    #[error("formal parameter {0} must have a value")]
+    FormalParameter(u32),
+    #[error("modifier `{modifier}` cannot be applied to operation `{op}`")]
+    InvalidModifier {
+        op: &'static str,
+        modifier: &'static str,
+    },
+    #[error("control qubits must be distinct from the qubits of the operation")]
+    ControlOverlap,
+    #[error("formal parameter {0} was not allocated in this circuit")]
+    UnknownParameter(u32),
+    #[error("expected {expected} values to bind the formal parameters, found {found}")]
+    BindingCount {
+        expected: usize,
+        found: usize,
+    },
 }
 
 impl QuantumCircuit {
@@ -79,6 +118,26 @@ impl QuantumCircuit {
     pub fn bit_count(&self) -> usize {
         self.bit_count as usize
     }
+
+    /// Returns an iterator over the instructions of the circuit, in order.
+    #[inline]
+    pub fn instructions(&self) -> InstrIter<'_> {
+        InstrIter::new(&self.instrs)
+    }
+
+    /// Returns true if all the instructions of the circuit are Clifford operations,
+    /// see [`Instr::is_clifford`].
+    pub fn is_clifford(&self) -> bool {
+        let mut iter = self.instructions();
+
+        while let Some(instr) = iter.next() {
+            if !instr.is_clifford() {
+                return false;
+            }
+        }
+
+        true
+    }
 }
 
 impl<'id> CircuitBuilder<'id> {
@@ -119,23 +178,245 @@ impl<'id> CircuitBuilder<'id> {
     pub fn alloc_tuple<T: SymbolTuple<'id>>(&mut self) -> Result<T, QuantumCircuitError> {
         T::alloc(self)
     }
+
+    /// Appends an instruction at the end of the circuit.
+    /// 
+    /// Fails if the number of qubits, bits or parameters doesn't match the arity of
+    /// the operation, if a unitary operation is applied twice to the same qubit, or
+    /// if a symbol was not allocated by this circuit.
+    pub fn push(&mut self, instr: Instr<'_, 'id>) -> Result<&mut Self, QuantumCircuitError> {
+        macro_rules! check_arity {
+            ( $($name: ident),* ) => {
+                $(
+                    if instr.op.$name().get().map_or(false, |n| n as usize != instr.$name.len()) {
+                        return Err(QuantumCircuitError::ArityMismatch {
+                            op: instr.op.label(),
+                            kind: stringify!($name),
+                        });
+                    }
+                )*
+            };
+        }
+
+        check_arity!(qubits, bits, parameters);
+
+        if matches!(instr.op, OpKind::Measure) && instr.qubits.len() != instr.bits.len() {
+            return Err(QuantumCircuitError::ArityMismatch {
+                op: instr.op.label(),
+                kind: "bits",
+            });
+        }
+
+        if instr.op.is_unitary() && instr.qubits.iter().enumerate().any(|(i, q)| instr.qubits[..i].iter().any(|p| p.id() == q.id())) {
+            return Err(QuantumCircuitError::DuplicateQubit(instr.op.label()));
+        }
+
+        let bits = match &instr.op {
+            OpKind::Compute(compute) => compute.bits,
+            _ => &[],
+        };
+
+        let modifier_bits = match &instr.modifier {
+            Some(Modifier::IfBit(bit) | Modifier::WhileBit(bit)) => std::slice::from_ref(bit),
+            Some(Modifier::IfCompute(compute) | Modifier::WhileCompute(compute)) => compute.bits,
+            Some(Modifier::ForCompute(compute)) => compute.bits,
+            _ => &[],
+        };
+
+        let (controls, exponent) = match &instr.modifier {
+            Some(Modifier::Control(controls) | Modifier::NegControl(controls)) => (controls.as_range(), None),
+            Some(Modifier::Pow(exponent)) => (0..0, Some(*exponent)),
+            _ => (0..0, None),
+        };
+
+        if let Some(modifier) = instr.modifier.as_ref().filter(|m| m.is_quantum() && !instr.op.is_unitary()) {
+            return Err(QuantumCircuitError::InvalidModifier {
+                op: instr.op.label(),
+                modifier: modifier.label(),
+            });
+        }
+
+        if instr.qubits.iter().any(|q| controls.contains(&q.id())) {
+            return Err(QuantumCircuitError::ControlOverlap);
+        }
+
+        let known = instr.qubits.iter().all(|q| q.id() < self.qubit_count)
+            && controls.end <= self.qubit_count
+            && instr.bits.iter().chain(bits).chain(modifier_bits).all(|b| b.id() < self.bit_count)
+            && instr.parameters.iter().chain(&exponent).filter_map(|p| p.as_formal()).all(|p| p.id() < self.parameter_count)
+            && instr.parameters.iter().chain(&exponent).filter_map(|p| p.as_expression()).all(|i| (i as usize) < self.expressions.len());
+
+        if !known {
+            return Err(QuantumCircuitError::UnknownSymbol);
+        }
+
+        instr.write(&mut self.circ.instrs);
+        Ok(self)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::instruction::Compute;
+
+    #[test]
+    fn push_and_read() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+
+            circ.push(Instr { op: OpKind::H, qubits: &[q1], ..Default::default() })?
+                .push(Instr { op: OpKind::H, qubits: &[q2], ..Default::default() })?;
+
+            Ok(())
+        }).unwrap();
+
+        let mut iter = circ.instructions();
+        let mut count = 0;
+
+        while let Some(instr) = iter.next() {
+            assert_eq!(instr.op, OpKind::H);
+            assert_eq!(instr.qubits.len(), 1);
+            assert_eq!(instr.qubits[0].id(), count);
+            assert!(instr.modifier.is_none());
+            count += 1;
+        }
+
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn push_arity_mismatch() {
+        let res = QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+            circ.push(Instr { op: OpKind::H, qubits: &[q1, q2], ..Default::default() })?;
+            Ok(())
+        });
+
+        assert!(matches!(res, Err(QuantumCircuitError::ArityMismatch { .. })));
+    }
 
-    // #[test]
-    // fn bell() {
-    //     let circ = QuantumCircuit::new(|circ| {
-    //         let [q1, q2] = circ.alloc_n()?;
+    #[test]
+    fn push_duplicate_qubit() {
+        let res = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            circ.push(Instr { op: OpKind::CX, qubits: &[q, q], ..Default::default() })?;
+            Ok(())
+        });
 
-    //         circ.h(q1)
-    //             .cx(q1, q2);
+        assert_eq!(res.err(), Some(QuantumCircuitError::DuplicateQubit("cx")));
+    }
+
+    #[test]
+    fn bell() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+
+            circ.h(q1)
+                .cx(q1, q2);
+
+            Ok(())
+        });
+
+        assert!(circ.is_ok());
+    }
+
+    #[test]
+    fn measure() {
+        let res = QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+            let [b1, b2] = circ.alloc_n()?;
+
+            circ.h(q1)
+                .cx(q1, q2)
+                .barrier(&[q1, q2])
+                .measure(&[q1, q2], &[b1, b2])
+                .reset(q1);
+
+            Ok(())
+        });
+
+        assert!(res.is_ok());
+
+        let res = QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+            let b = circ.alloc()?;
+            circ.push(Instr { op: OpKind::Measure, qubits: &[q1, q2], bits: &[b], ..Default::default() })?;
+            Ok(())
+        });
+
+        assert!(matches!(res, Err(QuantumCircuitError::ArityMismatch { .. })));
+    }
+
+    #[test]
+    fn fluent() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+            let [b1, b2] = circ.alloc_n()?;
+
+            circ.h(q1)
+                .nop()
+                .h(q2)
+                .compute(&[b2], Compute { bits: &[b1], func: |bits| bits });
+
+            Ok(())
+        }).unwrap();
 
-    //         Ok(())
-    //     });
+        let mut iter = circ.instructions();
+        let mut labels = Vec::new();
 
-    //     assert!(circ.is_ok());
-    // }
-}
\ No newline at end of file
+        while let Some(instr) = iter.next() {
+            labels.push(instr.op.label());
+        }
+
+        assert_eq!(labels, ["h", "nop", "h", "compute"]);
+    }
+
+    #[test]
+    fn quantum_modifiers() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [q1, q2, q3] = circ.alloc_n()?;
+            let controls = circ.alloc_list(2)?;
+
+            circ.push(Instr { op: OpKind::RX, qubits: &[q1], parameters: &[0.5.into()], modifier: Some(Modifier::Control(controls)), ..Default::default() })?
+                .push(Instr { op: OpKind::Swap, qubits: &[q2, q3], modifier: Some(Modifier::Pow(0.25.into())), ..Default::default() })?
+                .push(Instr { op: OpKind::T, qubits: &[q1], modifier: Some(Modifier::Inverse), ..Default::default() })?;
+
+            Ok(())
+        }).unwrap();
+
+        let mut iter = circ.instructions();
+        let instr = iter.next().unwrap();
+
+        assert!(matches!(&instr.modifier, Some(Modifier::Control(list)) if list.as_range() == (3..5)));
+        assert_eq!(instr.parameters[0].as_value(), Some(0.5));
+        assert!(matches!(&iter.next().unwrap().modifier, Some(Modifier::Pow(p)) if p.as_value() == Some(0.25)));
+        assert_eq!(iter.next().unwrap().modifier, Some(Modifier::Inverse));
+        assert!(iter.next().is_none());
+
+        let res = QuantumCircuit::new(|circ| {
+            let qubits = circ.alloc_list(2)?;
+            circ.push(Instr { op: OpKind::X, qubits: &[qubits.get(1).unwrap()], modifier: Some(Modifier::Control(qubits)), ..Default::default() })?;
+            Ok(())
+        });
+
+        assert_eq!(res.err(), Some(QuantumCircuitError::ControlOverlap));
+
+        let res = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            let b = circ.alloc()?;
+            circ.push(Instr { op: OpKind::Measure, qubits: &[q], bits: &[b], modifier: Some(Modifier::Inverse), ..Default::default() })?;
+            Ok(())
+        });
+
+        assert_eq!(res.err(), Some(QuantumCircuitError::InvalidModifier { op: "measure", modifier: "inv" }));
+
+        let res = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            circ.push(Instr { op: OpKind::X, qubits: &[q], modifier: Some(Modifier::Control(List::from_range_unchecked(1..3))), ..Default::default() })?;
+            Ok(())
+        });
+
+        assert_eq!(res.err(), Some(QuantumCircuitError::UnknownSymbol));
+    }
+}
@@ -1,7 +1,12 @@
 use crate::bitset::BitSet;
+use crate::matrix::Matrix;
 
-use super::instruction::{Compute, InstrFlags};
+use super::CircuitBuilder;
+use super::instruction::{Compute, Instr, InstrFlags};
+use super::parameter::Parameter;
 use super::storage;
+use super::symbol::{Bit, Qubit};
+use super::unitary;
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
 pub struct Arity(u32);
@@ -29,7 +34,7 @@ impl Arity {
 
     #[inline]
     pub fn get(self) -> Option<u32> {
-        self.is_definite().then(|| self.0)
+        self.is_definite().then_some(self.0)
     }
 }
 
@@ -40,6 +45,24 @@ impl From<u32> for Arity {
     }
 }
 
+/// Returns the type of an argument of a builder method, given it's category.
+macro_rules! arg_type {
+    (Qubit) => { Qubit<'id> };
+    (Bit) => { Bit<'id> };
+    (Parameter) => { impl Into<Parameter<'id>> };
+    ([Qubit]) => { &[Qubit<'id>] };
+    ([Bit]) => { &[Bit<'id>] };
+}
+
+/// Pushes an argument of a builder method to the right buffer, given it's category.
+macro_rules! arg_push {
+    (Qubit, $arg: ident, $qubits: ident, $bits: ident, $parameters: ident) => { $qubits.push($arg) };
+    (Bit, $arg: ident, $qubits: ident, $bits: ident, $parameters: ident) => { $bits.push($arg) };
+    (Parameter, $arg: ident, $qubits: ident, $bits: ident, $parameters: ident) => { $parameters.push($arg.into()) };
+    ([Qubit], $arg: ident, $qubits: ident, $bits: ident, $parameters: ident) => { $qubits.extend_from_slice($arg) };
+    ([Bit], $arg: ident, $qubits: ident, $bits: ident, $parameters: ident) => { $bits.extend_from_slice($arg) };
+}
+
 macro_rules! operations {
     {
         $(
@@ -49,7 +72,10 @@ macro_rules! operations {
                 bits: $bits: expr,
                 parameters: $parameters: expr,
                 unitary: $unitary: literal,
+                clifford: $clifford: literal,
                 label: $label: literal,
+                method: $method: ident($($arg: ident: $cat: tt),*),
+                $(matrix: $matrix: expr,)?
                 $(
                     payload: {
                         $inner: ident: $payload: ty,
@@ -62,7 +88,7 @@ macro_rules! operations {
     } => {
         #[non_exhaustive]
         #[derive(Clone, PartialEq, Eq, Default, Debug)]
-        pub enum OpKind<'id> {
+        pub enum OpKind<'a, 'id> {
             #[default]
             $(
                 $(#[doc$($args)*])*
@@ -70,7 +96,7 @@ macro_rules! operations {
             )*
         }
 
-        impl<'id> OpKind<'id> {
+        impl<'a, 'id> OpKind<'a, 'id> {
             /// Writes the operation kind along with the given flags to the destination.
             #[inline]
             pub(crate) fn write(&self, dest: &mut Vec<u32>, flags: InstrFlags) {
@@ -86,7 +112,7 @@ macro_rules! operations {
 
             /// Reads the operation kind along with it's associated flags from the destination.
             #[inline]
-            pub(crate) fn read(src: &mut &'id [u32]) -> (Self, InstrFlags) {
+            pub(crate) fn read(src: &mut &'a [u32]) -> (Self, InstrFlags) {
                 let (flags, id): (InstrFlags, u16) = storage::read(src);
 
                 let op = match id {
@@ -131,6 +157,17 @@ macro_rules! operations {
                 }
             }
 
+            /// Returns true if the operation can be simulated efficiently by a stabilizer
+            /// simulator. That is the case of Clifford gates, as well as measurements, resets
+            /// and purely classical operations.
+            #[inline]
+            #[allow(unused_variables)]
+            pub fn is_clifford(&self) -> bool {
+                match self {
+                    $(Self::$name $(($inner))? => $clifford,)*
+                }
+            }
+
             #[inline]
             #[allow(unused_variables)]
             pub fn label(&self) -> &'static str {
@@ -138,6 +175,67 @@ macro_rules! operations {
                     $(Self::$name $(($inner))? => $label,)*
                 }
             }
+
+            /// Returns the matrix of the operation, given the values of it's parameters.
+            /// Returns `None` if the operation is not unitary, or if the number of parameters
+            /// does not match the arity of the operation.
+            /// 
+            /// The first qubit of the operation is the least significant bit of the
+            /// matrix indices.
+            #[inline]
+            #[allow(unused_variables)]
+            pub fn matrix(&self, parameters: &[f64]) -> Option<Matrix> {
+                if self.parameters().get() != Some(parameters.len() as u32) {
+                    return None;
+                }
+
+                match self {
+                    $(
+                        Self::$name $(($inner))? => {
+                            $(
+                                let matrix: fn(&[f64]) -> Matrix = $matrix;
+                                return Some(matrix(parameters));
+                            )?
+                        }
+                    )*
+                }
+
+                None
+            }
+        }
+
+        impl<'id> CircuitBuilder<'id> {
+            $(
+                $(#[doc$($args)*])*
+                /// 
+                /// # Panics
+                /// 
+                /// Panics if the arguments do not match the arity of the operation,
+                /// if a unitary operation is applied twice to the same qubit, or if a
+                /// symbol was not allocated by this circuit.
+                #[inline]
+                #[allow(clippy::extra_unused_lifetimes, clippy::vec_init_then_push)]
+                pub fn $method<'a>(&mut self, $($arg: arg_type!($cat),)* $($inner: $payload)?) -> &mut Self {
+                    let mut qubits = Vec::new();
+                    let mut bits = Vec::new();
+                    let mut parameters = Vec::new();
+
+                    $(arg_push!($cat, $arg, qubits, bits, parameters);)*
+
+                    let instr = Instr {
+                        op: OpKind::$name $(($inner))?,
+                        qubits: &qubits,
+                        bits: &bits,
+                        parameters: &parameters,
+                        modifier: None,
+                    };
+
+                    match self.push(instr) {
+                        Ok(builder) => builder,
+                        Err(err) => panic!("{}", err),
+                    }
+                }
+            )*
         }
     }
 }
@@ -149,7 +247,9 @@ operations! {
         bits: 0,
         parameters: 0,
         unitary: false,
+        clifford: true,
         label: "nop",
+        method: nop(),
     },
     /// Hadamard transform.
     H = 1 {
@@ -157,7 +257,316 @@ operations! {
         bits: 0,
         parameters: 0,
         unitary: true,
+        clifford: true,
         label: "h",
+        method: h(target: Qubit),
+        matrix: |_| unitary::h(),
+    },
+    /// Pauli X gate, the quantum equivalent of the NOT gate.
+    X = 2 {
+        qubits: 1,
+        bits: 0,
+        parameters: 0,
+        unitary: true,
+        clifford: true,
+        label: "x",
+        method: x(target: Qubit),
+        matrix: |_| unitary::x(),
+    },
+    /// Pauli Y gate.
+    Y = 3 {
+        qubits: 1,
+        bits: 0,
+        parameters: 0,
+        unitary: true,
+        clifford: true,
+        label: "y",
+        method: y(target: Qubit),
+        matrix: |_| unitary::y(),
+    },
+    /// Pauli Z gate.
+    Z = 4 {
+        qubits: 1,
+        bits: 0,
+        parameters: 0,
+        unitary: true,
+        clifford: true,
+        label: "z",
+        method: z(target: Qubit),
+        matrix: |_| unitary::z(),
+    },
+    /// S gate, square root of Z.
+    S = 5 {
+        qubits: 1,
+        bits: 0,
+        parameters: 0,
+        unitary: true,
+        clifford: true,
+        label: "s",
+        method: s(target: Qubit),
+        matrix: |_| unitary::s(),
+    },
+    /// Adjoint of the S gate.
+    Sdg = 6 {
+        qubits: 1,
+        bits: 0,
+        parameters: 0,
+        unitary: true,
+        clifford: true,
+        label: "sdg",
+        method: sdg(target: Qubit),
+        matrix: |_| unitary::sdg(),
+    },
+    /// T gate, fourth root of Z.
+    T = 7 {
+        qubits: 1,
+        bits: 0,
+        parameters: 0,
+        unitary: true,
+        clifford: false,
+        label: "t",
+        method: t(target: Qubit),
+        matrix: |_| unitary::t(),
+    },
+    /// Adjoint of the T gate.
+    Tdg = 8 {
+        qubits: 1,
+        bits: 0,
+        parameters: 0,
+        unitary: true,
+        clifford: false,
+        label: "tdg",
+        method: tdg(target: Qubit),
+        matrix: |_| unitary::tdg(),
+    },
+    /// Square root of X.
+    SX = 9 {
+        qubits: 1,
+        bits: 0,
+        parameters: 0,
+        unitary: true,
+        clifford: true,
+        label: "sx",
+        method: sx(target: Qubit),
+        matrix: |_| unitary::sx(),
+    },
+    /// Rotation of angle θ around the X axis.
+    RX = 10 {
+        qubits: 1,
+        bits: 0,
+        parameters: 1,
+        unitary: true,
+        clifford: false,
+        label: "rx",
+        method: rx(theta: Parameter, target: Qubit),
+        matrix: |p| unitary::rx(p[0]),
+    },
+    /// Rotation of angle θ around the Y axis.
+    RY = 11 {
+        qubits: 1,
+        bits: 0,
+        parameters: 1,
+        unitary: true,
+        clifford: false,
+        label: "ry",
+        method: ry(theta: Parameter, target: Qubit),
+        matrix: |p| unitary::ry(p[0]),
+    },
+    /// Rotation of angle θ around the Z axis.
+    RZ = 12 {
+        qubits: 1,
+        bits: 0,
+        parameters: 1,
+        unitary: true,
+        clifford: false,
+        label: "rz",
+        method: rz(theta: Parameter, target: Qubit),
+        matrix: |p| unitary::rz(p[0]),
+    },
+    /// Phase gate, applies a phase of λ to the $|1\rangle$ state.
+    Phase = 13 {
+        qubits: 1,
+        bits: 0,
+        parameters: 1,
+        unitary: true,
+        clifford: false,
+        label: "p",
+        method: phase(lambda: Parameter, target: Qubit),
+        matrix: |p| unitary::phase(p[0]),
+    },
+    /// Generic single-qubit rotation, with the three euler angles θ, φ and λ.
+    U3 = 14 {
+        qubits: 1,
+        bits: 0,
+        parameters: 3,
+        unitary: true,
+        clifford: false,
+        label: "u3",
+        method: u3(theta: Parameter, phi: Parameter, lambda: Parameter, target: Qubit),
+        matrix: |p| unitary::u3(p[0], p[1], p[2]),
+    },
+    /// Controlled X gate, also known as CNOT.
+    CX = 20 {
+        qubits: 2,
+        bits: 0,
+        parameters: 0,
+        unitary: true,
+        clifford: true,
+        label: "cx",
+        method: cx(control: Qubit, target: Qubit),
+        matrix: |_| unitary::x().controlled(1),
+    },
+    /// Controlled Y gate.
+    CY = 21 {
+        qubits: 2,
+        bits: 0,
+        parameters: 0,
+        unitary: true,
+        clifford: true,
+        label: "cy",
+        method: cy(control: Qubit, target: Qubit),
+        matrix: |_| unitary::y().controlled(1),
+    },
+    /// Controlled Z gate.
+    CZ = 22 {
+        qubits: 2,
+        bits: 0,
+        parameters: 0,
+        unitary: true,
+        clifford: true,
+        label: "cz",
+        method: cz(control: Qubit, target: Qubit),
+        matrix: |_| unitary::z().controlled(1),
+    },
+    /// Controlled Hadamard gate.
+    CH = 23 {
+        qubits: 2,
+        bits: 0,
+        parameters: 0,
+        unitary: true,
+        clifford: false,
+        label: "ch",
+        method: ch(control: Qubit, target: Qubit),
+        matrix: |_| unitary::h().controlled(1),
+    },
+    /// Swaps the states of two qubits.
+    Swap = 24 {
+        qubits: 2,
+        bits: 0,
+        parameters: 0,
+        unitary: true,
+        clifford: true,
+        label: "swap",
+        method: swap(a: Qubit, b: Qubit),
+        matrix: |_| unitary::swap(),
+    },
+    /// Swaps the states of two qubits, applying a phase of $i$ to the $|01\rangle$ and $|10\rangle$ states.
+    ISwap = 25 {
+        qubits: 2,
+        bits: 0,
+        parameters: 0,
+        unitary: true,
+        clifford: true,
+        label: "iswap",
+        method: iswap(a: Qubit, b: Qubit),
+        matrix: |_| unitary::iswap(),
+    },
+    /// Controlled rotation around the Z axis.
+    CRZ = 26 {
+        qubits: 2,
+        bits: 0,
+        parameters: 1,
+        unitary: true,
+        clifford: false,
+        label: "crz",
+        method: crz(lambda: Parameter, control: Qubit, target: Qubit),
+        matrix: |p| unitary::rz(p[0]).controlled(1),
+    },
+    /// Controlled phase gate.
+    CP = 27 {
+        qubits: 2,
+        bits: 0,
+        parameters: 1,
+        unitary: true,
+        clifford: false,
+        label: "cp",
+        method: cp(lambda: Parameter, control: Qubit, target: Qubit),
+        matrix: |p| unitary::phase(p[0]).controlled(1),
+    },
+    /// Two-qubit XX rotation, $\exp(-i \frac{\theta}{2} X \otimes X)$.
+    RXX = 28 {
+        qubits: 2,
+        bits: 0,
+        parameters: 1,
+        unitary: true,
+        clifford: false,
+        label: "rxx",
+        method: rxx(theta: Parameter, a: Qubit, b: Qubit),
+        matrix: |p| unitary::rxx(p[0]),
+    },
+    /// Two-qubit ZZ rotation, $\exp(-i \frac{\theta}{2} Z \otimes Z)$.
+    RZZ = 29 {
+        qubits: 2,
+        bits: 0,
+        parameters: 1,
+        unitary: true,
+        clifford: false,
+        label: "rzz",
+        method: rzz(theta: Parameter, a: Qubit, b: Qubit),
+        matrix: |p| unitary::rzz(p[0]),
+    },
+    /// Doubly controlled X gate, also known as the Toffoli gate.
+    CCX = 30 {
+        qubits: 3,
+        bits: 0,
+        parameters: 0,
+        unitary: true,
+        clifford: false,
+        label: "ccx",
+        method: ccx(control1: Qubit, control2: Qubit, target: Qubit),
+        matrix: |_| unitary::x().controlled(2),
+    },
+    /// Controlled swap gate, also known as the Fredkin gate.
+    CSwap = 31 {
+        qubits: 3,
+        bits: 0,
+        parameters: 0,
+        unitary: true,
+        clifford: false,
+        label: "cswap",
+        method: cswap(control: Qubit, a: Qubit, b: Qubit),
+        matrix: |_| unitary::swap().controlled(1),
+    },
+    /// Measures each qubit in the computational basis, and stores the outcome
+    /// in the bit of the same index. Takes as many bits as qubits.
+    Measure = 50 {
+        qubits: Arity::variadic(),
+        bits: Arity::variadic(),
+        parameters: 0,
+        unitary: false,
+        clifford: true,
+        label: "measure",
+        method: measure(qubits: [Qubit], bits: [Bit]),
+    },
+    /// Resets the qubit to the $|0\rangle$ state.
+    Reset = 51 {
+        qubits: 1,
+        bits: 0,
+        parameters: 0,
+        unitary: false,
+        clifford: true,
+        label: "reset",
+        method: reset(target: Qubit),
+    },
+    /// Prevents optimizations from reordering instructions across it, on the given qubits.
+    Barrier = 52 {
+        qubits: Arity::variadic(),
+        bits: 0,
+        parameters: 0,
+        unitary: false,
+        clifford: true,
+        label: "barrier",
+        method: barrier(qubits: [Qubit]),
     },
     /// Compute node, performs an arbitrary classical compute on bits,
     /// as defined by a custom function.
@@ -166,11 +575,41 @@ operations! {
         bits: Arity::variadic(),
         parameters: 0,
         unitary: false,
+        clifford: true,
         label: "compute",
+        method: compute(bits: [Bit]),
         payload: {
-            inner: Compute<'id, BitSet>,
-            write: |dest| inner.write(dest),
+            compute: Compute<'a, 'id, BitSet>,
+            write: |dest| compute.write(dest),
             read: Compute::read,
         },
     },
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matrices() {
+        let ops = [
+            OpKind::H, OpKind::X, OpKind::Y, OpKind::Z, OpKind::S, OpKind::Sdg, OpKind::T,
+            OpKind::Tdg, OpKind::SX, OpKind::RX, OpKind::RY, OpKind::RZ, OpKind::Phase,
+            OpKind::U3, OpKind::CX, OpKind::CY, OpKind::CZ, OpKind::CH, OpKind::Swap,
+            OpKind::ISwap, OpKind::CRZ, OpKind::CP, OpKind::RXX, OpKind::RZZ, OpKind::CCX,
+            OpKind::CSwap,
+        ];
+
+        for op in ops {
+            assert!(op.is_unitary());
+
+            let parameters = vec![0.7; op.parameters().get().unwrap() as usize];
+            let matrix = op.matrix(&parameters).unwrap();
+
+            assert_eq!(matrix.dim(), 1 << op.qubits().get().unwrap());
+            assert!(matrix.is_unitary(1e-12), "{} is not unitary", op.label());
+        }
+
+        assert!(OpKind::Measure.matrix(&[]).is_none());
+        assert!(OpKind::RX.matrix(&[]).is_none());
+    }
+}
@@ -1,52 +1,184 @@
+use std::f64::consts::PI;
+
 use crate::genericity::Id;
 
 use super::symbol::{FormalParameter, Symbol};
 use super::symbol::private::SymbolPrivate;
 
+/// A parameter of an operation: a float value, an exact angle, a formal parameter or an
+/// expression of formal parameters.
+///
+/// Exact angles are rational multiples of $\pi$, canonicalised modulo $4\pi$, so that two
+/// parameters are equal if and only if their bits are equal. Rotations have a period of
+/// $4\pi$, even when controlled, so exact angles can be used for any rotation, but not as
+/// the exponents of powers.
 #[repr(transparent)]
-#[derive(Clone, Copy, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct Parameter<'id> {
     _id: Id<'id>,
     bits: u32,
 }
 
 impl<'id> Parameter<'id> {
-    /// The precision with which to compare two parameters. Quantum computers
-    /// can't reach this level of precision nowadays.
+    /// The precision with which to compare two parameters, see [`Parameter::approx_eq`].
+    /// Quantum computers can't reach this level of precision nowadays.
     pub const PRECISION: f32 = 1e-4;
 
+    /// The largest denominator of an exact angle.
+    pub const MAX_DENOMINATOR: u32 = (1 << Self::DENOMINATOR_BITS) - 1;
+
+    /// The bits holding the id of formal parameters and the index of expressions.
+    const MANTISSA_MASK: u32 = (1 << (f32::MANTISSA_DIGITS - 1)) - 1;
+
     /// Returns a new `Parameter` from it's bits.
     #[inline]
     fn new(bits: u32) -> Self {
         Self { bits, _id: Id::default() }
     }
 
+    /// The maximum number of expressions a circuit may hold. Expressions are encoded
+    /// like formal parameters, with the sign bit set.
+    pub const MAX_EXPRESSIONS: u32 = 1 << (f32::MANTISSA_DIGITS - 2);
+
+    /// The bits shared by all expressions, that of negative infinity.
+    const EXPRESSION_BITS: u32 = 0xff80_0000;
+
+    /// The bits shared by all exact angles, that of an expression with the highest bit
+    /// of the mantissa set. The numerator is stored above the denominator.
+    const ANGLE_BITS: u32 = 0xffc0_0000;
+
+    /// The number of bits holding the denominator of exact angles.
+    const DENOMINATOR_BITS: u32 = 10;
+
+    /// Returns the parameter referring to the expression at the given index of the
+    /// expression table of a circuit.
+    #[inline]
+    pub(crate) fn expression(index: u32) -> Self {
+        Self::new(Self::EXPRESSION_BITS | index)
+    }
+
+    /// Returns the exact angle $\frac{n}{d} \pi$, modulo $4\pi$. Falls back to a float
+    /// if the reduced denominator is greater than [`Parameter::MAX_DENOMINATOR`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the denominator is zero.
+    pub fn pi(numerator: i32, denominator: u32) -> Self {
+        assert_ne!(denominator, 0, "the denominator of an angle must not be zero");
+
+        let (mut a, mut b) = (numerator.unsigned_abs(), denominator);
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+
+        let denominator = denominator / a;
+        let numerator = (numerator as i64 / a as i64).rem_euclid(4 * denominator as i64) as u32;
+
+        if denominator > Self::MAX_DENOMINATOR {
+            return Self::from((numerator as f64 / denominator as f64 * PI) as f32);
+        }
+
+        Self::new(Self::ANGLE_BITS | numerator << Self::DENOMINATOR_BITS | denominator)
+    }
+
+    /// Returns true if the parameter has a value, that is if it is a float or an exact angle.
     #[inline]
     pub fn is_value(self) -> bool {
-        f32::from_bits(self.bits).is_finite()
+        f32::from_bits(self.bits).is_finite() || self.is_angle()
     }
 
     #[inline]
     pub fn is_formal(self) -> bool {
-        !self.is_value()
+        !self.is_value() && !self.is_expression()
+    }
+
+    /// Returns true if the parameter refers to an expression, see [`Expr`](super::expression::Expr).
+    #[inline]
+    pub fn is_expression(self) -> bool {
+        self.bits & Self::ANGLE_BITS == Self::EXPRESSION_BITS
+    }
+
+    /// Returns true if the parameter is an exact angle, see [`Parameter::pi`].
+    #[inline]
+    pub fn is_angle(self) -> bool {
+        self.bits & Self::ANGLE_BITS == Self::ANGLE_BITS
     }
 
+    /// Returns true if the parameter is an exact multiple of $\pi/2$. Floats are never
+    /// considered to be Clifford angles, however close they are.
+    #[inline]
+    pub fn is_clifford_angle(self) -> bool {
+        self.as_angle().is_some_and(|(_, denominator)| denominator <= 2)
+    }
+
+    /// Returns true if the parameter is an exact odd multiple of $\pi/4$.
+    #[inline]
+    pub fn is_t_angle(self) -> bool {
+        self.as_angle().is_some_and(|(_, denominator)| denominator == 4)
+    }
+
+    /// Returns the value of the parameter, rounded to the nearest float for exact angles.
     #[inline]
     pub fn as_value(self) -> Option<f32> {
-        self.is_value().then(|| f32::from_bits(self.bits))
+        self.as_f64().map(|value| value as f32)
+    }
+
+    /// Returns the value of the parameter, with double precision for exact angles.
+    #[inline]
+    pub fn as_f64(self) -> Option<f64> {
+        match self.as_angle() {
+            Some((numerator, denominator)) => Some(numerator as f64 / denominator as f64 * PI),
+            None => self.is_value().then(|| f32::from_bits(self.bits) as f64),
+        }
+    }
+
+    /// Returns the numerator and denominator of an exact angle, as a reduced fraction
+    /// of $\pi$ in $[0, 4)$.
+    #[inline]
+    pub fn as_angle(self) -> Option<(u32, u32)> {
+        let payload = self.bits & !Self::ANGLE_BITS;
+        self.is_angle().then_some((payload >> Self::DENOMINATOR_BITS, payload & Self::MAX_DENOMINATOR))
     }
 
     #[inline]
     pub fn as_formal(self) -> Option<FormalParameter<'id>> {
-        const MANTISSA_MASK: u32 = (1 << f32::MANTISSA_DIGITS) - 1;
-        self.is_formal().then(|| FormalParameter::new(self.bits & MANTISSA_MASK))
+        self.is_formal().then(|| FormalParameter::new(self.bits & Self::MANTISSA_MASK))
+    }
+
+    /// Returns the index of the expression the parameter refers to in the expression
+    /// table of it's circuit.
+    #[inline]
+    pub(crate) fn as_expression(self) -> Option<u32> {
+        self.is_expression().then_some(self.bits & Self::MANTISSA_MASK)
+    }
+
+    /// Writes an exact angle as `n*pi/d`, using the given name for $\pi$.
+    pub(crate) fn angle_string(self, pi: &str) -> Option<String> {
+        self.as_angle().map(|angle| match angle {
+            (0, _) => "0".to_owned(),
+            (1, 1) => pi.to_owned(),
+            (numerator, 1) => format!("{}*{}", numerator, pi),
+            (1, denominator) => format!("{}/{}", pi, denominator),
+            (numerator, denominator) => format!("{}*{}/{}", numerator, pi, denominator),
+        })
+    }
+
+    /// Returns true if both parameters are values closer than [`Parameter::PRECISION`],
+    /// or if they are equal.
+    #[inline]
+    pub fn approx_eq(self, rhs: Self) -> bool {
+        match (self.as_value(), rhs.as_value()) {
+            (Some(x), Some(y)) => (x - y).abs() < Self::PRECISION,
+            _ => self == rhs,
+        }
     }
 }
 
 impl<'id> From<f32> for Parameter<'id> {
     #[inline]
     fn from(mut value: f32) -> Self {
-        if !value.is_finite() {
+        // Both zeros share the same bits, so that they are equal.
+        if !value.is_finite() || value == 0.0 {
             value = 0.0;
         }
 
@@ -57,17 +189,54 @@ impl<'id> From<f32> for Parameter<'id> {
 impl<'id> From<FormalParameter<'id>> for Parameter<'id> {
     #[inline]
     fn from(formal: FormalParameter<'id>) -> Self {
-        Self::new(u32::from(formal.id()) | f32::INFINITY.to_bits())
+        Self::new(formal.id() | f32::INFINITY.to_bits())
     }
 }
 
-impl PartialEq for Parameter<'_> {
-    #[inline]
-    fn eq(&self, rhs: &Self) -> bool {
-        match (self.as_value(), rhs.as_value()) {
-            (Some(x), Some(y)) => (x - y).abs() < Self::PRECISION,
-            (None, None) => self.bits == rhs.bits,
-            _ => false,
-        }
+#[cfg(test)]
+mod tests {
+    use crate::circuit::QuantumCircuit;
+    use crate::circuit::symbol::Qubit;
+
+    use super::*;
+
+    #[test]
+    fn angles() {
+        assert_eq!(Parameter::pi(-1, 2), Parameter::pi(7, 2));
+        assert_eq!(Parameter::pi(18, 4), Parameter::pi(1, 2));
+        assert_ne!(Parameter::pi(3, 1), Parameter::pi(1, 1));
+        assert_eq!(Parameter::pi(8, 2).as_angle(), Some((0, 1)));
+        assert_eq!(Parameter::pi(3, 1).as_angle(), Some((3, 1)));
+        assert_eq!(Parameter::pi(i32::MIN, 1).as_angle(), Some((0, 1)));
+        assert_eq!(Parameter::pi(7, 4).as_angle(), Some((7, 4)));
+        assert_eq!(Parameter::pi(4091, 1023).as_angle(), Some((4091, 1023)));
+        assert_eq!(Parameter::pi(1, 2).as_f64(), Some(PI / 2.0));
+
+        let fallback = Parameter::pi(1, 1024);
+        assert!(!fallback.is_angle() && fallback.approx_eq(Parameter::from((PI / 1024.0) as f32)));
+
+        assert!(Parameter::pi(3, 2).is_clifford_angle() && Parameter::pi(5, 1).is_clifford_angle());
+        assert!(!Parameter::pi(1, 4).is_clifford_angle() && Parameter::pi(-1, 4).is_t_angle());
+        assert!(!Parameter::from(std::f32::consts::FRAC_PI_2).is_clifford_angle());
+
+        let angle = Parameter::pi(1, 3);
+        assert!(angle.is_value() && !angle.is_formal() && !angle.is_expression());
+        assert!(angle.approx_eq(Parameter::from(std::f32::consts::FRAC_PI_3)));
+        assert_ne!(angle, Parameter::from(std::f32::consts::FRAC_PI_3));
+        assert_eq!(angle.angle_string("pi").unwrap(), "pi/3");
+        assert_eq!(Parameter::pi(-2, 3).angle_string("PI").unwrap(), "10*PI/3");
+        assert!(Parameter::expression(Parameter::MAX_EXPRESSIONS - 1).is_expression());
+
+        // Controlled rotations have a period of 4 pi, so 3 pi must not be reduced to pi.
+        let undo = |angle: f32| QuantumCircuit::new(|circ| {
+            let [a, b] = circ.alloc_n()?;
+            circ.crz(Parameter::pi(3, 1), a, b).crz(-angle, a, b);
+            Ok(())
+        }).unwrap();
+
+        let identity = QuantumCircuit::new(|circ| circ.alloc_n::<Qubit, 2>().map(|_| ())).unwrap();
+
+        assert!(crate::sim::equivalent(&undo(3.0 * PI as f32), &identity, true).unwrap());
+        assert!(!crate::sim::equivalent(&undo(PI as f32), &identity, true).unwrap());
     }
-}
\ No newline at end of file
+}
@@ -127,6 +127,7 @@ symbols! {
     /// TODO: Doc
     FormalParameter {
         count: parameter_count_mut,
+        max: 1 << (f32::MANTISSA_DIGITS - 1),
     }
 
     /// TODO: Doc
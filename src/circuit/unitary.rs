@@ -0,0 +1,169 @@
+//! Definitions of the matrices of the unitary operations. The qubits of an
+//! operation are mapped to the bits of the matrix indices in little-endian order,
+//! meaning that the first qubit is the least significant one.
+
+use std::f64::consts::{FRAC_1_SQRT_2, FRAC_PI_2, FRAC_PI_4};
+
+use crate::complex::Complex;
+use crate::matrix::Matrix;
+
+/// Shorthand to build a 2x2 matrix.
+#[inline]
+fn mat2(data: [Complex; 4]) -> Matrix {
+    Matrix::from_vec(2, data.to_vec()).unwrap()
+}
+
+/// Shorthand for a real complex number.
+#[inline]
+fn re(x: f64) -> Complex {
+    Complex::from(x)
+}
+
+#[inline]
+pub(crate) fn h() -> Matrix {
+    let s = re(FRAC_1_SQRT_2);
+    mat2([s, s, s, -s])
+}
+
+#[inline]
+pub(crate) fn x() -> Matrix {
+    mat2([Complex::ZERO, Complex::ONE, Complex::ONE, Complex::ZERO])
+}
+
+#[inline]
+pub(crate) fn y() -> Matrix {
+    mat2([Complex::ZERO, -Complex::I, Complex::I, Complex::ZERO])
+}
+
+#[inline]
+pub(crate) fn z() -> Matrix {
+    phase(std::f64::consts::PI)
+}
+
+#[inline]
+pub(crate) fn s() -> Matrix {
+    phase(FRAC_PI_2)
+}
+
+#[inline]
+pub(crate) fn sdg() -> Matrix {
+    phase(-FRAC_PI_2)
+}
+
+#[inline]
+pub(crate) fn t() -> Matrix {
+    phase(FRAC_PI_4)
+}
+
+#[inline]
+pub(crate) fn tdg() -> Matrix {
+    phase(-FRAC_PI_4)
+}
+
+#[inline]
+pub(crate) fn sx() -> Matrix {
+    let a = Complex::new(0.5, 0.5);
+    let b = Complex::new(0.5, -0.5);
+    mat2([a, b, b, a])
+}
+
+#[inline]
+pub(crate) fn rx(theta: f64) -> Matrix {
+    let (s, c) = (theta / 2.0).sin_cos();
+    mat2([re(c), Complex::new(0.0, -s), Complex::new(0.0, -s), re(c)])
+}
+
+#[inline]
+pub(crate) fn ry(theta: f64) -> Matrix {
+    let (s, c) = (theta / 2.0).sin_cos();
+    mat2([re(c), re(-s), re(s), re(c)])
+}
+
+#[inline]
+pub(crate) fn rz(theta: f64) -> Matrix {
+    Matrix::diagonal(&[Complex::cis(-theta / 2.0), Complex::cis(theta / 2.0)])
+}
+
+#[inline]
+pub(crate) fn phase(lambda: f64) -> Matrix {
+    Matrix::diagonal(&[Complex::ONE, Complex::cis(lambda)])
+}
+
+#[inline]
+pub(crate) fn u3(theta: f64, phi: f64, lambda: f64) -> Matrix {
+    let (s, c) = (theta / 2.0).sin_cos();
+    mat2([
+        re(c),
+        -Complex::cis(lambda) * s,
+        Complex::cis(phi) * s,
+        Complex::cis(phi + lambda) * c,
+    ])
+}
+
+#[inline]
+pub(crate) fn swap() -> Matrix {
+    let mut res = Matrix::zeros(4);
+    res[(0, 0)] = Complex::ONE;
+    res[(1, 2)] = Complex::ONE;
+    res[(2, 1)] = Complex::ONE;
+    res[(3, 3)] = Complex::ONE;
+    res
+}
+
+#[inline]
+pub(crate) fn iswap() -> Matrix {
+    let mut res = Matrix::zeros(4);
+    res[(0, 0)] = Complex::ONE;
+    res[(1, 2)] = Complex::I;
+    res[(2, 1)] = Complex::I;
+    res[(3, 3)] = Complex::ONE;
+    res
+}
+
+#[inline]
+pub(crate) fn rxx(theta: f64) -> Matrix {
+    let (s, c) = (theta / 2.0).sin_cos();
+    let mut res = Matrix::zeros(4);
+
+    for i in 0..4 {
+        res[(i, i)] = re(c);
+        res[(i, 3 - i)] = Complex::new(0.0, -s);
+    }
+
+    res
+}
+
+#[inline]
+pub(crate) fn rzz(theta: f64) -> Matrix {
+    let even = Complex::cis(-theta / 2.0);
+    let odd = Complex::cis(theta / 2.0);
+    Matrix::diagonal(&[even, odd, odd, even])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPS: f64 = 1e-12;
+
+    #[test]
+    fn relations() {
+        assert!((&s() * &s()).approx_eq(&z(), EPS));
+        assert!((&t() * &t()).approx_eq(&s(), EPS));
+        assert!((&sx() * &sx()).approx_eq(&x(), EPS));
+        assert!((&h() * &(&z() * &h())).approx_eq(&x(), EPS));
+        assert!(rx(1.0).approx_eq_up_to_phase(&u3(1.0, -FRAC_PI_2, FRAC_PI_2), EPS));
+        assert!(rz(1.0).approx_eq_up_to_phase(&phase(1.0), EPS));
+        assert!((&iswap() * &iswap().adjoint()).approx_eq(&Matrix::identity(4), EPS));
+    }
+
+    #[test]
+    fn controlled() {
+        let cx = x().controlled(1);
+
+        // |c=1, t=0> = index 1 is mapped to |c=1, t=1> = index 3.
+        assert_eq!(cx[(3, 1)], Complex::ONE);
+        assert_eq!(cx[(2, 2)], Complex::ONE);
+        assert!(cx.is_unitary(EPS));
+    }
+}
@@ -3,12 +3,20 @@
 #![allow(unused)] // TODO: remove once not needed anymore
 
 mod genericity;
+mod parse;
 
 pub mod bitset;
 pub mod circuit;
+pub mod complex;
+pub mod matrix;
+pub mod qasm;
+pub mod quil;
+pub mod sim;
+pub mod zx;
 
 pub mod prelude {
     //! `use trident::prelude::*;` to import the most common types, traits and functions.
 
     pub use crate::circuit::QuantumCircuit;
+    pub use crate::sim::Backend;
 }
\ No newline at end of file
@@ -0,0 +1,199 @@
+use std::ops::{Index, IndexMut, Mul};
+
+use crate::complex::Complex;
+
+/// A dense square matrix of complex numbers, stored in row-major order.
+///
+/// When a matrix acts on several qubits, the $k$th qubit corresponds to the $k$th least
+/// significant bit of the row and column indices (little-endian ordering).
+#[derive(Clone, PartialEq, Debug)]
+pub struct Matrix {
+    dim: usize,
+    data: Box<[Complex]>,
+}
+
+impl Matrix {
+    /// Returns the null matrix of dimension `dim`.
+    #[inline]
+    pub fn zeros(dim: usize) -> Self {
+        Self {
+            dim,
+            data: vec![Complex::ZERO; dim * dim].into_boxed_slice(),
+        }
+    }
+
+    /// Returns the identity matrix of dimension `dim`.
+    #[inline]
+    pub fn identity(dim: usize) -> Self {
+        let mut res = Self::zeros(dim);
+        (0..dim).for_each(|i| res[(i, i)] = Complex::ONE);
+        res
+    }
+
+    /// Returns the matrix of dimension `dim` whose entries in row-major order are given
+    /// by `data`. Returns `None` if `data` is not of length `dim * dim`.
+    #[inline]
+    pub fn from_vec(dim: usize, data: Vec<Complex>) -> Option<Self> {
+        (data.len() == dim * dim).then(|| Self { dim, data: data.into_boxed_slice() })
+    }
+
+    /// Returns the diagonal matrix with the given entries.
+    #[inline]
+    pub fn diagonal(diag: &[Complex]) -> Self {
+        let mut res = Self::zeros(diag.len());
+        diag.iter().enumerate().for_each(|(i, &z)| res[(i, i)] = z);
+        res
+    }
+
+    #[inline]
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Returns the entries of the matrix, in row-major order.
+    #[inline]
+    pub fn as_slice(&self) -> &[Complex] {
+        &self.data
+    }
+
+    /// Returns the matrix with all entries conjugated.
+    #[inline]
+    pub fn conj(&self) -> Self {
+        Self {
+            dim: self.dim,
+            data: self.data.iter().map(|z| z.conj()).collect(),
+        }
+    }
+
+    /// Returns the conjugate transpose of the matrix.
+    pub fn adjoint(&self) -> Self {
+        let mut res = Self::zeros(self.dim);
+
+        for i in 0..self.dim {
+            for j in 0..self.dim {
+                res[(j, i)] = self[(i, j)].conj();
+            }
+        }
+
+        res
+    }
+
+    /// Returns the kronecker product $A \otimes B$, where `self` is $A$. The qubits
+    /// of $B$ are the least significant ones.
+    pub fn kron(&self, rhs: &Self) -> Self {
+        let dim = self.dim * rhs.dim;
+        let mut res = Self::zeros(dim);
+
+        for i in 0..dim {
+            for j in 0..dim {
+                res[(i, j)] = self[(i / rhs.dim, j / rhs.dim)] * rhs[(i % rhs.dim, j % rhs.dim)];
+            }
+        }
+
+        res
+    }
+
+    /// Returns the matrix controlled by `controls` additional qubits, which are
+    /// the least significant ones.
+    pub fn controlled(&self, controls: usize) -> Self {
+        let mask = (1 << controls) - 1;
+        let dim = self.dim << controls;
+        let mut res = Self::identity(dim);
+
+        for i in (mask..dim).step_by(mask + 1) {
+            for j in (mask..dim).step_by(mask + 1) {
+                res[(i, j)] = self[(i >> controls, j >> controls)];
+            }
+        }
+
+        res
+    }
+
+    /// Returns the matrix multiplied by the scalar `z`.
+    #[inline]
+    pub fn scale(&self, z: Complex) -> Self {
+        Self {
+            dim: self.dim,
+            data: self.data.iter().map(|&x| x * z).collect(),
+        }
+    }
+
+    #[inline]
+    pub fn trace(&self) -> Complex {
+        (0..self.dim).map(|i| self[(i, i)]).sum()
+    }
+
+    /// Returns true if all entries are within `eps` of those of `rhs`.
+    #[inline]
+    pub fn approx_eq(&self, rhs: &Self, eps: f64) -> bool {
+        self.dim == rhs.dim && self.data.iter().zip(rhs.data.iter()).all(|(x, y)| x.approx_eq(*y, eps))
+    }
+
+    /// Returns true if there is a global phase $e^{i \varphi}$ such that `self` is
+    /// approximately equal to $e^{i \varphi}$ `rhs`.
+    pub fn approx_eq_up_to_phase(&self, rhs: &Self, eps: f64) -> bool {
+        if self.dim != rhs.dim {
+            return false;
+        }
+
+        // Use the largest entry of rhs as a reference, for numerical stability.
+        let pivot = (0..rhs.data.len())
+            .max_by(|&i, &j| rhs.data[i].norm_sqr().total_cmp(&rhs.data[j].norm_sqr()));
+
+        match pivot {
+            Some(k) if rhs.data[k].abs() > eps => {
+                let phase = self.data[k] / rhs.data[k];
+                (phase.abs() - 1.0).abs() <= eps && self.approx_eq(&rhs.scale(phase), eps)
+            }
+            _ => self.approx_eq(rhs, eps),
+        }
+    }
+
+    /// Returns true if the matrix is unitary, within `eps`.
+    #[inline]
+    pub fn is_unitary(&self, eps: f64) -> bool {
+        (self * &self.adjoint()).approx_eq(&Self::identity(self.dim), eps)
+    }
+}
+
+impl Index<(usize, usize)> for Matrix {
+    type Output = Complex;
+
+    #[inline]
+    fn index(&self, (i, j): (usize, usize)) -> &Complex {
+        &self.data[i * self.dim + j]
+    }
+}
+
+impl IndexMut<(usize, usize)> for Matrix {
+    #[inline]
+    fn index_mut(&mut self, (i, j): (usize, usize)) -> &mut Complex {
+        &mut self.data[i * self.dim + j]
+    }
+}
+
+impl Mul for &Matrix {
+    type Output = Matrix;
+
+    fn mul(self, rhs: &Matrix) -> Matrix {
+        assert_eq!(self.dim, rhs.dim, "matrix dimensions do not match");
+
+        let mut res = Matrix::zeros(self.dim);
+
+        for i in 0..self.dim {
+            for k in 0..self.dim {
+                let x = self[(i, k)];
+
+                if x == Complex::ZERO {
+                    continue;
+                }
+
+                for j in 0..self.dim {
+                    res[(i, j)] += x * rhs[(k, j)];
+                }
+            }
+        }
+
+        res
+    }
+}
@@ -0,0 +1,359 @@
+//! Positions, tokens, errors and numbers shared by the parsers of the OpenQASM and Quil languages.
+
+use std::f64::consts::PI;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use thiserror::Error;
+
+use crate::circuit::QuantumCircuitError;
+use crate::circuit::parameter::Parameter;
+
+/// An error found while parsing a program, along with its position in the source.
+/// Both line and column start at 1.
+#[derive(Clone, PartialEq, Debug, Error)]
+#[error("{line}:{column}: {kind}")]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Clone, PartialEq, Debug, Error)]
+pub enum ParseErrorKind {
+    #[error("unexpected character `{0}`")]
+    UnexpectedChar(char),
+    #[error("invalid number `{0}`")]
+    InvalidNumber(String),
+    #[error("unterminated string")]
+    UnterminatedString,
+    #[error("expected {expected}, found {found}")]
+    UnexpectedToken { expected: String, found: String },
+    #[error("unsupported version, expected `OPENQASM 2.0`")]
+    UnsupportedVersion,
+    #[error("unsupported include `{0}`")]
+    UnsupportedInclude(String),
+    #[error("unknown identifier `{0}`")]
+    UnknownIdentifier(String),
+    #[error("unknown gate `{0}`")]
+    UnknownGate(String),
+    #[error("unknown register `{0}`")]
+    UnknownRegister(String),
+    #[error("unknown memory region `{0}`")]
+    UnknownRegion(String),
+    #[error("`{0}` is already defined")]
+    Redefinition(String),
+    #[error("register is too large")]
+    RegisterTooLarge,
+    #[error("memory region of type `{0}` cannot be represented")]
+    UnsupportedType(String),
+    #[error("index {index} is out of range for `{register}`")]
+    IndexOutOfRange { register: String, index: u64 },
+    #[error("gate `{gate}` expects {expected} {kind}, found {found}")]
+    ArityMismatch { gate: String, kind: &'static str, expected: usize, found: usize },
+    #[error("registers of different sizes")]
+    RegisterSizeMismatch,
+    #[error("a qubit is used twice by the same gate")]
+    DuplicateQubit,
+    #[error("opaque gate `{0}` cannot be applied")]
+    OpaqueGate(String),
+    #[error("parameters can only be numbers or elements of a `REAL` region")]
+    UnsupportedExpression,
+    #[error("instruction `{0}` cannot be represented")]
+    UnsupportedInstruction(String),
+    #[error("control flow cannot be represented by modifiers")]
+    UnsupportedControlFlow,
+    #[error(transparent)]
+    Circuit(#[from] QuantumCircuitError),
+}
+
+/// A position in the source, both line and column start at 1.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub(crate) struct Pos {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Pos {
+    /// Returns an error of the given kind at this position.
+    #[inline]
+    pub(crate) fn error(self, kind: ParseErrorKind) -> ParseError {
+        ParseError { line: self.line, column: self.column, kind }
+    }
+}
+
+/// The tokens of both languages. OpenQASM programs end with `Eof`, and each line of a
+/// Quil program with `Eol`.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Token {
+    Ident(String),
+    Int(u64),
+    Real(f64),
+    Str(String),
+    Label(String),
+    Semicolon,
+    Comma,
+    Colon,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    Arrow,
+    EqEq,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Caret,
+    Eol,
+    Eof,
+}
+
+impl Token {
+    /// Returns a short description of the token, used in error messages.
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            Self::Ident(name) => format!("`{}`", name),
+            Self::Int(n) => format!("`{}`", n),
+            Self::Real(x) => format!("`{}`", x),
+            Self::Str(s) => format!("\"{}\"", s),
+            Self::Label(name) => format!("`@{}`", name),
+            Self::Semicolon => "`;`".into(),
+            Self::Comma => "`,`".into(),
+            Self::Colon => "`:`".into(),
+            Self::LParen => "`(`".into(),
+            Self::RParen => "`)`".into(),
+            Self::LBracket => "`[`".into(),
+            Self::RBracket => "`]`".into(),
+            Self::LBrace => "`{`".into(),
+            Self::RBrace => "`}`".into(),
+            Self::Arrow => "`->`".into(),
+            Self::EqEq => "`==`".into(),
+            Self::Plus => "`+`".into(),
+            Self::Minus => "`-`".into(),
+            Self::Star => "`*`".into(),
+            Self::Slash => "`/`".into(),
+            Self::Caret => "`^`".into(),
+            Self::Eol => "end of line".into(),
+            Self::Eof => "end of file".into(),
+        }
+    }
+}
+
+/// Lexes the number at the start of `chars`, an integer or a real with an optional fraction
+/// and exponent, starting at `pos`. Returns the token and the number of characters read.
+pub(crate) fn number(chars: &[char], pos: Pos) -> Result<(Token, usize), ParseError> {
+    let mut len = chars.iter().take_while(|c| c.is_ascii_digit()).count();
+    let mut real = false;
+
+    if chars.get(len) == Some(&'.') {
+        real = true;
+        len += 1;
+        len += chars[len..].iter().take_while(|c| c.is_ascii_digit()).count();
+    }
+
+    if matches!(chars.get(len), Some('e' | 'E')) {
+        let sign = matches!(chars.get(len + 1), Some('+' | '-')) as usize;
+        let digits = chars[(len + 1 + sign).min(chars.len())..].iter().take_while(|c| c.is_ascii_digit()).count();
+
+        if digits != 0 {
+            real = true;
+            len += 1 + sign + digits;
+        }
+    }
+
+    let text: String = chars[..len].iter().collect();
+
+    let token = if real {
+        text.parse().ok().map(Token::Real)
+    } else {
+        text.parse().ok().map(Token::Int)
+    };
+
+    Ok((token.ok_or_else(|| pos.error(ParseErrorKind::InvalidNumber(text)))?, len))
+}
+
+/// A rational number, multiplied by $\pi$ if `pi` is set. Zero is never multiplied by $\pi$.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+struct Ratio {
+    numerator: i64,
+    denominator: i64,
+    pi: bool,
+}
+
+impl Ratio {
+    /// Returns the reduced ratio, or `None` if the denominator is zero.
+    fn new(numerator: i64, denominator: i64, pi: bool) -> Option<Self> {
+        if denominator == 0 {
+            return None;
+        }
+
+        let (mut a, mut b) = (numerator.unsigned_abs(), denominator.unsigned_abs());
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+
+        let gcd = a as i64 * denominator.signum();
+        Some(Self { numerator: numerator / gcd, denominator: denominator / gcd, pi: pi && numerator != 0 })
+    }
+
+    fn add(self, rhs: Self) -> Option<Self> {
+        match (self.numerator, rhs.numerator) {
+            (0, _) => Some(rhs),
+            (_, 0) => Some(self),
+            _ if self.pi != rhs.pi => None,
+            _ => Self::new(
+                self.numerator.checked_mul(rhs.denominator)?.checked_add(rhs.numerator.checked_mul(self.denominator)?)?,
+                self.denominator.checked_mul(rhs.denominator)?,
+                self.pi,
+            ),
+        }
+    }
+
+    fn mul(self, rhs: Self) -> Option<Self> {
+        if self.pi && rhs.pi {
+            return None;
+        }
+
+        Self::new(self.numerator.checked_mul(rhs.numerator)?, self.denominator.checked_mul(rhs.denominator)?, self.pi || rhs.pi)
+    }
+
+    fn div(self, rhs: Self) -> Option<Self> {
+        if rhs.pi && !self.pi && self.numerator != 0 {
+            return None;
+        }
+
+        Self::new(self.numerator.checked_mul(rhs.denominator)?, self.denominator.checked_mul(rhs.numerator)?, self.pi && !rhs.pi)
+    }
+}
+
+/// The value of a constant expression. Rational multiples of $\pi$ are tracked exactly
+/// through the arithmetic operations, so that angles such as `pi/2` are imported as exact
+/// angles, see [`Parameter::pi`].
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) struct Number {
+    pub value: f64,
+    exact: Option<Ratio>,
+}
+
+impl Number {
+    pub(crate) const PI: Self = Self { value: PI, exact: Some(Ratio { numerator: 1, denominator: 1, pi: true }) };
+
+    /// Returns an exact integer.
+    #[inline]
+    pub(crate) fn int(n: u64) -> Self {
+        Self { value: n as f64, exact: i64::try_from(n).ok().and_then(|n| Ratio::new(n, 1, false)) }
+    }
+
+    /// Applies a function to the value, which is no longer exact.
+    #[inline]
+    pub(crate) fn map(self, func: impl FnOnce(f64) -> f64) -> Self {
+        func(self.value).into()
+    }
+
+    #[inline]
+    pub(crate) fn powf(self, rhs: Self) -> Self {
+        self.value.powf(rhs.value).into()
+    }
+
+    #[inline]
+    fn combine(self, rhs: Self, value: f64, exact: fn(Ratio, Ratio) -> Option<Ratio>) -> Self {
+        Self { value, exact: self.exact.zip(rhs.exact).and_then(|(a, b)| exact(a, b)) }
+    }
+}
+
+impl From<f64> for Number {
+    #[inline]
+    fn from(value: f64) -> Self {
+        Self { value, exact: None }
+    }
+}
+
+impl Add for Number {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        self.combine(rhs, self.value + rhs.value, Ratio::add)
+    }
+}
+
+impl Sub for Number {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        self + -rhs
+    }
+}
+
+impl Mul for Number {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self {
+        self.combine(rhs, self.value * rhs.value, Ratio::mul)
+    }
+}
+
+impl Div for Number {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: Self) -> Self {
+        self.combine(rhs, self.value / rhs.value, Ratio::div)
+    }
+}
+
+impl Neg for Number {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self {
+        Self { value: -self.value, exact: self.exact.map(|ratio| Ratio { numerator: -ratio.numerator, ..ratio }) }
+    }
+}
+
+impl From<Number> for Parameter<'_> {
+    /// Returns an exact angle if the number is a rational multiple of $\pi$ whose denominator
+    /// fits in an angle, and a float otherwise.
+    fn from(number: Number) -> Self {
+        match number.exact {
+            Some(Ratio { numerator, denominator, pi }) if (pi || numerator == 0) && denominator <= Parameter::MAX_DENOMINATOR as i64 => {
+                Parameter::pi(numerator.rem_euclid(4 * denominator) as i32, denominator as u32)
+            }
+            _ => (number.value as f32).into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers() {
+        let lex = |src: &str| number(&src.chars().collect::<Vec<_>>(), Pos::default());
+
+        assert_eq!(lex("42;"), Ok((Token::Int(42), 2)));
+        assert_eq!(lex("1.5e-1)"), Ok((Token::Real(0.15), 6)));
+        assert_eq!(lex("2e"), Ok((Token::Int(2), 1)));
+        assert_eq!(lex(".").unwrap_err().kind, ParseErrorKind::InvalidNumber(".".into()));
+    }
+
+    #[test]
+    fn exact_angles() {
+        let [one, two, three] = [1, 2, 3].map(Number::int);
+        let angle = |x: Number| Parameter::from(x).as_angle();
+
+        assert_eq!(angle(Number::PI / two), Some((1, 2)));
+        assert_eq!(angle(-Number::PI / two), Some((7, 2)));
+        assert_eq!(angle(three * Number::PI / two - Number::PI + Number::PI / three), Some((5, 6)));
+        assert_eq!(angle(Number::PI / Number::PI - one), Some((0, 1)));
+        assert_eq!(angle(Number::PI * Number::PI), None);
+        assert_eq!(angle(Number::PI + one), None);
+        assert_eq!(angle(Number::from(0.5) * Number::PI), None);
+        assert_eq!(angle(Number::PI.map(f64::sqrt)), None);
+    }
+}
@@ -0,0 +1,605 @@
+use std::collections::HashMap;
+
+use crate::bitset::BitSet;
+use crate::circuit::QuantumCircuit;
+use crate::circuit::instruction::{Compute, Instr, Modifier};
+use crate::circuit::operation::OpKind;
+use crate::circuit::symbol::{Bit, Qubit};
+use crate::parse::{Number, ParseError, ParseErrorKind, Pos};
+
+use super::parser::{self, Arg, Condition, GateDef, QuantumOp, Stmt};
+
+/// Definitions of the gates of `qelib1.inc` that do not have a corresponding operation.
+const QELIB1: &str = "OPENQASM 2.0;
+gate u2(phi,lambda) q { U(pi/2,phi,lambda) q; }
+gate u(theta,phi,lambda) q { U(theta,phi,lambda) q; }
+gate u0(gamma) q { }
+gate id a { }
+gate sxdg a { s a; h a; s a; }
+gate crx(lambda) a,b { u1(pi/2) b; cx a,b; u3(-lambda/2,0,0) b; cx a,b; u3(lambda/2,-pi/2,0) b; }
+gate cry(lambda) a,b { ry(lambda/2) b; cx a,b; ry(-lambda/2) b; cx a,b; }
+gate cu3(theta,phi,lambda) c,t { u1((lambda+phi)/2) c; u1((lambda-phi)/2) t; cx c,t; u3(-theta/2,0,-(phi+lambda)/2) t; cx c,t; u3(theta/2,phi,0) t; }
+";
+
+/// Returns the operation corresponding to the given gate name. The `U` and `CX` gates are
+/// always available, the other ones require `qelib1.inc` to be included.
+fn native<'a, 'id>(name: &str, included: bool) -> Option<OpKind<'a, 'id>> {
+    let op = match name {
+        "U" => OpKind::U3,
+        "CX" => OpKind::CX,
+        _ if !included => return None,
+        "u3" => OpKind::U3,
+        "u1" | "p" => OpKind::Phase,
+        "cx" => OpKind::CX,
+        "x" => OpKind::X,
+        "y" => OpKind::Y,
+        "z" => OpKind::Z,
+        "h" => OpKind::H,
+        "s" => OpKind::S,
+        "sdg" => OpKind::Sdg,
+        "t" => OpKind::T,
+        "tdg" => OpKind::Tdg,
+        "sx" => OpKind::SX,
+        "rx" => OpKind::RX,
+        "ry" => OpKind::RY,
+        "rz" => OpKind::RZ,
+        "cy" => OpKind::CY,
+        "cz" => OpKind::CZ,
+        "ch" => OpKind::CH,
+        "swap" => OpKind::Swap,
+        "crz" => OpKind::CRZ,
+        "cu1" | "cp" => OpKind::CP,
+        "rxx" => OpKind::RXX,
+        "rzz" => OpKind::RZZ,
+        "ccx" => OpKind::CCX,
+        "cswap" => OpKind::CSwap,
+        _ => return None,
+    };
+
+    Some(op)
+}
+
+/// A classical register compared to an integer.
+#[derive(Clone, Debug)]
+struct Cond {
+    bits: Vec<u32>,
+    value: u64,
+}
+
+/// An instruction, with symbols given by their ids.
+#[derive(Clone, Debug)]
+enum Lowered {
+    Gate {
+        name: String,
+        params: Vec<Number>,
+        qubits: Vec<u32>,
+    },
+    Measure(u32, u32),
+    Reset(u32),
+    Barrier(Vec<u32>),
+}
+
+/// The state of the conversion of statements into instructions.
+#[derive(Default)]
+struct Importer {
+    included: bool,
+    gates: HashMap<String, GateDef>,
+    qregs: HashMap<String, (u32, u32)>,
+    cregs: HashMap<String, (u32, u32)>,
+    qubit_count: u32,
+    bit_count: u32,
+    instrs: Vec<(Lowered, Option<Cond>)>,
+}
+
+impl Importer {
+    /// Returns true if a gate of that name is defined.
+    #[inline]
+    fn is_gate(&self, name: &str) -> bool {
+        self.gates.contains_key(name) || native(name, self.included).is_some()
+    }
+
+    /// Checks that a name is not already taken by a gate or register.
+    fn check_free(&self, name: &str, pos: Pos) -> Result<(), ParseError> {
+        if self.is_gate(name) || self.qregs.contains_key(name) || self.cregs.contains_key(name) {
+            Err(pos.error(ParseErrorKind::Redefinition(name.to_owned())))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the ids of the elements of the given argument, in the given registers.
+    fn resolve(registers: &HashMap<String, (u32, u32)>, arg: &Arg) -> Result<Vec<u32>, ParseError> {
+        let &(start, size) = registers.get(&arg.name)
+            .ok_or_else(|| arg.pos.error(ParseErrorKind::UnknownRegister(arg.name.clone())))?;
+
+        match arg.index {
+            Some(index) if index >= size as u64 => Err(arg.pos.error(ParseErrorKind::IndexOutOfRange {
+                register: arg.name.clone(),
+                index,
+            })),
+            Some(index) => Ok(vec![start + index as u32]),
+            None => Ok((start..start + size).collect()),
+        }
+    }
+
+    /// Broadcasts a list of arguments, returning the ids of the qubits of each application.
+    fn broadcast(&self, args: &[Arg], pos: Pos) -> Result<Vec<Vec<u32>>, ParseError> {
+        let args = args.iter()
+            .map(|arg| Self::resolve(&self.qregs, arg))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let len = args.iter().map(Vec::len).filter(|&len| len != 1).max().unwrap_or(1);
+
+        if args.iter().any(|arg| arg.len() != 1 && arg.len() != len) {
+            return Err(pos.error(ParseErrorKind::RegisterSizeMismatch));
+        }
+
+        Ok((0..len)
+            .map(|i| args.iter().map(|arg| if arg.len() == 1 { arg[0] } else { arg[i] }).collect())
+            .collect())
+    }
+
+    /// Applies a gate to the given qubits, expanding it if it is user defined.
+    fn apply(&mut self, name: &str, params: Vec<Number>, qubits: Vec<u32>, cond: &Option<Cond>, pos: Pos) -> Result<(), ParseError> {
+        let arity = |kind: &'static str, expected: usize, found: usize| {
+            (expected != found).then(|| pos.error(ParseErrorKind::ArityMismatch {
+                gate: name.to_owned(),
+                kind,
+                expected,
+                found,
+            }))
+        };
+
+        if (1..qubits.len()).any(|i| qubits[..i].contains(&qubits[i])) {
+            return Err(pos.error(ParseErrorKind::DuplicateQubit));
+        }
+
+        if let Some(def) = self.gates.get(name).cloned() {
+            if let Some(err) = arity("parameters", def.params.len(), params.len())
+                .or_else(|| arity("qubits", def.qubits.len(), qubits.len()))
+            {
+                return Err(err);
+            }
+
+            let body = def.body.as_ref()
+                .ok_or_else(|| pos.error(ParseErrorKind::OpaqueGate(name.to_owned())))?;
+
+            let env: HashMap<_, _> = def.params.iter().cloned().zip(params).collect();
+            let qubit = |name: &String| qubits[def.qubits.iter().position(|q| q == name).unwrap()];
+
+            for stmt in body {
+                let args = stmt.args.iter().map(|(name, _)| qubit(name)).collect();
+
+                if stmt.name == "barrier" {
+                    self.instrs.push((Lowered::Barrier(args), cond.clone()));
+                } else {
+                    let params = stmt.params.iter().map(|param| param.eval(&env)).collect::<Result<_, _>>()?;
+                    self.apply(&stmt.name, params, args, cond, stmt.pos)?;
+                }
+            }
+
+            return Ok(());
+        }
+
+        let op = native(name, self.included)
+            .ok_or_else(|| pos.error(ParseErrorKind::UnknownGate(name.to_owned())))?;
+
+        if let Some(err) = arity("parameters", op.parameters().get().unwrap() as usize, params.len())
+            .or_else(|| arity("qubits", op.qubits().get().unwrap() as usize, qubits.len()))
+        {
+            return Err(err);
+        }
+
+        self.instrs.push((Lowered::Gate { name: name.to_owned(), params, qubits }, cond.clone()));
+        Ok(())
+    }
+
+    /// Checks and registers a gate definition.
+    fn define(&mut self, def: GateDef) -> Result<(), ParseError> {
+        self.check_free(&def.name, def.pos)?;
+
+        for stmt in def.body.iter().flatten() {
+            if stmt.name != "barrier" && !self.is_gate(&stmt.name) {
+                return Err(stmt.pos.error(ParseErrorKind::UnknownGate(stmt.name.clone())));
+            }
+
+            for (arg, pos) in &stmt.args {
+                if !def.qubits.contains(arg) {
+                    return Err(pos.error(ParseErrorKind::UnknownIdentifier(arg.clone())));
+                }
+            }
+        }
+
+        self.gates.insert(def.name.clone(), def);
+        Ok(())
+    }
+
+    /// Declares a quantum or classical register.
+    fn register(&mut self, name: String, size: u64, quantum: bool, pos: Pos) -> Result<(), ParseError> {
+        self.check_free(&name, pos)?;
+
+        let (count, registers) = if quantum {
+            (&mut self.qubit_count, &mut self.qregs)
+        } else {
+            (&mut self.bit_count, &mut self.cregs)
+        };
+
+        let size = u32::try_from(size).ok()
+            .filter(|&size| count.checked_add(size).is_some())
+            .ok_or_else(|| pos.error(ParseErrorKind::RegisterTooLarge))?;
+
+        registers.insert(name, (*count, size));
+        *count += size;
+        Ok(())
+    }
+
+    fn condition(&self, condition: &Condition) -> Result<Cond, ParseError> {
+        let &(start, size) = self.cregs.get(&condition.register)
+            .ok_or_else(|| condition.pos.error(ParseErrorKind::UnknownRegister(condition.register.clone())))?;
+
+        Ok(Cond { bits: (start..start + size).collect(), value: condition.value })
+    }
+
+    fn stmt(&mut self, stmt: Stmt, pos: Pos) -> Result<(), ParseError> {
+        match stmt {
+            Stmt::Include(file) => {
+                if file != "qelib1.inc" {
+                    return Err(pos.error(ParseErrorKind::UnsupportedInclude(file)));
+                }
+
+                if !self.included {
+                    self.included = true;
+
+                    for (stmt, _) in parser::parse(QELIB1).unwrap() {
+                        if let Stmt::Gate(def) = stmt {
+                            self.gates.insert(def.name.clone(), def);
+                        }
+                    }
+                }
+            }
+            Stmt::QReg(name, size) => self.register(name, size, true, pos)?,
+            Stmt::CReg(name, size) => self.register(name, size, false, pos)?,
+            Stmt::Gate(def) => self.define(def)?,
+            Stmt::Barrier(args) => {
+                let qubits = args.iter()
+                    .map(|arg| Self::resolve(&self.qregs, arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.instrs.push((Lowered::Barrier(qubits.concat()), None));
+            }
+            Stmt::Op { op, condition } => {
+                let cond = condition.as_ref().map(|condition| self.condition(condition)).transpose()?;
+
+                match op {
+                    QuantumOp::Call { name, params, args } => {
+                        let params: Vec<_> = params.iter()
+                            .map(|param| param.eval(&HashMap::new()))
+                            .collect::<Result<_, _>>()?;
+
+                        for qubits in self.broadcast(&args, pos)? {
+                            self.apply(&name, params.clone(), qubits, &cond, pos)?;
+                        }
+                    }
+                    QuantumOp::Measure { qubit, bit } => {
+                        let qubits = Self::resolve(&self.qregs, &qubit)?;
+                        let bits = Self::resolve(&self.cregs, &bit)?;
+
+                        if qubits.len() != bits.len() {
+                            return Err(pos.error(ParseErrorKind::RegisterSizeMismatch));
+                        }
+
+                        for (q, b) in qubits.into_iter().zip(bits) {
+                            self.instrs.push((Lowered::Measure(q, b), cond.clone()));
+                        }
+                    }
+                    QuantumOp::Reset { qubit } => {
+                        for q in Self::resolve(&self.qregs, &qubit)? {
+                            self.instrs.push((Lowered::Reset(q), cond.clone()));
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// True if all bits are set.
+fn all_set(bits: BitSet) -> bool {
+    bits.count_ones() == bits.len()
+}
+
+/// True if no bit is set.
+fn none_set(bits: BitSet) -> bool {
+    bits.is_zero()
+}
+
+/// True if the bits of the first half are set and those of the second half are not.
+fn first_half_set(bits: BitSet) -> bool {
+    let half = bits.len() / 2;
+    bits.iter().enumerate().all(|(i, bit)| bit == (i < half))
+}
+
+/// Returns the modifier testing the condition, given the bits of the circuit, or
+/// `None` if the condition is always false.
+///
+/// Since computes can't capture the value the register is compared to, the bits
+/// that should be set are put in a first half and the other ones in a second half,
+/// both halves being padded to the size of the register by repeating their first bit.
+fn modifier<'a, 'id>(cond: &Cond, bits: &[Bit<'id>], buffer: &'a mut Vec<Bit<'id>>) -> Option<Modifier<'a, 'id>> {
+    let size = cond.bits.len();
+
+    if size < 64 && cond.value >> size != 0 {
+        return None;
+    }
+
+    let (ones, zeros): (Vec<_>, Vec<_>) = cond.bits.iter()
+        .enumerate()
+        .partition(|&(i, _)| i < 64 && cond.value >> i & 1 == 1);
+
+    if size == 1 && ones.len() == 1 {
+        return Some(Modifier::IfBit(bits[cond.bits[0] as usize]));
+    }
+
+    let func: fn(BitSet) -> bool = if zeros.is_empty() {
+        buffer.extend(ones.iter().map(|&(_, &b)| bits[b as usize]));
+        all_set
+    } else if ones.is_empty() {
+        buffer.extend(zeros.iter().map(|&(_, &b)| bits[b as usize]));
+        none_set
+    } else {
+        for half in [ones, zeros] {
+            let padding = std::iter::repeat_n(&half[0], size - half.len());
+            buffer.extend(half.iter().chain(padding).map(|&(_, &b)| bits[b as usize]));
+        }
+        first_half_set
+    };
+
+    Some(Modifier::IfCompute(Compute { bits: buffer, func }))
+}
+
+impl QuantumCircuit {
+    /// Parses an OpenQASM 2.0 program and returns the corresponding circuit.
+    ///
+    /// Qubits and bits are allocated register by register, in the order of their
+    /// declarations. The `if` statements comparing a register of one bit to 1 are
+    /// converted to the `IfBit` modifier, other comparisons to the `IfCompute` modifier.
+    /// User defined gates are expanded, and applying an opaque gate is an error.
+    /// Parameters that are rational multiples of $\pi$, such as `pi/2`, are exact angles.
+    pub fn from_qasm2(src: &str) -> Result<Self, ParseError> {
+        let mut importer = Importer::default();
+
+        for (stmt, pos) in parser::parse(src)? {
+            importer.stmt(stmt, pos)?;
+        }
+
+        let Importer { included, qregs, cregs, instrs, .. } = importer;
+
+        // Registers, sorted by order of declaration.
+        let sizes = |registers: HashMap<String, (u32, u32)>| {
+            let mut registers: Vec<_> = registers.into_values().collect();
+            registers.sort_unstable();
+            registers.into_iter().map(|(_, size)| size as usize).collect::<Vec<_>>()
+        };
+        let (qregs, cregs) = (sizes(qregs), sizes(cregs));
+
+        QuantumCircuit::new(|circ| {
+            let mut qubits: Vec<Qubit> = Vec::new();
+            let mut bits: Vec<Bit> = Vec::new();
+
+            for &size in &qregs {
+                qubits.extend(circ.alloc_list::<Qubit>(size)?);
+            }
+
+            for &size in &cregs {
+                bits.extend(circ.alloc_list::<Bit>(size)?);
+            }
+
+            for (lowered, cond) in &instrs {
+                let mut buffer = Vec::new();
+
+                let modifier = match cond {
+                    Some(cond) => match modifier(cond, &bits, &mut buffer) {
+                        Some(modifier) => Some(modifier),
+                        None => continue,
+                    },
+                    None => None,
+                };
+
+                let qubit = |ids: &[u32]| ids.iter().map(|&q| qubits[q as usize]).collect::<Vec<_>>();
+
+                match lowered {
+                    Lowered::Gate { name, params, qubits: ids } => {
+                        let params: Vec<_> = params.iter().map(|&x| x.into()).collect();
+                        circ.push(Instr {
+                            op: native(name, included).unwrap(),
+                            qubits: &qubit(ids),
+                            parameters: &params,
+                            modifier,
+                            ..Default::default()
+                        })?;
+                    }
+                    Lowered::Measure(q, b) => {
+                        circ.push(Instr {
+                            op: OpKind::Measure,
+                            qubits: &qubit(&[*q]),
+                            bits: &[bits[*b as usize]],
+                            modifier,
+                            ..Default::default()
+                        })?;
+                    }
+                    Lowered::Reset(q) => {
+                        circ.push(Instr { op: OpKind::Reset, qubits: &qubit(&[*q]), modifier, ..Default::default() })?;
+                    }
+                    Lowered::Barrier(ids) => {
+                        circ.push(Instr { op: OpKind::Barrier, qubits: &qubit(ids), modifier, ..Default::default() })?;
+                    }
+                }
+            }
+
+            Ok(())
+        }).map_err(|err| Pos { line: 1, column: 1 }.error(ParseErrorKind::Circuit(err)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::parameter::Parameter;
+    use crate::circuit::symbol::Symbol;
+    use crate::sim::{Backend, StateVector};
+
+    use super::*;
+
+    #[test]
+    fn import() {
+        let circ = QuantumCircuit::from_qasm2("
+            OPENQASM 2.0;
+            include \"qelib1.inc\";
+
+            gate bell a, b { h a; cx a, b; }
+
+            qreg q[2];
+            qreg r[2];
+            creg c[2];
+
+            bell q[0], q[1];
+            x r;
+            barrier q, r;
+            measure q -> c;
+            if(c==3) reset r[0];
+        ").unwrap();
+
+        assert_eq!(circ.qubit_count(), 4);
+        assert_eq!(circ.bit_count(), 2);
+
+        let mut labels = Vec::new();
+        let mut iter = circ.instructions();
+
+        while let Some(instr) = iter.next() {
+            labels.push(instr.op.label());
+        }
+
+        assert_eq!(labels, ["h", "cx", "x", "x", "barrier", "measure", "measure", "reset"]);
+
+        // When both bits are one, the third qubit is reset.
+        for seed in 0..8 {
+            let sim = StateVector::simulate(&circ, seed).unwrap();
+            let both = sim.register().count_ones() == 2;
+            assert!((sim.probability_one(2) - if both { 0.0 } else { 1.0 }).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+            let [b1, b2] = circ.alloc_n()?;
+            circ.h(q1).iswap(q1, q2).u3(0.5, 0.25, -1.0, q2).measure(&[q1], &[b1]);
+            circ.push(Instr {
+                op: OpKind::X,
+                qubits: &[q2],
+                modifier: Some(Modifier::IfBit(b1)),
+                ..Default::default()
+            })?;
+            circ.measure(&[q2], &[b2]);
+            Ok(())
+        }).unwrap();
+
+        let qasm = circ.to_qasm2().unwrap();
+        let imported = QuantumCircuit::from_qasm2(&qasm).unwrap();
+
+        let mut iter = imported.instructions();
+        let mut labels = Vec::new();
+
+        while let Some(instr) = iter.next() {
+            labels.push(instr.op.label());
+
+            if instr.op == OpKind::X {
+                assert!(matches!(instr.modifier, Some(Modifier::IfBit(b)) if b.id() == 0));
+            }
+        }
+
+        assert_eq!(labels, ["h", "s", "s", "cz", "swap", "u3", "measure", "x", "measure"]);
+        assert_eq!(imported.to_qasm2().unwrap().lines().last(), qasm.lines().last());
+    }
+
+    #[test]
+    fn exact_angles() {
+        let circ = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            circ.rz(Parameter::pi(1, 2), q).u3(Parameter::pi(1, 2), Parameter::pi(0, 1), Parameter::pi(3, 1), q).rx(Parameter::pi(3, 4), q);
+            Ok(())
+        }).unwrap();
+
+        let angles = |circ: &QuantumCircuit| {
+            let mut angles = Vec::new();
+            let mut iter = circ.instructions();
+
+            while let Some(instr) = iter.next() {
+                angles.extend(instr.parameters.iter().map(|p| p.as_angle()));
+            }
+
+            angles
+        };
+
+        let imported = QuantumCircuit::from_qasm2(&circ.to_qasm2().unwrap()).unwrap();
+        assert_eq!(angles(&imported), [Some((1, 2)), Some((1, 2)), Some((0, 1)), Some((3, 1)), Some((3, 4))]);
+
+        let imported = QuantumCircuit::from_qasm2("
+            OPENQASM 2.0;
+            include \"qelib1.inc\";
+            qreg q[2];
+            rz(-pi/2 + 2*pi) q[0];
+            cry(pi) q[0], q[1];
+        ").unwrap();
+
+        assert!(imported.is_clifford());
+    }
+
+    #[test]
+    fn errors() {
+        let err = |src: &str| QuantumCircuit::from_qasm2(src).err().unwrap();
+
+        assert_eq!(err("OPENQASM 2.0;\nqreg q[1];\nh q;"), ParseError {
+            line: 3,
+            column: 1,
+            kind: ParseErrorKind::UnknownGate("h".into()),
+        });
+
+        assert_eq!(err("OPENQASM 2.0;\ninclude \"qelib1.inc\";\nqreg q[2];\n  cx q[0];"), ParseError {
+            line: 4,
+            column: 3,
+            kind: ParseErrorKind::ArityMismatch { gate: "cx".into(), kind: "qubits", expected: 2, found: 1 },
+        });
+
+        assert_eq!(err("OPENQASM 2.0;\nqreg q[2];\nU(0, 0) q[2];").kind, ParseErrorKind::IndexOutOfRange {
+            register: "q".into(),
+            index: 2,
+        });
+
+        assert_eq!(err("OPENQASM 2.0;\nopaque g a;\nqreg q[1];\ng q;").kind, ParseErrorKind::OpaqueGate("g".into()));
+        assert_eq!(err("OPENQASM 3.0;").kind, ParseErrorKind::UnsupportedVersion);
+    }
+
+    #[test]
+    fn conditions() {
+        let circ = QuantumCircuit::from_qasm2("
+            OPENQASM 2.0;
+            include \"qelib1.inc\";
+            qreg q[3];
+            creg c[3];
+            x q[0];
+            x q[2];
+            measure q -> c;
+            if(c==5) x q[1];
+            if(c==7) x q[0];
+            if(c==8) x q[0];
+        ").unwrap();
+
+        let sim = StateVector::simulate(&circ, 0).unwrap();
+
+        assert!((sim.probability_one(0) - 1.0).abs() < 1e-9);
+        assert!((sim.probability_one(1) - 1.0).abs() < 1e-9);
+        assert!((sim.probability_one(2) - 1.0).abs() < 1e-9);
+    }
+}
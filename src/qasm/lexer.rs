@@ -0,0 +1,106 @@
+use crate::parse::{ParseError, ParseErrorKind, Pos, Token, number};
+
+/// Splits the source into tokens, along with their positions. The last token is always `Eof`.
+/// Comments start with `//` and span until the end of the line.
+pub(crate) fn tokenize(src: &str) -> Result<Vec<(Token, Pos)>, ParseError> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut pos = Pos { line: 1, column: 1 };
+    let mut i = 0;
+
+    // Advances by n characters, keeping track of the position.
+    let advance = |i: &mut usize, pos: &mut Pos, n: usize| {
+        for _ in 0..n {
+            if chars[*i] == '\n' {
+                pos.line += 1;
+                pos.column = 1;
+            } else {
+                pos.column += 1;
+            }
+            *i += 1;
+        }
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let start = pos;
+
+        if c.is_whitespace() {
+            advance(&mut i, &mut pos, 1);
+            continue;
+        }
+
+        if c == '/' && next == Some('/') {
+            while i < chars.len() && chars[i] != '\n' {
+                advance(&mut i, &mut pos, 1);
+            }
+            continue;
+        }
+
+        let (token, len) = match c {
+            'a'..='z' | 'A'..='Z' | '_' => {
+                let len = chars[i..].iter().take_while(|c| c.is_ascii_alphanumeric() || **c == '_').count();
+                (Token::Ident(chars[i..i + len].iter().collect()), len)
+            }
+            '0'..='9' | '.' => number(&chars[i..], start)?,
+            '"' => {
+                let len = chars[i + 1..].iter().take_while(|c| **c != '"' && **c != '\n').count();
+
+                if chars.get(i + 1 + len) != Some(&'"') {
+                    return Err(start.error(ParseErrorKind::UnterminatedString));
+                }
+
+                (Token::Str(chars[i + 1..i + 1 + len].iter().collect()), len + 2)
+            }
+            '-' if next == Some('>') => (Token::Arrow, 2),
+            '=' if next == Some('=') => (Token::EqEq, 2),
+            ';' => (Token::Semicolon, 1),
+            ',' => (Token::Comma, 1),
+            '(' => (Token::LParen, 1),
+            ')' => (Token::RParen, 1),
+            '[' => (Token::LBracket, 1),
+            ']' => (Token::RBracket, 1),
+            '{' => (Token::LBrace, 1),
+            '}' => (Token::RBrace, 1),
+            '+' => (Token::Plus, 1),
+            '-' => (Token::Minus, 1),
+            '*' => (Token::Star, 1),
+            '/' => (Token::Slash, 1),
+            '^' => (Token::Caret, 1),
+            c => return Err(start.error(ParseErrorKind::UnexpectedChar(c))),
+        };
+
+        tokens.push((token, start));
+        advance(&mut i, &mut pos, len);
+    }
+
+    tokens.push((Token::Eof, pos));
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens() {
+        let tokens = tokenize("OPENQASM 2.0;\n// comment\nrx(1.5e-1) q[0]; measure q -> c;").unwrap();
+        let kinds: Vec<_> = tokens.iter().map(|(token, _)| token.clone()).collect();
+
+        assert_eq!(kinds, [
+            Token::Ident("OPENQASM".into()), Token::Real(2.0), Token::Semicolon,
+            Token::Ident("rx".into()), Token::LParen, Token::Real(0.15), Token::RParen,
+            Token::Ident("q".into()), Token::LBracket, Token::Int(0), Token::RBracket, Token::Semicolon,
+            Token::Ident("measure".into()), Token::Ident("q".into()), Token::Arrow,
+            Token::Ident("c".into()), Token::Semicolon, Token::Eof,
+        ]);
+
+        assert_eq!(tokens[3].1, Pos { line: 3, column: 1 });
+        assert_eq!(tokenize("h q;\n  $").unwrap_err(), ParseError {
+            line: 2,
+            column: 3,
+            kind: ParseErrorKind::UnexpectedChar('$'),
+        });
+    }
+}
@@ -0,0 +1,27 @@
+//! Conversions between quantum circuits and the OpenQASM language, along with
+//! the Qobj format of Qiskit backends.
+
+mod import;
+mod lexer;
+mod parser;
+mod qasm2;
+mod qasm3;
+mod qobj;
+
+pub use crate::parse::{ParseError, ParseErrorKind};
+pub use qasm3::MAX_COMPUTE_BITS;
+pub use qobj::{QOBJ_SCHEMA_VERSION, QobjConfig, to_qobj};
+
+use thiserror::Error;
+
+#[derive(Clone, PartialEq, Eq, Debug, Error)]
+pub enum QasmError {
+    #[error("operation `{0}` cannot be expressed in this version of OpenQASM")]
+    UnsupportedOperation(&'static str),
+    #[error("modifier `{0}` cannot be expressed in this version of OpenQASM")]
+    UnsupportedModifier(&'static str),
+    #[error("formal parameter {0} cannot be expressed in this version of OpenQASM")]
+    FormalParameter(u32),
+    #[error("compute reads {0} bits, more than can be written as a truth table")]
+    ComputeTooLarge(usize),
+}
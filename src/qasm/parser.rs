@@ -0,0 +1,503 @@
+use std::collections::HashMap;
+
+use crate::parse::{Number, ParseError, ParseErrorKind, Pos, Token};
+
+use super::lexer::tokenize;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+/// A real-valued expression, as found in gate parameters.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Expr {
+    Num(Number),
+    Ident(String, Pos),
+    Neg(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+    Call(String, Box<Expr>, Pos),
+}
+
+impl Expr {
+    /// Evaluates the expression, looking up identifiers in `env`.
+    pub(crate) fn eval(&self, env: &HashMap<String, Number>) -> Result<Number, ParseError> {
+        Ok(match self {
+            Self::Num(x) => *x,
+            Self::Ident(name, pos) => *env.get(name)
+                .ok_or_else(|| pos.error(ParseErrorKind::UnknownIdentifier(name.clone())))?,
+            Self::Neg(expr) => -expr.eval(env)?,
+            Self::Binary(op, lhs, rhs) => {
+                let (x, y) = (lhs.eval(env)?, rhs.eval(env)?);
+
+                match op {
+                    BinOp::Add => x + y,
+                    BinOp::Sub => x - y,
+                    BinOp::Mul => x * y,
+                    BinOp::Div => x / y,
+                    BinOp::Pow => x.powf(y),
+                }
+            }
+            Self::Call(func, arg, pos) => {
+                let x = arg.eval(env)?;
+
+                match func.as_str() {
+                    "sin" => x.map(f64::sin),
+                    "cos" => x.map(f64::cos),
+                    "tan" => x.map(f64::tan),
+                    "exp" => x.map(f64::exp),
+                    "ln" => x.map(f64::ln),
+                    "sqrt" => x.map(f64::sqrt),
+                    _ => return Err(pos.error(ParseErrorKind::UnknownIdentifier(func.clone()))),
+                }
+            }
+        })
+    }
+}
+
+/// A register, or a single element of a register.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Arg {
+    pub name: String,
+    pub index: Option<u64>,
+    pub pos: Pos,
+}
+
+/// A quantum operation, which may be conditioned by an `if` statement.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum QuantumOp {
+    Call {
+        name: String,
+        params: Vec<Expr>,
+        args: Vec<Arg>,
+    },
+    Measure {
+        qubit: Arg,
+        bit: Arg,
+    },
+    Reset {
+        qubit: Arg,
+    },
+}
+
+/// The condition of an `if` statement, which compares a whole classical register to an integer.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Condition {
+    pub register: String,
+    pub value: u64,
+    pub pos: Pos,
+}
+
+/// A statement inside the body of a gate definition.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct GateStmt {
+    /// The name of the applied gate, or `barrier`.
+    pub name: String,
+    pub params: Vec<Expr>,
+    pub args: Vec<(String, Pos)>,
+    pub pos: Pos,
+}
+
+/// A gate definition, opaque gates don't have a body.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct GateDef {
+    pub name: String,
+    pub params: Vec<String>,
+    pub qubits: Vec<String>,
+    pub body: Option<Vec<GateStmt>>,
+    pub pos: Pos,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Stmt {
+    Include(String),
+    QReg(String, u64),
+    CReg(String, u64),
+    Gate(GateDef),
+    Barrier(Vec<Arg>),
+    Op {
+        op: QuantumOp,
+        condition: Option<Condition>,
+    },
+}
+
+struct Parser {
+    tokens: Vec<(Token, Pos)>,
+    index: usize,
+}
+
+impl Parser {
+    /// Returns the current token, the last one being repeated past the end.
+    #[inline]
+    fn current(&self) -> &(Token, Pos) {
+        &self.tokens[self.index.min(self.tokens.len() - 1)]
+    }
+
+    #[inline]
+    fn peek(&self) -> &Token {
+        &self.current().0
+    }
+
+    #[inline]
+    fn pos(&self) -> Pos {
+        self.current().1
+    }
+
+    /// Returns the current token and advances, use `self.index -= 1` to go back.
+    #[inline]
+    fn next(&mut self) -> (Token, Pos) {
+        let res = self.current().clone();
+        self.index += 1;
+        res
+    }
+
+    /// Returns an error stating the current token was unexpected.
+    fn unexpected(&self, expected: &str) -> ParseError {
+        self.pos().error(ParseErrorKind::UnexpectedToken {
+            expected: expected.to_owned(),
+            found: self.peek().describe(),
+        })
+    }
+
+    /// Consumes the next token if it is `token`.
+    #[inline]
+    fn eat(&mut self, token: &Token) -> bool {
+        let res = self.peek() == token;
+        if res {
+            self.next();
+        }
+        res
+    }
+
+    fn expect(&mut self, token: Token) -> Result<Pos, ParseError> {
+        if self.peek() == &token {
+            Ok(self.next().1)
+        } else {
+            Err(self.unexpected(&token.describe()))
+        }
+    }
+
+    fn expect_ident(&mut self) -> Result<(String, Pos), ParseError> {
+        match self.next() {
+            (Token::Ident(name), pos) => Ok((name, pos)),
+            _ => {
+                self.index -= 1;
+                Err(self.unexpected("an identifier"))
+            }
+        }
+    }
+
+    fn expect_int(&mut self) -> Result<u64, ParseError> {
+        match self.next() {
+            (Token::Int(n), _) => Ok(n),
+            _ => {
+                self.index -= 1;
+                Err(self.unexpected("an integer"))
+            }
+        }
+    }
+
+    /// Parses a comma separated list of at least one element.
+    fn list<T>(&mut self, mut f: impl FnMut(&mut Self) -> Result<T, ParseError>) -> Result<Vec<T>, ParseError> {
+        let mut res = vec![f(self)?];
+
+        while self.eat(&Token::Comma) {
+            res.push(f(self)?);
+        }
+
+        Ok(res)
+    }
+
+    /// Parses an optional list of parameters in parentheses.
+    fn params<T>(&mut self, f: impl FnMut(&mut Self) -> Result<T, ParseError>) -> Result<Vec<T>, ParseError> {
+        if !self.eat(&Token::LParen) {
+            return Ok(Vec::new());
+        }
+
+        if self.eat(&Token::RParen) {
+            return Ok(Vec::new());
+        }
+
+        let res = self.list(f)?;
+        self.expect(Token::RParen)?;
+        Ok(res)
+    }
+
+    fn arg(&mut self) -> Result<Arg, ParseError> {
+        let (name, pos) = self.expect_ident()?;
+
+        let index = if self.eat(&Token::LBracket) {
+            let index = self.expect_int()?;
+            self.expect(Token::RBracket)?;
+            Some(index)
+        } else {
+            None
+        };
+
+        Ok(Arg { name, index, pos })
+    }
+
+    /// Parses an expression: terms separated by `+` or `-`.
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.term()?;
+
+        loop {
+            let op = match self.peek() {
+                Token::Plus => BinOp::Add,
+                Token::Minus => BinOp::Sub,
+                _ => return Ok(lhs),
+            };
+
+            self.next();
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.term()?));
+        }
+    }
+
+    /// Parses a term: factors separated by `*` or `/`.
+    fn term(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.factor()?;
+
+        loop {
+            let op = match self.peek() {
+                Token::Star => BinOp::Mul,
+                Token::Slash => BinOp::Div,
+                _ => return Ok(lhs),
+            };
+
+            self.next();
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.factor()?));
+        }
+    }
+
+    /// Parses a factor: an optionally negated power.
+    fn factor(&mut self) -> Result<Expr, ParseError> {
+        if self.eat(&Token::Minus) {
+            Ok(Expr::Neg(Box::new(self.factor()?)))
+        } else if self.eat(&Token::Plus) {
+            self.factor()
+        } else {
+            self.power()
+        }
+    }
+
+    /// Parses a power, which is right associative.
+    fn power(&mut self) -> Result<Expr, ParseError> {
+        let base = self.primary()?;
+
+        if self.eat(&Token::Caret) {
+            Ok(Expr::Binary(BinOp::Pow, Box::new(base), Box::new(self.factor()?)))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        match self.next() {
+            (Token::Int(n), _) => Ok(Expr::Num(Number::int(n))),
+            (Token::Real(x), _) => Ok(Expr::Num(x.into())),
+            (Token::Ident(name), _) if name == "pi" => Ok(Expr::Num(Number::PI)),
+            (Token::Ident(name), pos) => {
+                if self.eat(&Token::LParen) {
+                    let arg = self.expr()?;
+                    self.expect(Token::RParen)?;
+                    Ok(Expr::Call(name, Box::new(arg), pos))
+                } else {
+                    Ok(Expr::Ident(name, pos))
+                }
+            }
+            (Token::LParen, _) => {
+                let res = self.expr()?;
+                self.expect(Token::RParen)?;
+                Ok(res)
+            }
+            _ => {
+                self.index -= 1;
+                Err(self.unexpected("an expression"))
+            }
+        }
+    }
+
+    fn header(&mut self) -> Result<(), ParseError> {
+        match self.next() {
+            (Token::Ident(name), _) if name == "OPENQASM" => (),
+            _ => {
+                self.index -= 1;
+                return Err(self.unexpected("`OPENQASM`"));
+            }
+        }
+
+        match self.next() {
+            (Token::Real(2.0), _) => (),
+            (Token::Int(2), _) => (),
+            (Token::Real(_) | Token::Int(_), pos) => return Err(pos.error(ParseErrorKind::UnsupportedVersion)),
+            _ => {
+                self.index -= 1;
+                return Err(self.unexpected("a version number"));
+            }
+        }
+
+        self.expect(Token::Semicolon)?;
+        Ok(())
+    }
+
+    fn gate_def(&mut self, opaque: bool) -> Result<GateDef, ParseError> {
+        let (name, pos) = self.expect_ident()?;
+        let params = self.params(|p| p.expect_ident().map(|(name, _)| name))?;
+        let qubits = self.list(|p| p.expect_ident().map(|(name, _)| name))?;
+
+        if opaque {
+            self.expect(Token::Semicolon)?;
+            return Ok(GateDef { name, params, qubits, body: None, pos });
+        }
+
+        self.expect(Token::LBrace)?;
+        let mut body = Vec::new();
+
+        while !self.eat(&Token::RBrace) {
+            let (name, pos) = self.expect_ident()?;
+            let params = self.params(Self::expr)?;
+            let args = self.list(Self::expect_ident)?;
+            self.expect(Token::Semicolon)?;
+            body.push(GateStmt { name, params, args, pos });
+        }
+
+        Ok(GateDef { name, params, qubits, body: Some(body), pos })
+    }
+
+    fn quantum_op(&mut self) -> Result<QuantumOp, ParseError> {
+        let (name, _) = self.expect_ident()?;
+
+        let res = match name.as_str() {
+            "measure" => {
+                let qubit = self.arg()?;
+                self.expect(Token::Arrow)?;
+                let bit = self.arg()?;
+                QuantumOp::Measure { qubit, bit }
+            }
+            "reset" => QuantumOp::Reset { qubit: self.arg()? },
+            _ => {
+                self.index -= 1;
+                let (name, _) = self.expect_ident()?;
+                let params = self.params(Self::expr)?;
+                let args = self.list(Self::arg)?;
+                QuantumOp::Call { name, params, args }
+            }
+        };
+
+        self.expect(Token::Semicolon)?;
+        Ok(res)
+    }
+
+    fn stmt(&mut self) -> Result<(Stmt, Pos), ParseError> {
+        let pos = self.pos();
+
+        let keyword = match self.peek() {
+            Token::Ident(name) => name.clone(),
+            _ => return Err(self.unexpected("a statement")),
+        };
+
+        let stmt = match keyword.as_str() {
+            "include" => {
+                self.next();
+                let file = match self.next() {
+                    (Token::Str(file), _) => file,
+                    _ => {
+                        self.index -= 1;
+                        return Err(self.unexpected("a file name"));
+                    }
+                };
+                self.expect(Token::Semicolon)?;
+                Stmt::Include(file)
+            }
+            "qreg" | "creg" => {
+                self.next();
+                let (name, _) = self.expect_ident()?;
+                self.expect(Token::LBracket)?;
+                let size = self.expect_int()?;
+                self.expect(Token::RBracket)?;
+                self.expect(Token::Semicolon)?;
+
+                if keyword == "qreg" {
+                    Stmt::QReg(name, size)
+                } else {
+                    Stmt::CReg(name, size)
+                }
+            }
+            "gate" | "opaque" => {
+                self.next();
+                Stmt::Gate(self.gate_def(keyword == "opaque")?)
+            }
+            "barrier" => {
+                self.next();
+                let args = self.list(Self::arg)?;
+                self.expect(Token::Semicolon)?;
+                Stmt::Barrier(args)
+            }
+            "if" => {
+                self.next();
+                self.expect(Token::LParen)?;
+                let (register, pos) = self.expect_ident()?;
+                self.expect(Token::EqEq)?;
+                let value = self.expect_int()?;
+                self.expect(Token::RParen)?;
+
+                Stmt::Op {
+                    op: self.quantum_op()?,
+                    condition: Some(Condition { register, value, pos }),
+                }
+            }
+            _ => Stmt::Op { op: self.quantum_op()?, condition: None },
+        };
+
+        Ok((stmt, pos))
+    }
+}
+
+/// Parses an OpenQASM 2.0 program into a list of statements, along with their positions.
+pub(crate) fn parse(src: &str) -> Result<Vec<(Stmt, Pos)>, ParseError> {
+    let mut parser = Parser { tokens: tokenize(src)?, index: 0 };
+    parser.header()?;
+
+    let mut res = Vec::new();
+
+    while parser.peek() != &Token::Eof {
+        res.push(parser.stmt()?);
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+
+    #[test]
+    fn expressions() {
+        let stmts = parse("OPENQASM 2.0; rx(-pi/2 + 2*3^2) q;").unwrap();
+
+        match &stmts[0].0 {
+            Stmt::Op { op: QuantumOp::Call { params, .. }, .. } => {
+                let x = params[0].eval(&HashMap::new()).unwrap();
+                assert!((x.value - (18.0 - PI / 2.0)).abs() < 1e-12);
+            }
+            stmt => panic!("unexpected statement {:?}", stmt),
+        }
+    }
+
+    #[test]
+    fn syntax_error() {
+        let err = parse("OPENQASM 2.0;\nqreg q[2];\nh q[0]\ncx q[0], q[1];").unwrap_err();
+
+        assert_eq!((err.line, err.column), (4, 1));
+        assert_eq!(err.kind, ParseErrorKind::UnexpectedToken {
+            expected: "`;`".into(),
+            found: "`cx`".into(),
+        });
+    }
+}
@@ -0,0 +1,230 @@
+use std::fmt::Write;
+
+use crate::circuit::QuantumCircuit;
+use crate::circuit::instruction::{Instr, Modifier};
+use crate::circuit::operation::OpKind;
+use crate::circuit::parameter::Parameter;
+use crate::circuit::symbol::Symbol;
+
+use super::QasmError;
+
+/// Definition of the gates that are not part of Qiskit's `qelib1.inc`.
+const ISWAP_DEFINITION: &str = "gate iswap a,b { s a; s b; cz a,b; swap a,b; }\n";
+
+/// Returns the name of the `qelib1.inc` gate corresponding to the operation.
+fn gate_name(op: &OpKind<'_, '_>) -> Result<&'static str, QasmError> {
+    Ok(match op {
+        OpKind::Phase => "u1",
+        OpKind::CP => "cu1",
+        op if op.is_unitary() => op.label(),
+        op => return Err(QasmError::UnsupportedOperation(op.label())),
+    })
+}
+
+/// Writes a comma separated list of parameters, in parentheses.
+fn write_parameters(circ: &QuantumCircuit, out: &mut String, parameters: &[Parameter<'_>]) -> Result<(), QasmError> {
+    if parameters.is_empty() {
+        return Ok(());
+    }
+
+    out.push('(');
+
+    for (i, parameter) in parameters.iter().enumerate() {
+        let value = circ.evaluate(*parameter, &[]).map_err(QasmError::FormalParameter)?;
+
+        if i != 0 {
+            out.push(',');
+        }
+
+        match parameter.angle_string("pi") {
+            Some(angle) => out.push_str(&angle),
+            None => write!(out, "{}", value as f32).unwrap(),
+        }
+    }
+
+    out.push(')');
+    Ok(())
+}
+
+/// Writes a single instruction. Bits are written as `c[i]` if `split_bits` is false,
+/// and as `ci[0]` otherwise.
+fn write_instr(circ: &QuantumCircuit, out: &mut String, instr: &Instr<'_, '_>, split_bits: bool) -> Result<(), QasmError> {
+    let bit = |id: u32| if split_bits { format!("c{}[0]", id) } else { format!("c[{}]", id) };
+
+    let prefix = match &instr.modifier {
+        None => String::new(),
+        Some(Modifier::IfBit(b)) => format!("if(c{}==1) ", b.id()),
+        Some(Modifier::IfCompute(_)) => return Err(QasmError::UnsupportedModifier("if")),
+        Some(Modifier::WhileBit(_) | Modifier::WhileCompute(_)) => return Err(QasmError::UnsupportedModifier("while")),
+        Some(Modifier::ForConst(_) | Modifier::ForCompute(_)) => return Err(QasmError::UnsupportedModifier("for")),
+
+        Some(modifier) => return Err(QasmError::UnsupportedModifier(modifier.label())),
+    };
+
+    let qubits = instr.qubits.iter().map(|q| format!("q[{}]", q.id())).collect::<Vec<_>>();
+
+    match &instr.op {
+        OpKind::Nop => (),
+        OpKind::Measure => {
+            for (q, b) in qubits.iter().zip(instr.bits) {
+                writeln!(out, "{}measure {} -> {};", prefix, q, bit(b.id())).unwrap();
+            }
+        }
+        OpKind::Reset => {
+            for q in &qubits {
+                writeln!(out, "{}reset {};", prefix, q).unwrap();
+            }
+        }
+        OpKind::Barrier => {
+            if !qubits.is_empty() {
+                writeln!(out, "{}barrier {};", prefix, qubits.join(",")).unwrap();
+            }
+        }
+        op => {
+            out.push_str(&prefix);
+            out.push_str(gate_name(op)?);
+            write_parameters(circ, out, instr.parameters)?;
+            writeln!(out, " {};", qubits.join(",")).unwrap();
+        }
+    }
+
+    Ok(())
+}
+
+impl QuantumCircuit {
+    /// Returns the circuit as an OpenQASM 2.0 program, using the gates of Qiskit's
+    /// `qelib1.inc`, which extends the original one with gates such as `sx`, `swap`,
+    /// `cswap`, `rxx` and `rzz`. The `iswap` gate is defined in the program.
+    ///
+    /// Qubits are declared in a single register `q`. Bits are declared in a single
+    /// register `c`, unless an instruction is conditioned by a bit: since OpenQASM 2.0
+    /// can only compare whole registers, each bit $i$ is then declared in it's own
+    /// register `ci`, and the `IfBit` modifier is written as `if(ci==1)`.
+    ///
+    /// Fails on compute nodes, on modifiers other than `IfBit`, and on formal parameters. Quantum
+    /// modifiers can be replaced by basic gates with [`QuantumCircuit::expand_modifiers`].
+    pub fn to_qasm2(&self) -> Result<String, QasmError> {
+        let mut split_bits = false;
+        let mut iswap = false;
+        let mut iter = self.instructions();
+
+        while let Some(instr) = iter.next() {
+            split_bits |= matches!(instr.modifier, Some(Modifier::IfBit(_)));
+            iswap |= matches!(instr.op, OpKind::ISwap);
+        }
+
+        let mut out = String::from("OPENQASM 2.0;\ninclude \"qelib1.inc\";\n");
+
+        if iswap {
+            out.push_str(ISWAP_DEFINITION);
+        }
+
+        if self.qubit_count() != 0 {
+            writeln!(out, "qreg q[{}];", self.qubit_count()).unwrap();
+        }
+
+        if split_bits {
+            (0..self.bit_count()).for_each(|i| writeln!(out, "creg c{}[1];", i).unwrap());
+        } else if self.bit_count() != 0 {
+            writeln!(out, "creg c[{}];", self.bit_count()).unwrap();
+        }
+
+        let mut iter = self.instructions();
+
+        while let Some(instr) = iter.next() {
+            write_instr(self, &mut out, instr, split_bits)?;
+        }
+
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::instruction::{Compute, Instr, Modifier};
+    use crate::circuit::operation::OpKind;
+    use crate::circuit::symbol::FormalParameter;
+
+    use super::*;
+
+    #[test]
+    fn bell() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+            let [b1, b2] = circ.alloc_n()?;
+            circ.h(q1).cx(q1, q2).phase(0.5, q2).barrier(&[q1, q2]).measure(&[q1, q2], &[b1, b2]);
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(circ.to_qasm2().unwrap(), "\
+OPENQASM 2.0;
+include \"qelib1.inc\";
+qreg q[2];
+creg c[2];
+h q[0];
+cx q[0],q[1];
+u1(0.5) q[1];
+barrier q[0],q[1];
+measure q[0] -> c[0];
+measure q[1] -> c[1];
+");
+    }
+
+    #[test]
+    fn if_bit() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+            let [b1, b2] = circ.alloc_n()?;
+            circ.measure(&[q1], &[b1]);
+            circ.push(Instr {
+                op: OpKind::RX,
+                qubits: &[q2],
+                parameters: &[(-1.5).into()],
+                modifier: Some(Modifier::IfBit(b1)),
+                ..Default::default()
+            })?;
+            circ.measure(&[q2], &[b2]);
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(circ.to_qasm2().unwrap(), "\
+OPENQASM 2.0;
+include \"qelib1.inc\";
+qreg q[2];
+creg c0[1];
+creg c1[1];
+measure q[0] -> c0[0];
+if(c0==1) rx(-1.5) q[1];
+measure q[1] -> c1[0];
+");
+    }
+
+    #[test]
+    fn errors() {
+        let circ = QuantumCircuit::new(|circ| {
+            let b = circ.alloc()?;
+            circ.compute(&[b], Compute { bits: &[], func: |bits| bits });
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(circ.to_qasm2(), Err(QasmError::UnsupportedOperation("compute")));
+
+        let circ = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            let theta: FormalParameter = circ.alloc()?;
+            circ.rz(theta, q);
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(circ.to_qasm2(), Err(QasmError::FormalParameter(0)));
+
+        let circ = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            let b = circ.alloc()?;
+            circ.push(Instr { op: OpKind::X, qubits: &[q], modifier: Some(Modifier::WhileBit(b)), ..Default::default() })?;
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(circ.to_qasm2(), Err(QasmError::UnsupportedModifier("while")));
+    }
+}
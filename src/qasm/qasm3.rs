@@ -0,0 +1,437 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::bitset::BitSet;
+use crate::circuit::QuantumCircuit;
+use crate::circuit::instruction::{Compute, Instr, Modifier};
+use crate::circuit::operation::OpKind;
+use crate::circuit::parameter::Parameter;
+use crate::circuit::symbol::{Bit, Symbol};
+
+use super::QasmError;
+
+/// Definition of the gates that are not part of `stdgates.inc`.
+const DEFINITIONS: [(&str, &str); 3] = [
+    ("iswap", "gate iswap a, b { s a; s b; cz a, b; swap a, b; }\n"),
+    ("rxx", "gate rxx(theta) a, b { h a; h b; cx a, b; rz(theta) b; cx a, b; h a; h b; }\n"),
+    ("rzz", "gate rzz(theta) a, b { cx a, b; rz(theta) b; cx a, b; }\n"),
+];
+
+/// The maximum number of bits a compute can read. Computes are written as
+/// their truth table, whose size is exponential in the number of bits.
+pub const MAX_COMPUTE_BITS: usize = 10;
+
+/// Returns the name of the `stdgates.inc` gate corresponding to the operation.
+fn gate_name(op: &OpKind<'_, '_>) -> Result<&'static str, QasmError> {
+    Ok(match op {
+        OpKind::Phase => "p",
+        OpKind::CP => "cp",
+        op if op.is_unitary() => op.label(),
+        op => return Err(QasmError::UnsupportedOperation(op.label())),
+    })
+}
+
+/// Writes a parameter, formal parameters being named `thetai`.
+fn parameter(circ: &QuantumCircuit, parameter: &Parameter<'_>) -> String {
+    if let Some(expr) = circ.expression(*parameter) {
+        return expr.to_string();
+    }
+
+    if let Some(angle) = parameter.angle_string("pi") {
+        return angle;
+    }
+
+    match parameter.as_formal() {
+        Some(formal) => format!("theta{}", formal.id()),
+        None => parameter.as_value().unwrap().to_string(),
+    }
+}
+
+/// Returns the inputs of a compute, as `BitSet`s, along with their integer value.
+fn inputs<T>(compute: &Compute<'_, '_, T>) -> Result<impl Iterator<Item = (u64, BitSet)>, QasmError> {
+    let len = compute.bits.len();
+
+    if len > MAX_COMPUTE_BITS {
+        return Err(QasmError::ComputeTooLarge(len));
+    }
+
+    Ok((0..1 << len).map(move |n| (n, BitSet::from_integer(len, n))))
+}
+
+/// Returns a boolean expression true when the bits are equal to one of the given integers.
+fn condition(bits: &[Bit<'_>], values: &[u64]) -> String {
+    if values.is_empty() {
+        return "false".into();
+    }
+
+    if values.len() == 1 << bits.len() {
+        return "true".into();
+    }
+
+    let term = |n: u64| {
+        let literals: Vec<_> = bits.iter()
+            .enumerate()
+            .map(|(i, b)| format!("{}c[{}]", if n >> i & 1 == 1 { "" } else { "!" }, b.id()))
+            .collect();
+
+        if literals.len() > 1 && values.len() > 1 {
+            format!("({})", literals.join(" && "))
+        } else {
+            literals.join(" && ")
+        }
+    };
+
+    values.iter().map(|&n| term(n)).collect::<Vec<_>>().join(" || ")
+}
+
+/// Returns a boolean expression equal to the result of the compute.
+fn predicate(compute: &Compute<'_, '_, bool>) -> Result<String, QasmError> {
+    let values: Vec<_> = inputs(compute)?
+        .filter(|(_, bits)| (compute.func)(bits.clone()))
+        .map(|(n, _)| n)
+        .collect();
+
+    Ok(condition(compute.bits, &values))
+}
+
+/// Writes the lines of the operation of an instruction, ignoring it's modifier unless it is a
+/// quantum one.
+fn write_op(circ: &QuantumCircuit, lines: &mut Vec<String>, instr: &Instr<'_, '_>) -> Result<(), QasmError> {
+    let qubits = instr.qubits.iter().map(|q| format!("q[{}]", q.id())).collect::<Vec<_>>();
+
+    match &instr.op {
+        OpKind::Nop => (),
+        OpKind::Measure => {
+            for (q, b) in qubits.iter().zip(instr.bits) {
+                lines.push(format!("c[{}] = measure {};", b.id(), q));
+            }
+        }
+        OpKind::Reset => {
+            for q in &qubits {
+                lines.push(format!("reset {};", q));
+            }
+        }
+        OpKind::Barrier => {
+            if !qubits.is_empty() {
+                lines.push(format!("barrier {};", qubits.join(", ")));
+            }
+        }
+        OpKind::Compute(compute) => {
+            // The results are stored in temporaries first, since the outputs may be read by the compute.
+            let mut outputs = vec![Vec::new(); instr.bits.len()];
+
+            for (n, bits) in inputs(compute)? {
+                let res = (compute.func)(bits);
+
+                for (i, output) in outputs.iter_mut().enumerate() {
+                    if res.get(i).unwrap_or(false) {
+                        output.push(n);
+                    }
+                }
+            }
+
+            for (i, values) in outputs.iter().enumerate() {
+                lines.push(format!("tmp[{}] = {};", i, condition(compute.bits, values)));
+            }
+
+            for (i, b) in instr.bits.iter().enumerate() {
+                lines.push(format!("c[{}] = tmp[{}];", b.id(), i));
+            }
+        }
+        op => {
+            let mut line = String::new();
+            let mut controls = Vec::new();
+
+            match &instr.modifier {
+                Some(Modifier::Control(list)) => {
+                    write!(line, "ctrl({}) @ ", list.len()).unwrap();
+                    controls.extend(list.iter());
+                }
+                Some(Modifier::NegControl(list)) => {
+                    write!(line, "negctrl({}) @ ", list.len()).unwrap();
+                    controls.extend(list.iter());
+                }
+                Some(Modifier::Pow(exponent)) => write!(line, "pow({}) @ ", parameter(circ, exponent)).unwrap(),
+                Some(Modifier::Inverse) => line.push_str("inv @ "),
+                _ => (),
+            }
+
+            line.push_str(gate_name(op)?);
+
+            // The control qubits come first in the operands of a controlled gate.
+            let qubits: Vec<_> = controls.iter().map(|q| format!("q[{}]", q.id())).chain(qubits).collect();
+
+            if !instr.parameters.is_empty() {
+                let parameters: Vec<_> = instr.parameters.iter().map(|p| parameter(circ, p)).collect();
+                write!(line, "({})", parameters.join(", ")).unwrap();
+            }
+
+            write!(line, " {};", qubits.join(", ")).unwrap();
+            lines.push(line);
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes a block, opened by the given header.
+fn write_block(out: &mut String, header: &str, body: &[String]) {
+    writeln!(out, "{} {{", header).unwrap();
+
+    for line in body {
+        writeln!(out, "    {}", line).unwrap();
+    }
+
+    out.push_str("}\n");
+}
+
+/// Writes a single instruction, along with the control flow of it's modifier.
+fn write_instr(circ: &QuantumCircuit, out: &mut String, instr: &Instr<'_, '_>) -> Result<(), QasmError> {
+    let mut body = Vec::new();
+    write_op(circ, &mut body, instr)?;
+
+    if body.is_empty() {
+        return Ok(());
+    }
+
+    match &instr.modifier {
+        // Quantum modifiers are written as part of the gate, see `write_op`.
+        None | Some(Modifier::Control(_) | Modifier::NegControl(_) | Modifier::Pow(_) | Modifier::Inverse) => {
+            body.iter().for_each(|line| writeln!(out, "{}", line).unwrap());
+        }
+        Some(Modifier::IfBit(b)) => write_block(out, &format!("if (c[{}])", b.id()), &body),
+        Some(Modifier::IfCompute(compute)) => write_block(out, &format!("if ({})", predicate(compute)?), &body),
+        Some(Modifier::WhileBit(b)) => write_block(out, &format!("while (c[{}])", b.id()), &body),
+        Some(Modifier::WhileCompute(compute)) => write_block(out, &format!("while ({})", predicate(compute)?), &body),
+        Some(Modifier::ForConst(n)) => write_block(out, &format!("for uint i in [1:{}]", n), &body),
+        Some(Modifier::ForCompute(compute)) => {
+            // The number of iterations is computed once, before the loop.
+            let mut counts = BTreeMap::<u32, Vec<u64>>::new();
+
+            for (n, bits) in inputs(compute)? {
+                counts.entry((compute.func)(bits)).or_default().push(n);
+            }
+
+            writeln!(out, "count = 0;").unwrap();
+
+            for (count, values) in counts.iter().filter(|(&count, _)| count != 0) {
+                write_block(out, &format!("if ({})", condition(compute.bits, values)), &[format!("count = {};", count)]);
+            }
+
+            write_block(out, "for uint i in [1:count]", &body);
+        }
+    }
+
+    Ok(())
+}
+
+impl QuantumCircuit {
+    /// Returns the circuit as an OpenQASM 3 program, using the gates of `stdgates.inc`.
+    ///
+    /// Qubits are declared in a register `q` and bits in a register `c`. Formal parameters
+    /// are declared as `input angle thetai`. Classical modifiers are written as `if`, `while`
+    /// and `for` blocks, and quantum modifiers as `ctrl @`, `negctrl @`, `pow @` and `inv @`.
+    ///
+    /// Computes are written as their truth table, and fail if they read more than
+    /// [`MAX_COMPUTE_BITS`] bits. Compute operations store their results in a `tmp`
+    /// register before assigning them.
+    pub fn to_qasm3(&self) -> Result<String, QasmError> {
+        let mut gates = [false; DEFINITIONS.len()];
+        let mut tmp = 0;
+        let mut count = false;
+        let mut iter = self.instructions();
+
+        while let Some(instr) = iter.next() {
+            if let Some(i) = DEFINITIONS.iter().position(|(name, _)| *name == instr.op.label()) {
+                gates[i] = true;
+            }
+
+            if let OpKind::Compute(_) = instr.op {
+                tmp = tmp.max(instr.bits.len());
+            }
+
+            count |= matches!(instr.modifier, Some(Modifier::ForCompute(_)));
+        }
+
+        let mut out = String::from("OPENQASM 3.0;\ninclude \"stdgates.inc\";\n");
+
+        for ((_, definition), used) in DEFINITIONS.iter().zip(gates) {
+            if used {
+                out.push_str(definition);
+            }
+        }
+
+        for i in 0..self.parameter_count() {
+            writeln!(out, "input angle theta{};", i).unwrap();
+        }
+
+        if self.qubit_count() != 0 {
+            writeln!(out, "qubit[{}] q;", self.qubit_count()).unwrap();
+        }
+
+        if self.bit_count() != 0 {
+            writeln!(out, "bit[{}] c;", self.bit_count()).unwrap();
+        }
+
+        if tmp != 0 {
+            writeln!(out, "bit[{}] tmp;", tmp).unwrap();
+        }
+
+        if count {
+            writeln!(out, "uint[32] count;").unwrap();
+        }
+
+        let mut iter = self.instructions();
+
+        while let Some(instr) = iter.next() {
+            write_instr(self, &mut out, instr)?;
+        }
+
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::symbol::FormalParameter;
+
+    use super::*;
+
+    #[test]
+    fn bell() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+            let [b1, b2] = circ.alloc_n()?;
+            let theta: FormalParameter = circ.alloc()?;
+            circ.h(q1).cx(q1, q2).rzz(theta, q1, q2).barrier(&[q1, q2]).measure(&[q1, q2], &[b1, b2]);
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(circ.to_qasm3().unwrap(), "\
+OPENQASM 3.0;
+include \"stdgates.inc\";
+gate rzz(theta) a, b { cx a, b; rz(theta) b; cx a, b; }
+input angle theta0;
+qubit[2] q;
+bit[2] c;
+h q[0];
+cx q[0], q[1];
+rzz(theta0) q[0], q[1];
+barrier q[0], q[1];
+c[0] = measure q[0];
+c[1] = measure q[1];
+");
+    }
+
+    #[test]
+    fn control_flow() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+            let [b1, b2] = circ.alloc_n()?;
+            circ.measure(&[q1], &[b1]);
+            circ.push(Instr { op: OpKind::X, qubits: &[q2], modifier: Some(Modifier::IfBit(b1)), ..Default::default() })?;
+            circ.push(Instr {
+                op: OpKind::Measure,
+                qubits: &[q2],
+                bits: &[b2],
+                modifier: Some(Modifier::WhileBit(b2)),
+                ..Default::default()
+            })?;
+            circ.push(Instr { op: OpKind::H, qubits: &[q1], modifier: Some(Modifier::ForConst(3)), ..Default::default() })?;
+            circ.push(Instr {
+                op: OpKind::Z,
+                qubits: &[q1],
+                modifier: Some(Modifier::IfCompute(Compute { bits: &[b1, b2], func: |bits| bits.count_ones() == 1 })),
+                ..Default::default()
+            })?;
+            circ.push(Instr {
+                op: OpKind::S,
+                qubits: &[q2],
+                modifier: Some(Modifier::ForCompute(Compute { bits: &[b2], func: |bits| 2 * bits.count_ones() as u32 })),
+                ..Default::default()
+            })?;
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(circ.to_qasm3().unwrap(), "\
+OPENQASM 3.0;
+include \"stdgates.inc\";
+qubit[2] q;
+bit[2] c;
+uint[32] count;
+c[0] = measure q[0];
+if (c[0]) {
+    x q[1];
+}
+while (c[1]) {
+    c[1] = measure q[1];
+}
+for uint i in [1:3] {
+    h q[0];
+}
+if ((c[0] && !c[1]) || (!c[0] && c[1])) {
+    z q[0];
+}
+count = 0;
+if (c[1]) {
+    count = 2;
+}
+for uint i in [1:count] {
+    s q[1];
+}
+");
+    }
+
+    #[test]
+    fn modifiers() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+            let controls = circ.alloc_list(2)?;
+            let theta: FormalParameter = circ.alloc()?;
+            circ.push(Instr { op: OpKind::CX, qubits: &[q1, q2], modifier: Some(Modifier::Control(controls.clone())), ..Default::default() })?;
+            circ.push(Instr { op: OpKind::X, qubits: &[q1], modifier: Some(Modifier::NegControl(controls)), ..Default::default() })?;
+            circ.push(Instr { op: OpKind::H, qubits: &[q2], modifier: Some(Modifier::Pow(theta.into())), ..Default::default() })?;
+            circ.push(Instr { op: OpKind::RX, qubits: &[q1], parameters: &[0.5.into()], modifier: Some(Modifier::Inverse), ..Default::default() })?;
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(circ.to_qasm3().unwrap(), "\
+OPENQASM 3.0;
+include \"stdgates.inc\";
+input angle theta0;
+qubit[4] q;
+ctrl(2) @ cx q[2], q[3], q[0], q[1];
+negctrl(2) @ x q[2], q[3], q[0];
+pow(theta0) @ h q[1];
+inv @ rx(0.5) q[0];
+");
+    }
+
+    #[test]
+    fn compute() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [b1, b2] = circ.alloc_n()?;
+            circ.compute(&[b1, b2], Compute { bits: &[b1, b2], func: |bits| bits.iter().collect::<Vec<_>>().into_iter().rev().collect() });
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(circ.to_qasm3().unwrap(), "\
+OPENQASM 3.0;
+include \"stdgates.inc\";
+bit[2] c;
+bit[2] tmp;
+tmp[0] = (!c[0] && c[1]) || (c[0] && c[1]);
+tmp[1] = (c[0] && !c[1]) || (c[0] && c[1]);
+c[0] = tmp[0];
+c[1] = tmp[1];
+");
+
+        let circ = QuantumCircuit::new(|circ| {
+            let bits = circ.alloc_list(MAX_COMPUTE_BITS + 1)?;
+            circ.compute(&[], Compute { bits: &bits.iter().collect::<Vec<_>>(), func: |bits| bits });
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(circ.to_qasm3(), Err(QasmError::ComputeTooLarge(MAX_COMPUTE_BITS + 1)));
+    }
+}
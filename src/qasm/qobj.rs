@@ -0,0 +1,305 @@
+use std::fmt::Write;
+
+use crate::circuit::QuantumCircuit;
+use crate::circuit::instruction::{Instr, Modifier};
+use crate::circuit::operation::OpKind;
+use crate::circuit::symbol::Symbol;
+
+use super::QasmError;
+
+/// The version of the Qobj schema the exporter follows.
+pub const QOBJ_SCHEMA_VERSION: &str = "1.3.0";
+
+/// The configuration shared by all experiments of a Qobj.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct QobjConfig {
+    /// The identifier of the Qobj.
+    pub qobj_id: String,
+    /// The name of the backend the Qobj is meant for, written in the header.
+    pub backend_name: Option<String>,
+    /// The number of times each experiment is run.
+    pub shots: usize,
+    /// Whether or not the backend should return the result of each shot.
+    pub memory: bool,
+    /// The seed of the simulator, if any.
+    pub seed: Option<u64>,
+}
+
+impl Default for QobjConfig {
+    fn default() -> Self {
+        Self {
+            qobj_id: "trident".into(),
+            backend_name: None,
+            shots: 1024,
+            memory: false,
+            seed: None,
+        }
+    }
+}
+
+/// Returns a JSON string literal.
+fn string(s: &str) -> String {
+    let mut out = String::from('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+/// Returns a JSON array of the given elements.
+fn array<T: ToString>(elements: impl IntoIterator<Item = T>) -> String {
+    format!("[{}]", elements.into_iter().map(|e| e.to_string()).collect::<Vec<_>>().join(","))
+}
+
+/// Returns the hexadecimal string of the integer whose only set bit is `bit`.
+fn mask(bit: u32) -> String {
+    format!("0x{}{}", 1 << (bit % 4), "0".repeat(bit as usize / 4))
+}
+
+/// Returns the name of the Qiskit instruction corresponding to the operation.
+fn gate_name(op: &OpKind<'_, '_>) -> Result<&'static str, QasmError> {
+    Ok(match op {
+        OpKind::Phase => "u1",
+        OpKind::CP => "cu1",
+        op if op.is_unitary() => op.label(),
+        op => return Err(QasmError::UnsupportedOperation(op.label())),
+    })
+}
+
+/// Writes the instructions corresponding to a single instruction of the circuit. The register
+/// slot `condition` is used to store the value of the bit conditioning the instruction, if any.
+fn write_instr(circ: &QuantumCircuit, instrs: &mut Vec<String>, instr: &Instr<'_, '_>, condition: u32, registers: bool) -> Result<(), QasmError> {
+    let conditional = match &instr.modifier {
+        None => String::new(),
+        Some(Modifier::IfBit(b)) => {
+            instrs.push(format!(
+                "{{\"name\":\"bfunc\",\"mask\":\"{0}\",\"relation\":\"==\",\"val\":\"{0}\",\"register\":{1}}}",
+                mask(b.id()),
+                condition,
+            ));
+            format!(",\"conditional\":{}", condition)
+        }
+        Some(Modifier::IfCompute(_)) => return Err(QasmError::UnsupportedModifier("if")),
+        Some(Modifier::WhileBit(_) | Modifier::WhileCompute(_)) => return Err(QasmError::UnsupportedModifier("while")),
+        Some(Modifier::ForConst(_) | Modifier::ForCompute(_)) => return Err(QasmError::UnsupportedModifier("for")),
+
+        Some(modifier) => return Err(QasmError::UnsupportedModifier(modifier.label())),
+    };
+
+    let qubits: Vec<_> = instr.qubits.iter().map(|q| q.id()).collect();
+    let mut gate = |name: &str, qubits: &[u32], params: &str| {
+        instrs.push(format!("{{\"name\":{},\"qubits\":{}{}{}}}", string(name), array(qubits), params, conditional));
+    };
+
+    match &instr.op {
+        OpKind::Nop => (),
+        OpKind::Measure => {
+            for (&q, b) in qubits.iter().zip(instr.bits) {
+                let slots = if registers {
+                    format!(",\"memory\":[{0}],\"register\":[{0}]", b.id())
+                } else {
+                    format!(",\"memory\":[{}]", b.id())
+                };
+                gate("measure", &[q], &slots);
+            }
+        }
+        OpKind::Reset => qubits.iter().for_each(|&q| gate("reset", &[q], "")),
+        OpKind::Barrier => {
+            if !qubits.is_empty() {
+                gate("barrier", &qubits, "");
+            }
+        }
+        // Qiskit backends do not know of the iswap gate.
+        OpKind::ISwap => {
+            gate("s", &qubits[..1], "");
+            gate("s", &qubits[1..], "");
+            gate("cz", &qubits, "");
+            gate("swap", &qubits, "");
+        }
+        op => {
+            let name = gate_name(op)?;
+            let params = instr.parameters.iter()
+                .map(|&p| circ.evaluate(p, &[]).map(|value| value as f32).map_err(QasmError::FormalParameter))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            if params.is_empty() {
+                gate(name, &qubits, "");
+            } else {
+                gate(name, &qubits, &format!(",\"params\":{}", array(params)));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns a single experiment of a Qobj.
+fn experiment(circ: &QuantumCircuit, name: &str) -> Result<String, QasmError> {
+    let (qubits, bits) = (circ.qubit_count(), circ.bit_count());
+    let mut registers = false;
+    let mut iter = circ.instructions();
+
+    while let Some(instr) = iter.next() {
+        registers |= matches!(instr.modifier, Some(Modifier::IfBit(_)));
+    }
+
+    let mut instrs = Vec::new();
+    let mut iter = circ.instructions();
+
+    while let Some(instr) = iter.next() {
+        write_instr(circ, &mut instrs, instr, bits as u32, registers)?;
+    }
+
+    let labels = |register: &str, len: usize| array((0..len).map(|i| format!("[\"{}\",{}]", register, i)));
+    let sizes = |register: &str, len: usize| if len == 0 { "[]".to_owned() } else { format!("[[\"{}\",{}]]", register, len) };
+
+    let mut header = format!("{{\"name\":{},\"n_qubits\":{},\"memory_slots\":{}", string(name), qubits, bits);
+    write!(header, ",\"qubit_labels\":{},\"clbit_labels\":{}", labels("q", qubits), labels("c", bits)).unwrap();
+    write!(header, ",\"qreg_sizes\":{},\"creg_sizes\":{}}}", sizes("q", qubits), sizes("c", bits)).unwrap();
+
+    let mut config = format!("{{\"n_qubits\":{},\"memory_slots\":{}", qubits, bits);
+
+    if registers {
+        write!(config, ",\"n_registers\":{}", bits + 1).unwrap();
+    }
+
+    config.push('}');
+
+    Ok(format!("{{\"header\":{},\"config\":{},\"instructions\":{}}}", header, config, array(instrs)))
+}
+
+/// Returns the circuits as a Qobj of type `QASM`, following the Qiskit backend specification.
+/// The experiments are named `circuit-i`, in order, and have the same layout as the circuits:
+/// a single quantum register `q` and a single classical register `c`.
+///
+/// Measurements are stored in the memory slot of their bit. If an instruction is conditioned
+/// by a bit, measurements are also stored in the register slot of their bit, and the condition
+/// is evaluated in an extra register slot by a `bfunc` instruction. The `iswap` gate is
+/// decomposed, since Qiskit backends do not know of it.
+///
+/// Fails on the same instructions as [`QuantumCircuit::to_qasm2`].
+pub fn to_qobj<'c>(circuits: impl IntoIterator<Item = &'c QuantumCircuit>, config: &QobjConfig) -> Result<String, QasmError> {
+    let mut experiments = Vec::new();
+    let (mut qubits, mut bits) = (0, 0);
+
+    for (i, circ) in circuits.into_iter().enumerate() {
+        experiments.push(experiment(circ, &format!("circuit-{}", i))?);
+        qubits = qubits.max(circ.qubit_count());
+        bits = bits.max(circ.bit_count());
+    }
+
+    let header = match &config.backend_name {
+        Some(name) => format!("{{\"backend_name\":{}}}", string(name)),
+        None => "{}".to_owned(),
+    };
+
+    let mut out = format!("{{\"qobj_id\":{},\"type\":\"QASM\",\"schema_version\":\"{}\"", string(&config.qobj_id), QOBJ_SCHEMA_VERSION);
+    write!(out, ",\"header\":{}", header).unwrap();
+    write!(out, ",\"config\":{{\"shots\":{},\"memory\":{}", config.shots, config.memory).unwrap();
+    write!(out, ",\"memory_slots\":{},\"n_qubits\":{}", bits, qubits).unwrap();
+
+    if let Some(seed) = config.seed {
+        write!(out, ",\"seed_simulator\":{}", seed).unwrap();
+    }
+
+    write!(out, "}},\"experiments\":{}}}", array(experiments)).unwrap();
+    Ok(out)
+}
+
+impl QuantumCircuit {
+    /// Returns the circuit as a Qobj with a single experiment. See [`to_qobj`] for details.
+    #[inline]
+    pub fn to_qobj(&self, config: &QobjConfig) -> Result<String, QasmError> {
+        to_qobj([self], config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::symbol::FormalParameter;
+
+    use super::*;
+
+    #[test]
+    fn bell() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+            let [b1, b2] = circ.alloc_n()?;
+            circ.h(q1).cx(q1, q2).phase(0.5, q2).measure(&[q1, q2], &[b1, b2]);
+            Ok(())
+        }).unwrap();
+
+        let config = QobjConfig { backend_name: Some("qasm_simulator".into()), seed: Some(7), ..Default::default() };
+
+        assert_eq!(circ.to_qobj(&config).unwrap(), concat!(
+            r#"{"qobj_id":"trident","type":"QASM","schema_version":"1.3.0","header":{"backend_name":"qasm_simulator"},"#,
+            r#""config":{"shots":1024,"memory":false,"memory_slots":2,"n_qubits":2,"seed_simulator":7},"#,
+            r#""experiments":[{"header":{"name":"circuit-0","n_qubits":2,"memory_slots":2,"#,
+            r#""qubit_labels":[["q",0],["q",1]],"clbit_labels":[["c",0],["c",1]],"qreg_sizes":[["q",2]],"creg_sizes":[["c",2]]},"#,
+            r#""config":{"n_qubits":2,"memory_slots":2},"instructions":["#,
+            r#"{"name":"h","qubits":[0]},{"name":"cx","qubits":[0,1]},{"name":"u1","qubits":[1],"params":[0.5]},"#,
+            r#"{"name":"measure","qubits":[0],"memory":[0]},{"name":"measure","qubits":[1],"memory":[1]}]}]}"#,
+        ));
+    }
+
+    #[test]
+    fn batch() {
+        let first = QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+            let [b1, b2, b3, b4, b5] = circ.alloc_n()?;
+            circ.measure(&[q1, q2], &[b1, b5]);
+            circ.push(Instr { op: OpKind::X, qubits: &[q2], modifier: Some(Modifier::IfBit(b5)), ..Default::default() })?;
+            circ.measure(&[q2, q1, q2], &[b2, b3, b4]);
+            Ok(())
+        }).unwrap();
+
+        let second = QuantumCircuit::new(|circ| {
+            let [q1, q2, q3] = circ.alloc_n()?;
+            circ.iswap(q1, q3).barrier(&[q1, q2, q3]);
+            Ok(())
+        }).unwrap();
+
+        let qobj = to_qobj([&first, &second], &QobjConfig { memory: true, ..Default::default() }).unwrap();
+
+        assert!(qobj.contains(r#""config":{"shots":1024,"memory":true,"memory_slots":5,"n_qubits":3}"#));
+        assert!(qobj.contains(r#"{"name":"measure","qubits":[1],"memory":[4],"register":[4]}"#));
+        assert!(qobj.contains(concat!(
+            r#"{"name":"bfunc","mask":"0x10","relation":"==","val":"0x10","register":5},"#,
+            r#"{"name":"x","qubits":[1],"conditional":5}"#,
+        )));
+        assert!(qobj.contains(r#""config":{"n_qubits":2,"memory_slots":5,"n_registers":6}"#));
+        assert!(qobj.contains(concat!(
+            r#""name":"circuit-1","n_qubits":3,"memory_slots":0,"#,
+            r#""qubit_labels":[["q",0],["q",1],["q",2]],"clbit_labels":[],"qreg_sizes":[["q",3]],"creg_sizes":[]"#,
+        )));
+        assert!(qobj.contains(concat!(
+            r#"[{"name":"s","qubits":[0]},{"name":"s","qubits":[2]},{"name":"cz","qubits":[0,2]},"#,
+            r#"{"name":"swap","qubits":[0,2]},{"name":"barrier","qubits":[0,1,2]}]"#,
+        )));
+    }
+
+    #[test]
+    fn errors() {
+        let circ = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            let theta: FormalParameter = circ.alloc()?;
+            circ.rx(theta, q);
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(circ.to_qobj(&QobjConfig::default()), Err(QasmError::FormalParameter(0)));
+        assert_eq!(string("a\"b\\\n"), r#""a\"b\\\n""#);
+        assert_eq!(mask(0), "0x1");
+        assert_eq!(mask(6), "0x40");
+        assert_eq!(mask(9), "0x200");
+    }
+}
@@ -0,0 +1,293 @@
+use std::fmt::Write;
+
+use crate::circuit::QuantumCircuit;
+use crate::circuit::instruction::{Instr, Modifier};
+use crate::circuit::operation::OpKind;
+use crate::circuit::parameter::Parameter;
+use crate::circuit::symbol::Symbol;
+
+use super::QuilError;
+
+/// Writes a parameter, formal parameters being elements of the `theta` region.
+fn parameter(circ: &QuantumCircuit, parameter: &Parameter<'_>) -> String {
+    if let Some(expr) = circ.expression(*parameter) {
+        let mut out = String::new();
+        expr.write(&mut out, &|id| format!("theta[{}]", id), ["SIN", "COS"]);
+        return out;
+    }
+
+    if let Some(angle) = parameter.angle_string("pi") {
+        return angle;
+    }
+
+    match parameter.as_formal() {
+        Some(formal) => format!("theta[{}]", formal.id()),
+        None => parameter.as_value().unwrap().to_string(),
+    }
+}
+
+/// Writes a gate, controlled by the given qubits.
+fn write_gate(lines: &mut Vec<String>, name: &str, params: &[&String], controls: &[u32], qubits: &[u32]) {
+    let mut line = "CONTROLLED ".repeat(controls.len()) + name;
+
+    if !params.is_empty() {
+        let params: Vec<_> = params.iter().map(|p| p.as_str()).collect();
+        write!(line, "({})", params.join(", ")).unwrap();
+    }
+
+    controls.iter().chain(qubits).for_each(|q| write!(line, " {}", q).unwrap());
+    lines.push(line);
+}
+
+/// Writes the lines of the operation of an instruction, controlled by the given qubits,
+/// ignoring it's modifier. Operations without a Quil counterpart are decomposed, up to
+/// a global phase which is restored on the controls.
+fn write_op(circ: &QuantumCircuit, lines: &mut Vec<String>, instr: &Instr<'_, '_>, controls: &[u32]) -> Result<(), QuilError> {
+    let q: Vec<_> = instr.qubits.iter().map(|q| q.id()).collect();
+    let p: Vec<_> = instr.parameters.iter().map(|p| parameter(circ, p)).collect();
+
+    let mut gate = |name: &str, params: &[&String], qubits: &[u32]| write_gate(lines, name, params, controls, qubits);
+
+    let mut global_phase: Option<String> = None;
+
+    match &instr.op {
+        OpKind::Nop => (),
+        OpKind::H => gate("H", &[], &q),
+        OpKind::X => gate("X", &[], &q),
+        OpKind::Y => gate("Y", &[], &q),
+        OpKind::Z => gate("Z", &[], &q),
+        OpKind::S => gate("S", &[], &q),
+        OpKind::Sdg => gate("DAGGER S", &[], &q),
+        OpKind::T => gate("T", &[], &q),
+        OpKind::Tdg => gate("DAGGER T", &[], &q),
+        OpKind::SX => {
+            gate("RX", &[&"pi/2".into()], &q);
+            global_phase = Some("pi/4".into());
+        }
+        OpKind::RX => gate("RX", &[&p[0]], &q),
+        OpKind::RY => gate("RY", &[&p[0]], &q),
+        OpKind::RZ => gate("RZ", &[&p[0]], &q),
+        OpKind::Phase => gate("PHASE", &[&p[0]], &q),
+        OpKind::U3 => {
+            gate("RZ", &[&p[2]], &q);
+            gate("RY", &[&p[0]], &q);
+            gate("RZ", &[&p[1]], &q);
+            global_phase = Some(format!("({} + {})/2", p[1], p[2]));
+        }
+        OpKind::CX => gate("CNOT", &[], &q),
+        OpKind::CY => gate("CONTROLLED Y", &[], &q),
+        OpKind::CZ => gate("CZ", &[], &q),
+        OpKind::CH => gate("CONTROLLED H", &[], &q),
+        OpKind::Swap => gate("SWAP", &[], &q),
+        OpKind::ISwap => gate("ISWAP", &[], &q),
+        OpKind::CRZ => gate("CONTROLLED RZ", &[&p[0]], &q),
+        OpKind::CP => gate("CPHASE", &[&p[0]], &q),
+        OpKind::RXX => {
+            gate("H", &[], &q[..1]);
+            gate("H", &[], &q[1..]);
+            gate("CNOT", &[], &q);
+            gate("RZ", &[&p[0]], &q[1..]);
+            gate("CNOT", &[], &q);
+            gate("H", &[], &q[..1]);
+            gate("H", &[], &q[1..]);
+        }
+        OpKind::RZZ => {
+            gate("CNOT", &[], &q);
+            gate("RZ", &[&p[0]], &q[1..]);
+            gate("CNOT", &[], &q);
+        }
+        OpKind::CCX => gate("CCNOT", &[], &q),
+        OpKind::CSwap => gate("CSWAP", &[], &q),
+        OpKind::Measure => {
+            for (q, b) in q.iter().zip(instr.bits) {
+                lines.push(format!("MEASURE {} ro[{}]", q, b.id()));
+            }
+        }
+        OpKind::Reset => q.iter().for_each(|q| lines.push(format!("RESET {}", q))),
+        OpKind::Barrier => {
+            if !q.is_empty() {
+                gate("FENCE", &[], &q);
+            }
+        }
+        op => return Err(QuilError::UnsupportedOperation(op.label())),
+    }
+
+    if let (Some(phase), Some((&last, controls))) = (global_phase, controls.split_last()) {
+        write_gate(lines, "PHASE", &[&phase], controls, &[last]);
+    }
+
+    Ok(())
+}
+
+impl QuantumCircuit {
+    /// Returns the circuit as a Quil program.
+    ///
+    /// Bits are declared in a region `ro` and formal parameters in a region `theta`.
+    /// The `IfBit` and `WhileBit` modifiers are written with jumps, as described in the
+    /// [module documentation](crate::quil), and the `ForConst` modifier is unrolled.
+    /// Barriers are written as `FENCE` instructions. The `Control` and `Inverse` modifiers
+    /// are written as `CONTROLLED` and `DAGGER` gate modifiers, and negative controls are
+    /// surrounded by `X` gates.
+    ///
+    /// Fails on compute nodes, on modifiers that depend on a compute, and on powers, which
+    /// can be replaced by basic gates with [`QuantumCircuit::expand_modifiers`].
+    pub fn to_quil(&self) -> Result<String, QuilError> {
+        let mut out = String::new();
+        let mut labels = 0;
+
+        if self.bit_count() != 0 {
+            writeln!(out, "DECLARE ro BIT[{}]", self.bit_count()).unwrap();
+        }
+
+        if self.parameter_count() != 0 {
+            writeln!(out, "DECLARE theta REAL[{}]", self.parameter_count()).unwrap();
+        }
+
+        let mut iter = self.instructions();
+
+        while let Some(instr) = iter.next() {
+            let controls: Vec<_> = match &instr.modifier {
+                Some(Modifier::Control(controls) | Modifier::NegControl(controls)) => controls.as_range().collect(),
+                _ => Vec::new(),
+            };
+
+            let mut body = Vec::new();
+            write_op(self, &mut body, instr, &controls)?;
+
+            if body.is_empty() {
+                continue;
+            }
+
+            match &instr.modifier {
+                None => body.iter().for_each(|line| writeln!(out, "{}", line).unwrap()),
+                Some(Modifier::IfBit(b)) => {
+                    writeln!(out, "JUMP-UNLESS @end-{} ro[{}]", labels, b.id()).unwrap();
+                    body.iter().for_each(|line| writeln!(out, "{}", line).unwrap());
+                    writeln!(out, "LABEL @end-{}", labels).unwrap();
+                    labels += 1;
+                }
+                Some(Modifier::WhileBit(b)) => {
+                    writeln!(out, "JUMP-UNLESS @end-{} ro[{}]", labels, b.id()).unwrap();
+                    writeln!(out, "LABEL @loop-{}", labels).unwrap();
+                    body.iter().for_each(|line| writeln!(out, "{}", line).unwrap());
+                    writeln!(out, "JUMP-WHEN @loop-{} ro[{}]", labels, b.id()).unwrap();
+                    writeln!(out, "LABEL @end-{}", labels).unwrap();
+                    labels += 1;
+                }
+                Some(Modifier::ForConst(n)) => {
+                    for _ in 0..*n {
+                        body.iter().for_each(|line| writeln!(out, "{}", line).unwrap());
+                    }
+                }
+                Some(Modifier::Control(_)) => body.iter().for_each(|line| writeln!(out, "{}", line).unwrap()),
+                Some(Modifier::NegControl(_)) => {
+                    controls.iter().for_each(|c| writeln!(out, "X {}", c).unwrap());
+                    body.iter().for_each(|line| writeln!(out, "{}", line).unwrap());
+                    controls.iter().for_each(|c| writeln!(out, "X {}", c).unwrap());
+                }
+                Some(Modifier::Inverse) => body.iter().rev().for_each(|line| writeln!(out, "DAGGER {}", line).unwrap()),
+                Some(Modifier::IfCompute(_)) => return Err(QuilError::UnsupportedModifier("if")),
+                Some(Modifier::WhileCompute(_)) => return Err(QuilError::UnsupportedModifier("while")),
+                Some(Modifier::ForCompute(_)) => return Err(QuilError::UnsupportedModifier("for")),
+                Some(modifier) => return Err(QuilError::UnsupportedModifier(modifier.label())),
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::instruction::Compute;
+    use crate::circuit::symbol::FormalParameter;
+
+    use super::*;
+
+    #[test]
+    fn export() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+            let [b1, b2] = circ.alloc_n()?;
+            let theta: FormalParameter = circ.alloc()?;
+            circ.h(q1).cx(q1, q2).rx(theta, q2).sdg(q1).barrier(&[q1, q2]).measure(&[q1], &[b1]);
+            circ.push(Instr { op: OpKind::X, qubits: &[q2], modifier: Some(Modifier::IfBit(b1)), ..Default::default() })?;
+            circ.push(Instr {
+                op: OpKind::Measure,
+                qubits: &[q2],
+                bits: &[b2],
+                modifier: Some(Modifier::WhileBit(b2)),
+                ..Default::default()
+            })?;
+            circ.push(Instr { op: OpKind::Z, qubits: &[q1], modifier: Some(Modifier::ForConst(2)), ..Default::default() })?;
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(circ.to_quil().unwrap(), "\
+DECLARE ro BIT[2]
+DECLARE theta REAL[1]
+H 0
+CNOT 0 1
+RX(theta[0]) 1
+DAGGER S 0
+FENCE 0 1
+MEASURE 0 ro[0]
+JUMP-UNLESS @end-0 ro[0]
+X 1
+LABEL @end-0
+JUMP-UNLESS @end-1 ro[1]
+LABEL @loop-1
+MEASURE 1 ro[1]
+JUMP-WHEN @loop-1 ro[1]
+LABEL @end-1
+Z 0
+Z 0
+");
+    }
+
+    #[test]
+    fn modifiers() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [q1, q2, q3] = circ.alloc_n()?;
+            let controls = circ.alloc_list(2)?;
+            circ.push(Instr { op: OpKind::RZ, qubits: &[q1], parameters: &[0.5.into()], modifier: Some(Modifier::Control(controls.clone())), ..Default::default() })?;
+            circ.push(Instr { op: OpKind::Sdg, qubits: &[q2], modifier: Some(Modifier::NegControl(controls.clone())), ..Default::default() })?;
+            circ.push(Instr { op: OpKind::SX, qubits: &[q3], modifier: Some(Modifier::Control(controls.clone())), ..Default::default() })?;
+            circ.push(Instr { op: OpKind::RZZ, qubits: &[q1, q2], parameters: &[0.25.into()], modifier: Some(Modifier::Inverse), ..Default::default() })?;
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(circ.to_quil().unwrap(), "\
+CONTROLLED CONTROLLED RZ(0.5) 3 4 0
+X 3
+X 4
+CONTROLLED CONTROLLED DAGGER S 3 4 1
+X 3
+X 4
+CONTROLLED CONTROLLED RX(pi/2) 3 4 2
+CONTROLLED PHASE(pi/4) 3 4
+DAGGER CNOT 0 1
+DAGGER RZ(0.25) 1
+DAGGER CNOT 0 1
+");
+    }
+
+    #[test]
+    fn errors() {
+        let circ = QuantumCircuit::new(|circ| {
+            let b = circ.alloc()?;
+            circ.compute(&[b], Compute { bits: &[], func: |bits| bits });
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(circ.to_quil(), Err(QuilError::UnsupportedOperation("compute")));
+
+        let circ = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            circ.push(Instr { op: OpKind::X, qubits: &[q], modifier: Some(Modifier::Pow(0.5.into())), ..Default::default() })?;
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(circ.to_quil(), Err(QuilError::UnsupportedModifier("pow")));
+    }
+}
@@ -0,0 +1,986 @@
+use std::collections::HashMap;
+
+use crate::circuit::QuantumCircuit;
+use crate::circuit::instruction::{Instr, Modifier};
+use crate::circuit::operation::OpKind;
+use crate::circuit::parameter::Parameter;
+use crate::circuit::symbol::{Bit, FormalParameter, List, Qubit, Symbol};
+use crate::parse::{Number, ParseError, ParseErrorKind, Pos, Token, number};
+
+/// Splits a line into tokens, along with their positions. The last token is always `Eol`.
+/// Comments start with `#` and span until the end of the line.
+fn tokenize(src: &str, line: usize) -> Result<Vec<(Token, Pos)>, ParseError> {
+    let chars: Vec<char> = src.chars().collect();
+    let ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let pos = Pos { line, column: i + 1 };
+
+        if c == '#' {
+            break;
+        }
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let (token, len) = match c {
+            'a'..='z' | 'A'..='Z' | '_' | '@' => {
+                // Identifiers may contain dashes, but not end with one.
+                let start = i + (c == '@') as usize;
+                let mut end = start;
+
+                while end < chars.len() && (ident(chars[end]) || chars[end] == '-' && chars.get(end + 1).is_some_and(|&c| ident(c))) {
+                    end += 1;
+                }
+
+                let name: String = chars[start..end].iter().collect();
+
+                if c == '@' {
+                    (Token::Label(name), end - i)
+                } else {
+                    (Token::Ident(name), end - i)
+                }
+            }
+            '0'..='9' | '.' => {
+                let (token, len) = number(&chars[i..], pos)?;
+
+                // Imaginary numbers can't be parameters of a circuit.
+                if chars.get(i + len).is_some_and(|&c| ident(c)) {
+                    return Err(pos.error(ParseErrorKind::InvalidNumber(chars[i..=i + len].iter().collect())));
+                }
+
+                (token, len)
+            }
+            '(' => (Token::LParen, 1),
+            ')' => (Token::RParen, 1),
+            '[' => (Token::LBracket, 1),
+            ']' => (Token::RBracket, 1),
+            ',' => (Token::Comma, 1),
+            ':' => (Token::Colon, 1),
+            '+' => (Token::Plus, 1),
+            '-' => (Token::Minus, 1),
+            '*' => (Token::Star, 1),
+            '/' => (Token::Slash, 1),
+            '^' => (Token::Caret, 1),
+            c => return Err(pos.error(ParseErrorKind::UnexpectedChar(c))),
+        };
+
+        tokens.push((token, pos));
+        i += len;
+    }
+
+    tokens.push((Token::Eol, Pos { line, column: chars.len() + 1 }));
+    Ok(tokens)
+}
+
+/// The type of a declared memory region.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum RegionKind {
+    Bit,
+    Real,
+}
+
+/// A parameter of a gate, either a number or the id of a formal parameter.
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Value {
+    Const(Number),
+    Formal(u32),
+}
+
+/// A modifier of a gate that is not part of its native name.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum GateModifier {
+    /// The gate is controlled by the given number of qubits, which come first.
+    Control(u32),
+    Inverse,
+}
+
+/// Keywords of Quil instructions that cannot be represented.
+const UNSUPPORTED: &[&str] = &[
+    "DEFGATE", "DEFCIRCUIT", "DEFFRAME", "DEFWAVEFORM", "DEFCAL", "INCLUDE", "WAIT", "DELAY",
+    "PULSE", "CAPTURE", "RAW-CAPTURE", "SET-FREQUENCY", "SHIFT-FREQUENCY", "SET-PHASE", "SHIFT-PHASE",
+    "SWAP-PHASES", "SET-SCALE", "LOAD", "STORE", "MOVE", "EXCHANGE", "CONVERT", "NEG", "NOT",
+    "AND", "IOR", "XOR", "ADD", "SUB", "MUL", "DIV", "EQ", "GT", "GE", "LT", "LE",
+];
+
+/// A Quil instruction, memory references being resolved to the ids of bits.
+#[derive(Clone, PartialEq, Debug)]
+enum Quil {
+    Declare(String, RegionKind, u32),
+    Gate { name: String, params: Vec<Value>, qubits: Vec<u32>, modifier: Option<GateModifier> },
+    Measure(u32, u32),
+    Reset(Option<u32>),
+    Fence(Vec<u32>),
+    Label(String),
+    Jump(String),
+    JumpWhen(String, u32),
+    JumpUnless(String, u32),
+    Halt,
+}
+
+impl Quil {
+    /// Returns true if the instruction doesn't alter control flow.
+    #[inline]
+    fn is_simple(&self) -> bool {
+        matches!(self, Self::Gate { .. } | Self::Measure(..) | Self::Reset(_) | Self::Fence(_))
+    }
+
+    /// Returns true if the instruction is simple and can be given a condition, which excludes
+    /// gates that already have a modifier.
+    #[inline]
+    fn is_conditional(&self) -> bool {
+        self.is_simple() && !matches!(self, Self::Gate { modifier: Some(_), .. })
+    }
+}
+
+/// Returns the operation corresponding to the given gate name, modifiers included.
+fn native<'a, 'id>(name: &str) -> Option<OpKind<'a, 'id>> {
+    let op = match name {
+        "H" => OpKind::H,
+        "X" => OpKind::X,
+        "Y" => OpKind::Y,
+        "Z" => OpKind::Z,
+        "S" => OpKind::S,
+        "DAGGER S" => OpKind::Sdg,
+        "T" => OpKind::T,
+        "DAGGER T" => OpKind::Tdg,
+        "RX" => OpKind::RX,
+        "RY" => OpKind::RY,
+        "RZ" => OpKind::RZ,
+        "PHASE" => OpKind::Phase,
+        "CNOT" | "CONTROLLED X" => OpKind::CX,
+        "CONTROLLED Y" => OpKind::CY,
+        "CZ" | "CONTROLLED Z" => OpKind::CZ,
+        "CONTROLLED H" => OpKind::CH,
+        "SWAP" => OpKind::Swap,
+        "ISWAP" => OpKind::ISwap,
+        "CONTROLLED RZ" => OpKind::CRZ,
+        "CPHASE" | "CONTROLLED PHASE" => OpKind::CP,
+        "CCNOT" | "CONTROLLED CNOT" | "CONTROLLED CONTROLLED X" => OpKind::CCX,
+        "CSWAP" | "CONTROLLED SWAP" => OpKind::CSwap,
+        _ => return None,
+    };
+
+    Some(op)
+}
+
+/// A cursor over the tokens of a line.
+struct Parser<'p> {
+    tokens: Vec<(Token, Pos)>,
+    index: usize,
+    regions: &'p HashMap<String, (RegionKind, u32, u32)>,
+}
+
+impl Parser<'_> {
+    /// Returns the current token, the last one being repeated past the end of the line.
+    #[inline]
+    fn current(&self) -> &(Token, Pos) {
+        &self.tokens[self.index.min(self.tokens.len() - 1)]
+    }
+
+    #[inline]
+    fn peek(&self) -> &Token {
+        &self.current().0
+    }
+
+    #[inline]
+    fn pos(&self) -> Pos {
+        self.current().1
+    }
+
+    #[inline]
+    fn next(&mut self) -> Token {
+        let token = self.peek().clone();
+        self.index += 1;
+        token
+    }
+
+    fn unexpected(&self, expected: &str) -> ParseError {
+        self.pos().error(ParseErrorKind::UnexpectedToken {
+            expected: expected.into(),
+            found: self.peek().describe(),
+        })
+    }
+
+    /// Consumes the next token if it is equal to `token`.
+    fn eat(&mut self, token: &Token) -> bool {
+        let eq = self.peek() == token;
+
+        if eq {
+            self.next();
+        }
+
+        eq
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<(), ParseError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&token.describe()))
+        }
+    }
+
+    fn expect_ident(&mut self) -> Result<String, ParseError> {
+        match self.peek() {
+            Token::Ident(_) => match self.next() {
+                Token::Ident(name) => Ok(name),
+                _ => unreachable!(),
+            },
+            _ => Err(self.unexpected("an identifier")),
+        }
+    }
+
+    fn expect_int(&mut self) -> Result<u64, ParseError> {
+        match *self.peek() {
+            Token::Int(n) => {
+                self.next();
+                Ok(n)
+            }
+            _ => Err(self.unexpected("an integer")),
+        }
+    }
+
+    fn expect_label(&mut self) -> Result<String, ParseError> {
+        match self.peek() {
+            Token::Label(_) => match self.next() {
+                Token::Label(name) => Ok(name),
+                _ => unreachable!(),
+            },
+            _ => Err(self.unexpected("a label")),
+        }
+    }
+
+    fn expect_eol(&mut self) -> Result<(), ParseError> {
+        self.expect(&Token::Eol)
+    }
+
+    /// Parses a qubit index.
+    fn qubit(&mut self) -> Result<u32, ParseError> {
+        let pos = self.pos();
+        let n = self.expect_int()?;
+        u32::try_from(n).ok()
+            .filter(|&n| n < Qubit::MAX)
+            .ok_or_else(|| pos.error(ParseErrorKind::InvalidNumber(n.to_string())))
+    }
+
+    /// Parses a memory reference `name[i]` or `name`, and returns the global id of the element.
+    fn reference(&mut self, kind: RegionKind) -> Result<u32, ParseError> {
+        let pos = self.pos();
+        let name = self.expect_ident()?;
+
+        let index = if self.eat(&Token::LBracket) {
+            let index = self.expect_int()?;
+            self.expect(&Token::RBracket)?;
+            index
+        } else {
+            0
+        };
+
+        match self.regions.get(&name) {
+            Some(&(k, start, size)) if k == kind => {
+                if index >= size as u64 {
+                    Err(pos.error(ParseErrorKind::IndexOutOfRange { register: name, index }))
+                } else {
+                    Ok(start + index as u32)
+                }
+            }
+            _ => Err(pos.error(ParseErrorKind::UnknownRegion(name))),
+        }
+    }
+
+    /// Parses an expression, which must either be constant or a single element of a `REAL` region.
+    fn expr(&mut self) -> Result<Value, ParseError> {
+        let pos = self.pos();
+        let mut lhs = self.term()?;
+
+        loop {
+            let op = match self.peek() {
+                Token::Plus => |a, b| a + b,
+                Token::Minus => |a, b| a - b,
+                _ => return Ok(lhs),
+            };
+
+            self.next();
+            lhs = Value::Const(op(constant(lhs, pos)?, constant(self.term()?, pos)?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Value, ParseError> {
+        let pos = self.pos();
+        let mut lhs = self.power()?;
+
+        loop {
+            let op = match self.peek() {
+                Token::Star => |a, b| a * b,
+                Token::Slash => |a, b| a / b,
+                _ => return Ok(lhs),
+            };
+
+            self.next();
+            lhs = Value::Const(op(constant(lhs, pos)?, constant(self.power()?, pos)?));
+        }
+    }
+
+    fn power(&mut self) -> Result<Value, ParseError> {
+        let pos = self.pos();
+        let lhs = self.unary()?;
+
+        if self.eat(&Token::Caret) {
+            let rhs = self.power()?;
+            Ok(Value::Const(constant(lhs, pos)?.powf(constant(rhs, pos)?)))
+        } else {
+            Ok(lhs)
+        }
+    }
+
+    fn unary(&mut self) -> Result<Value, ParseError> {
+        let pos = self.pos();
+
+        if self.eat(&Token::Minus) {
+            Ok(Value::Const(-constant(self.unary()?, pos)?))
+        } else if self.eat(&Token::Plus) {
+            self.unary()
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Value, ParseError> {
+        let pos = self.pos();
+
+        match self.next() {
+            Token::Int(n) => Ok(Value::Const(Number::int(n))),
+            Token::Real(x) => Ok(Value::Const(x.into())),
+            Token::LParen => {
+                let value = self.expr()?;
+                self.expect(&Token::RParen)?;
+                Ok(value)
+            }
+            Token::Ident(name) if name == "pi" => Ok(Value::Const(Number::PI)),
+            Token::Ident(name) if self.peek() == &Token::LParen => {
+                let func: fn(f64) -> f64 = match name.as_str() {
+                    "sin" => f64::sin,
+                    "cos" => f64::cos,
+                    "sqrt" => f64::sqrt,
+                    "exp" => f64::exp,
+                    "cis" => return Err(pos.error(ParseErrorKind::UnsupportedExpression)),
+                    _ => return Err(pos.error(ParseErrorKind::UnknownIdentifier(name))),
+                };
+
+                self.next();
+                let arg = constant(self.expr()?, pos)?;
+                self.expect(&Token::RParen)?;
+                Ok(Value::Const(arg.map(func)))
+            }
+            Token::Ident(_) => {
+                self.index -= 1;
+                self.reference(RegionKind::Real).map(Value::Formal)
+            }
+            _ => {
+                self.index -= 1;
+                Err(self.unexpected("an expression"))
+            }
+        }
+    }
+
+    /// Parses a gate application, starting with it's modifiers.
+    fn gate(&mut self, first: String, pos: Pos) -> Result<Quil, ParseError> {
+        let mut daggers = 0;
+        let mut controls = 0;
+        let mut name = first;
+
+        loop {
+            match name.as_str() {
+                "DAGGER" => daggers += 1,
+                "CONTROLLED" => controls += 1,
+                "FORKED" => return Err(pos.error(ParseErrorKind::UnsupportedInstruction(name))),
+                _ => break,
+            }
+
+            name = self.expect_ident()?;
+        }
+
+        let mut params = Vec::new();
+
+        if self.eat(&Token::LParen) {
+            loop {
+                params.push(self.expr()?);
+
+                if !self.eat(&Token::Comma) {
+                    break;
+                }
+            }
+
+            self.expect(&Token::RParen)?;
+        }
+
+        let mut qubits = Vec::new();
+
+        while self.peek() != &Token::Eol {
+            qubits.push(self.qubit()?);
+        }
+
+        let full = "DAGGER ".repeat(daggers) + &"CONTROLLED ".repeat(controls) + &name;
+
+        // Resolve the daggers: the inverses of S and T are native, self-inverse gates are left
+        // unchanged, and other gates are inverted by a modifier.
+        let mut modifier = None;
+
+        if daggers % 2 == 1 {
+            match name.as_str() {
+                "S" | "T" => name = format!("DAGGER {}", name),
+                "I" | "H" | "X" | "Y" | "Z" | "CNOT" | "CZ" | "SWAP" | "CCNOT" | "CSWAP" => (),
+                _ => modifier = Some(GateModifier::Inverse),
+            }
+        }
+
+        if name == "I" {
+            return Ok(Quil::Gate { name, params, qubits, modifier: None });
+        }
+
+        // Resolve the controls: the longest native controlled gate is used, and the remaining
+        // controls become a modifier.
+        let native_controls = (0..=controls).rev()
+            .find(|&n| native(&("CONTROLLED ".repeat(n) + &name)).is_some())
+            .ok_or_else(|| pos.error(ParseErrorKind::UnknownGate(full.clone())))?;
+
+        let extra = controls - native_controls;
+        let name = "CONTROLLED ".repeat(native_controls) + &name;
+        let op = native(&name).unwrap();
+
+        let arity = |kind: &'static str, expected: u32, found: usize| {
+            (expected as usize != found).then(|| pos.error(ParseErrorKind::ArityMismatch {
+                gate: full.clone(),
+                kind,
+                expected: expected as usize,
+                found,
+            }))
+        };
+
+        if let Some(err) = arity("parameters", op.parameters().get().unwrap(), params.len())
+            .or_else(|| arity("qubits", op.qubits().get().unwrap() + extra as u32, qubits.len()))
+        {
+            return Err(err);
+        }
+
+        if (1..qubits.len()).any(|i| qubits[..i].contains(&qubits[i])) {
+            return Err(pos.error(ParseErrorKind::DuplicateQubit));
+        }
+
+        if extra != 0 {
+            // Controls are represented by a range of qubits, and can't be combined with an inverse.
+            if modifier.is_some() || qubits[..extra].windows(2).any(|w| w[1] != w[0] + 1) {
+                return Err(pos.error(ParseErrorKind::UnsupportedInstruction(full.clone())));
+            }
+
+            modifier = Some(GateModifier::Control(extra as u32));
+        }
+
+        Ok(Quil::Gate { name, params, qubits, modifier })
+    }
+
+    /// Parses an instruction, or returns `None` on instructions without effect.
+    fn instr(&mut self) -> Result<Option<Quil>, ParseError> {
+        let pos = self.pos();
+        let keyword = self.expect_ident()?;
+
+        let instr = match keyword.as_str() {
+            "DECLARE" => {
+                let name = self.expect_ident()?;
+                let type_pos = self.pos();
+
+                let kind = match self.expect_ident()?.as_str() {
+                    "BIT" => RegionKind::Bit,
+                    "REAL" => RegionKind::Real,
+                    ty => return Err(type_pos.error(ParseErrorKind::UnsupportedType(ty.into()))),
+                };
+
+                let size = if self.eat(&Token::LBracket) {
+                    let size_pos = self.pos();
+                    let size = self.expect_int()?;
+                    self.expect(&Token::RBracket)?;
+                    u32::try_from(size).map_err(|_| size_pos.error(ParseErrorKind::InvalidNumber(size.to_string())))?
+                } else {
+                    1
+                };
+
+                Quil::Declare(name, kind, size)
+            }
+            "MEASURE" => {
+                let qubit = self.qubit()?;
+
+                if self.peek() == &Token::Eol {
+                    return Err(pos.error(ParseErrorKind::UnsupportedInstruction("MEASURE".into())));
+                }
+
+                Quil::Measure(qubit, self.reference(RegionKind::Bit)?)
+            }
+            "RESET" => match self.peek() {
+                Token::Eol => Quil::Reset(None),
+                _ => Quil::Reset(Some(self.qubit()?)),
+            },
+            "FENCE" => {
+                let mut qubits = Vec::new();
+
+                while self.peek() != &Token::Eol {
+                    qubits.push(self.qubit()?);
+                }
+
+                Quil::Fence(qubits)
+            }
+            "LABEL" => Quil::Label(self.expect_label()?),
+            "JUMP" => Quil::Jump(self.expect_label()?),
+            "JUMP-WHEN" => Quil::JumpWhen(self.expect_label()?, self.reference(RegionKind::Bit)?),
+            "JUMP-UNLESS" => Quil::JumpUnless(self.expect_label()?, self.reference(RegionKind::Bit)?),
+            "HALT" => Quil::Halt,
+            "NOP" => {
+                self.expect_eol()?;
+                return Ok(None);
+            }
+            _ if UNSUPPORTED.contains(&keyword.as_str()) => {
+                return Err(pos.error(ParseErrorKind::UnsupportedInstruction(keyword)));
+            }
+            _ => self.gate(keyword, pos)?,
+        };
+
+        self.expect_eol()?;
+        Ok(Some(instr))
+    }
+}
+
+/// Returns the value of a constant expression.
+#[inline]
+fn constant(value: Value, pos: Pos) -> Result<Number, ParseError> {
+    match value {
+        Value::Const(x) => Ok(x),
+        Value::Formal(_) => Err(pos.error(ParseErrorKind::UnsupportedExpression)),
+    }
+}
+
+/// The condition attached to a simple instruction.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Cond {
+    If(u32),
+    While(u32),
+}
+
+/// Converts the labels and jumps of the program into modifiers.
+fn structure(instrs: Vec<(Quil, Pos)>) -> Result<Vec<(Quil, Option<Cond>)>, ParseError> {
+    let mut refs = HashMap::<&str, usize>::new();
+
+    for (instr, _) in &instrs {
+        if let Quil::Jump(label) | Quil::JumpWhen(label, _) | Quil::JumpUnless(label, _) = instr {
+            *refs.entry(label).or_default() += 1;
+        }
+    }
+
+    let once = |label: &str| refs.get(label) == Some(&1);
+    let get = |i: usize| instrs.get(i).map(|(instr, _)| instr);
+    let is_label = |i: usize, label: &str| matches!(get(i), Some(Quil::Label(l)) if l == label && once(l));
+    let is_conditional = |i: usize| get(i).is_some_and(Quil::is_conditional);
+
+    // Returns the index of the label ending an if block starting at `i`, if it is well-formed.
+    let if_block = |i: usize, end: &str, bit: u32| {
+        let len = instrs[i..].iter().take_while(|(instr, _)| instr.is_conditional()).count();
+
+        // Only the last instruction may change the condition.
+        let writes = instrs[i..i + len].iter().rev().skip(1).any(|(instr, _)| matches!(instr, Quil::Measure(_, b) if *b == bit));
+        (is_label(i + len, end) && !writes).then_some(i + len)
+    };
+
+    let mut res = Vec::new();
+    let mut i = 0;
+
+    while i < instrs.len() {
+        let (instr, pos) = &instrs[i];
+
+        match instr {
+            instr if instr.is_simple() => {
+                res.push((instr.clone(), None));
+                i += 1;
+            }
+            // JUMP-UNLESS @end b; LABEL @loop; X; JUMP-WHEN @loop b; LABEL @end
+            Quil::JumpUnless(end, bit) if once(end) && matches!(
+                (get(i + 1), get(i + 3)),
+                (Some(Quil::Label(l1)), Some(Quil::JumpWhen(l2, b))) if l1 == l2 && once(l1) && b == bit
+            ) && is_conditional(i + 2) && is_label(i + 4, end) => {
+                res.push((instrs[i + 2].0.clone(), Some(Cond::While(*bit))));
+                i += 5;
+            }
+            // JUMP-UNLESS @end b; ...; LABEL @end
+            Quil::JumpUnless(end, bit) if once(end) && if_block(i + 1, end, *bit).is_some() => {
+                let j = if_block(i + 1, end, *bit).unwrap();
+                res.extend(instrs[i + 1..j].iter().map(|(instr, _)| (instr.clone(), Some(Cond::If(*bit)))));
+                i = j + 1;
+            }
+            // JUMP-WHEN @then b; JUMP @end; LABEL @then; ...; LABEL @end
+            Quil::JumpWhen(then, bit) if once(then) && is_label(i + 2, then) && matches!(
+                get(i + 1),
+                Some(Quil::Jump(end)) if once(end) && if_block(i + 3, end, *bit).is_some()
+            ) => {
+                let Some(Quil::Jump(end)) = get(i + 1) else { unreachable!() };
+                let j = if_block(i + 3, end, *bit).unwrap();
+                res.extend(instrs[i + 3..j].iter().map(|(instr, _)| (instr.clone(), Some(Cond::If(*bit)))));
+                i = j + 1;
+            }
+            // LABEL @loop; X; JUMP-WHEN @loop b
+            Quil::Label(label) if once(label) && is_conditional(i + 1) && matches!(
+                get(i + 2),
+                Some(Quil::JumpWhen(l, _)) if l == label
+            ) => {
+                let Some(&Quil::JumpWhen(_, bit)) = get(i + 2) else { unreachable!() };
+                res.push((instrs[i + 1].0.clone(), None));
+                res.push((instrs[i + 1].0.clone(), Some(Cond::While(bit))));
+                i += 3;
+            }
+            // LABEL @loop; JUMP-UNLESS @end b; X; JUMP @loop; LABEL @end
+            Quil::Label(label) if once(label) && matches!(
+                (get(i + 1), get(i + 3)),
+                (Some(Quil::JumpUnless(end, _)), Some(Quil::Jump(l))) if l == label && once(end) && is_label(i + 4, end)
+            ) && is_conditional(i + 2) => {
+                let Some(&Quil::JumpUnless(_, bit)) = get(i + 1) else { unreachable!() };
+                res.push((instrs[i + 2].0.clone(), Some(Cond::While(bit))));
+                i += 5;
+            }
+            // Labels that are never jumped to are ignored.
+            Quil::Label(label) if !refs.contains_key(label.as_str()) => i += 1,
+            Quil::Halt if i + 1 == instrs.len() => i += 1,
+            _ => return Err(pos.error(ParseErrorKind::UnsupportedControlFlow)),
+        }
+    }
+
+    Ok(res)
+}
+
+impl QuantumCircuit {
+    /// Parses a Quil program and returns the corresponding circuit.
+    ///
+    /// Qubits are allocated up to the greatest index used. Bits and formal parameters
+    /// are allocated for each `BIT` and `REAL` region respectively, in the order of their
+    /// declarations. Gate parameters must either be constant expressions or elements of
+    /// a `REAL` region, and those that are rational multiples of $\pi$ are exact angles.
+    /// `CONTROLLED` and `DAGGER` gate modifiers without a native counterpart become the
+    /// `Control` and `Inverse` modifiers, the controls being consecutive qubits in increasing
+    /// order. Control flow is converted to the `IfBit` and `WhileBit` modifiers when it
+    /// follows one of the patterns of the [module documentation](crate::quil).
+    pub fn from_quil(src: &str) -> Result<Self, ParseError> {
+        let mut regions = HashMap::new();
+        let (mut bit_count, mut parameter_count) = (0u32, 0u32);
+        let mut instrs = Vec::new();
+
+        for (line, text) in src.lines().enumerate() {
+            // Pragmas are hints to the compiler, that do not change the semantics of the program.
+            if text.trim_start().starts_with("PRAGMA") {
+                continue;
+            }
+
+            let tokens = tokenize(text, line + 1)?;
+
+            if tokens.len() == 1 {
+                continue;
+            }
+
+            let mut parser = Parser { tokens, index: 0, regions: &regions };
+            let pos = parser.pos();
+
+            match parser.instr()? {
+                Some(Quil::Declare(name, kind, size)) => {
+                    if regions.contains_key(&name) {
+                        return Err(pos.error(ParseErrorKind::Redefinition(name)));
+                    }
+
+                    let count = match kind {
+                        RegionKind::Bit => &mut bit_count,
+                        RegionKind::Real => &mut parameter_count,
+                    };
+
+                    let start = *count;
+                    *count = count.checked_add(size).ok_or_else(|| pos.error(ParseErrorKind::InvalidNumber(size.to_string())))?;
+                    regions.insert(name, (kind, start, size));
+                }
+                Some(instr) => instrs.push((instr, pos)),
+                None => (),
+            }
+        }
+
+        let instrs = structure(instrs)?;
+
+        let qubit_count = instrs.iter()
+            .filter_map(|(instr, _)| match instr {
+                Quil::Gate { qubits, .. } | Quil::Fence(qubits) => qubits.iter().max().copied(),
+                Quil::Measure(q, _) | Quil::Reset(Some(q)) => Some(*q),
+                _ => None,
+            })
+            .max()
+            .map_or(0, |q| q as usize + 1);
+
+        let mut regions: Vec<_> = regions.into_values().collect();
+        regions.sort_unstable_by_key(|&(kind, start, _)| (kind == RegionKind::Real, start));
+
+        QuantumCircuit::new(|circ| {
+            let qubits: Vec<Qubit> = circ.alloc_list(qubit_count)?.iter().collect();
+            let mut bits: Vec<Bit> = Vec::new();
+            let mut formals: Vec<FormalParameter> = Vec::new();
+
+            for &(kind, _, size) in &regions {
+                match kind {
+                    RegionKind::Bit => bits.extend(circ.alloc_list::<Bit>(size as usize)?),
+                    RegionKind::Real => formals.extend(circ.alloc_list::<FormalParameter>(size as usize)?),
+                }
+            }
+
+            for (instr, cond) in &instrs {
+                let modifier = cond.map(|cond| match cond {
+                    Cond::If(b) => Modifier::IfBit(bits[b as usize]),
+                    Cond::While(b) => Modifier::WhileBit(bits[b as usize]),
+                });
+
+                let qubit = |ids: &[u32]| ids.iter().map(|&q| qubits[q as usize]).collect::<Vec<_>>();
+
+                match instr {
+                    Quil::Gate { name, .. } if name == "I" => (),
+                    Quil::Gate { name, params, qubits: ids, modifier: gate_modifier } => {
+                        let params: Vec<Parameter> = params.iter()
+                            .map(|&param| match param {
+                                Value::Const(x) => x.into(),
+                                Value::Formal(id) => formals[id as usize].into(),
+                            })
+                            .collect();
+
+                        let (ids, modifier) = match *gate_modifier {
+                            Some(GateModifier::Control(n)) => {
+                                let (controls, ids) = ids.split_at(n as usize);
+                                let first = qubits[controls[0] as usize];
+                                let last = qubits[controls[controls.len() - 1] as usize];
+                                (ids, Some(Modifier::Control(List::new(first, last).unwrap())))
+                            }
+                            Some(GateModifier::Inverse) => (&ids[..], Some(Modifier::Inverse)),
+                            None => (&ids[..], modifier),
+                        };
+
+                        circ.push(Instr {
+                            op: native(name).unwrap(),
+                            qubits: &qubit(ids),
+                            parameters: &params,
+                            modifier,
+                            ..Default::default()
+                        })?;
+                    }
+                    Quil::Measure(q, b) => {
+                        circ.push(Instr {
+                            op: OpKind::Measure,
+                            qubits: &qubit(&[*q]),
+                            bits: &[bits[*b as usize]],
+                            modifier,
+                            ..Default::default()
+                        })?;
+                    }
+                    Quil::Reset(q) => {
+                        let ids = q.map_or_else(|| (0..qubit_count as u32).collect(), |q| vec![q]);
+
+                        for id in ids {
+                            circ.push(Instr { op: OpKind::Reset, qubits: &qubit(&[id]), modifier: modifier.clone(), ..Default::default() })?;
+                        }
+                    }
+                    Quil::Fence(ids) => {
+                        let ids = if ids.is_empty() { (0..qubit_count as u32).collect() } else { ids.clone() };
+                        circ.push(Instr { op: OpKind::Barrier, qubits: &qubit(&ids), modifier, ..Default::default() })?;
+                    }
+                    _ => unreachable!(),
+                }
+            }
+
+            Ok(())
+        }).map_err(|err| Pos { line: 1, column: 1 }.error(ParseErrorKind::Circuit(err)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sim::{Backend, StateVector, equivalent};
+
+    use super::*;
+
+    fn labels(circ: &QuantumCircuit) -> Vec<&'static str> {
+        let mut labels = Vec::new();
+        let mut iter = circ.instructions();
+
+        while let Some(instr) = iter.next() {
+            labels.push(instr.op.label());
+        }
+
+        labels
+    }
+
+    #[test]
+    fn import() {
+        let circ = QuantumCircuit::from_quil("
+            # A teleportation-like program.
+            DECLARE ro BIT[2]
+            DECLARE theta REAL
+            PRAGMA INITIAL_REWIRING \"NAIVE\"
+            RX(theta) 0
+            H 1
+            CNOT 1 2
+            DAGGER RZ(pi/2) 2
+            MEASURE 0 ro[0]
+            JUMP-WHEN @then ro[0]
+            JUMP @end
+            LABEL @then
+            X 2
+            LABEL @end
+            RESET
+        ").unwrap();
+
+        assert_eq!(circ.qubit_count(), 3);
+        assert_eq!(circ.bit_count(), 2);
+        assert_eq!(circ.parameter_count(), 1);
+        assert_eq!(labels(&circ), ["rx", "h", "cx", "rz", "measure", "x", "reset", "reset", "reset"]);
+
+        let mut iter = circ.instructions();
+
+        while let Some(instr) = iter.next() {
+            match instr.op {
+                OpKind::RZ => {
+                    assert_eq!(instr.parameters[0].as_value(), Some(std::f32::consts::FRAC_PI_2));
+                    assert_eq!(instr.modifier, Some(Modifier::Inverse));
+                }
+                OpKind::X => assert!(matches!(instr.modifier, Some(Modifier::IfBit(b)) if b.id() == 0)),
+                _ => (),
+            }
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+            let [b1, b2] = circ.alloc_n()?;
+            circ.h(q1).cz(q1, q2).tdg(q2).ch(q1, q2).measure(&[q1], &[b1]);
+            circ.push(Instr { op: OpKind::X, qubits: &[q2], modifier: Some(Modifier::IfBit(b1)), ..Default::default() })?;
+            circ.push(Instr {
+                op: OpKind::Measure,
+                qubits: &[q1],
+                bits: &[b2],
+                modifier: Some(Modifier::WhileBit(b2)),
+                ..Default::default()
+            })?;
+            Ok(())
+        }).unwrap();
+
+        let quil = circ.to_quil().unwrap();
+        let imported = QuantumCircuit::from_quil(&quil).unwrap();
+
+        assert_eq!(labels(&imported), labels(&circ));
+        assert_eq!(imported.to_quil().unwrap(), quil);
+
+        for seed in 0..4 {
+            let a = StateVector::simulate(&circ, seed).unwrap();
+            let b = StateVector::simulate(&imported, seed).unwrap();
+            assert_eq!(a.register(), b.register());
+        }
+    }
+
+    #[test]
+    fn roundtrip_modifiers() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [q1, q2, q3] = circ.alloc_n()?;
+            let controls = circ.alloc_list(2)?;
+            let c: Qubit = controls.get(0).unwrap();
+            circ.push(Instr { op: OpKind::S, qubits: &[q1], modifier: Some(Modifier::Control(c.into())), ..Default::default() })?;
+            circ.push(Instr { op: OpKind::RZ, qubits: &[q1], parameters: &[0.5.into()], modifier: Some(Modifier::Control(controls.clone())), ..Default::default() })?;
+            circ.push(Instr { op: OpKind::Sdg, qubits: &[q2], modifier: Some(Modifier::NegControl(controls.clone())), ..Default::default() })?;
+            circ.push(Instr { op: OpKind::SX, qubits: &[q3], modifier: Some(Modifier::Control(controls.clone())), ..Default::default() })?;
+            circ.push(Instr { op: OpKind::U3, qubits: &[q3], parameters: &[0.1.into(), 0.2.into(), 0.3.into()], modifier: Some(Modifier::Control(c.into())), ..Default::default() })?;
+            circ.push(Instr { op: OpKind::RZZ, qubits: &[q1, q2], parameters: &[0.25.into()], modifier: Some(Modifier::Inverse), ..Default::default() })?;
+            Ok(())
+        }).unwrap();
+
+        let imported = QuantumCircuit::from_quil(&circ.to_quil().unwrap()).unwrap();
+        assert_eq!(equivalent(&circ, &imported, false), Ok(true));
+
+        // Formal parameters can be inverted by a modifier.
+        let circ = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            let theta: FormalParameter = circ.alloc()?;
+            circ.push(Instr { op: OpKind::RZ, qubits: &[q], parameters: &[theta.into()], modifier: Some(Modifier::Inverse), ..Default::default() })?;
+            Ok(())
+        }).unwrap();
+
+        let quil = circ.to_quil().unwrap();
+        assert_eq!(quil, "DECLARE theta REAL[1]\nDAGGER RZ(theta[0]) 0\n");
+        assert_eq!(QuantumCircuit::from_quil(&quil).unwrap().to_quil().unwrap(), quil);
+    }
+
+    #[test]
+    fn exact_angles() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+            circ.rz(Parameter::pi(1, 2), q1).rx(Parameter::pi(3, 1), q2).cp(Parameter::pi(5, 4), q1, q2);
+            Ok(())
+        }).unwrap();
+
+        let quil = circ.to_quil().unwrap();
+        let imported = QuantumCircuit::from_quil(&quil).unwrap();
+        assert_eq!(imported.to_quil().unwrap(), quil);
+
+        let mut iter = imported.instructions();
+        let mut angles = Vec::new();
+
+        while let Some(instr) = iter.next() {
+            angles.extend(instr.parameters.iter().map(|p| p.as_angle()));
+        }
+
+        assert_eq!(angles, [Some((1, 2)), Some((3, 1)), Some((5, 4))]);
+
+        let imported = QuantumCircuit::from_quil("RZ(-pi/2 + 2*pi) 0\nPHASE(-pi) 1\nRX(3*pi/(1 + 1)) 0").unwrap();
+        assert!(imported.is_clifford());
+    }
+
+    #[test]
+    fn do_while() {
+        let circ = QuantumCircuit::from_quil("
+            DECLARE ro BIT
+            H 0
+            LABEL @repeat
+            MEASURE 0 ro
+            JUMP-WHEN @repeat ro[0]
+        ").unwrap();
+
+        assert_eq!(labels(&circ), ["h", "measure", "measure"]);
+    }
+
+    #[test]
+    fn errors() {
+        let err = |src: &str| QuantumCircuit::from_quil(src).err().unwrap();
+
+        assert_eq!(err("H 0\n  CNOT 0"), ParseError {
+            line: 2,
+            column: 3,
+            kind: ParseErrorKind::ArityMismatch { gate: "CNOT".into(), kind: "qubits", expected: 2, found: 1 },
+        });
+
+        assert_eq!(err("DECLARE ro BIT\nMEASURE 0 ro[1]").kind, ParseErrorKind::IndexOutOfRange {
+            register: "ro".into(),
+            index: 1,
+        });
+
+        assert_eq!(err("DECLARE ro OCTET").kind, ParseErrorKind::UnsupportedType("OCTET".into()));
+        assert_eq!(err("DECLARE theta REAL\nRX(2*theta) 0").kind, ParseErrorKind::UnsupportedExpression);
+        assert_eq!(err("FOO 0").kind, ParseErrorKind::UnknownGate("FOO".into()));
+        assert_eq!(err("CONTROLLED CONTROLLED S 0 2 1").kind, ParseErrorKind::UnsupportedInstruction("CONTROLLED CONTROLLED S".into()));
+        assert_eq!(err("DEFGATE FOO:").kind, ParseErrorKind::UnsupportedInstruction("DEFGATE".into()));
+
+        assert_eq!(err("DECLARE ro BIT\nLABEL @a\nX 0\nY 0\nJUMP-WHEN @a ro"), ParseError {
+            line: 2,
+            column: 1,
+            kind: ParseErrorKind::UnsupportedControlFlow,
+        });
+    }
+}
@@ -0,0 +1,28 @@
+//! Conversions between quantum circuits and the Quil language.
+//!
+//! Control flow is expressed in Quil with labels and jumps. The `IfBit` and `WhileBit`
+//! modifiers are written as the following patterns, which are the ones recognized when
+//! reading a program, along with the do-while loop `LABEL @l; ...; JUMP-WHEN @l ro[i]`:
+//!
+//! ```text
+//! JUMP-UNLESS @end ro[i]        JUMP-UNLESS @end ro[i]
+//! ...                           LABEL @loop
+//! LABEL @end                    ...
+//!                               JUMP-WHEN @loop ro[i]
+//!                               LABEL @end
+//! ```
+
+mod export;
+mod import;
+
+pub use crate::parse::{ParseError, ParseErrorKind};
+
+use thiserror::Error;
+
+#[derive(Clone, PartialEq, Eq, Debug, Error)]
+pub enum QuilError {
+    #[error("operation `{0}` cannot be expressed in Quil")]
+    UnsupportedOperation(&'static str),
+    #[error("modifier `{0}` cannot be expressed in Quil")]
+    UnsupportedModifier(&'static str),
+}
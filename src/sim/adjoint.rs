@@ -0,0 +1,169 @@
+//! Adjoint differentiation of expectation values, following Jones and Gacon [12], see
+//! `doc/biblio.md`. The circuit is run forward once, then both the state and the image of
+//! the state by the observable are run backward through the inverse of each gate, which
+//! gives the derivative with respect to every parameter of the gate along the way.
+
+use crate::circuit::QuantumCircuit;
+use crate::circuit::operation::OpKind;
+use crate::circuit::symbol::Symbol;
+use crate::complex::Complex;
+use crate::matrix::Matrix;
+
+use super::gradient::{FOUR_TERMS, partials};
+use super::observable::Observable;
+use super::{SimulationError, StateVector, apply_matrix, resolve_all};
+
+/// A gate of the circuit, with the derivatives of it's matrix with respect to each of it's
+/// parameters that depend on formal parameters.
+struct Gate {
+    matrix: Matrix,
+    qubits: Vec<usize>,
+    derivatives: Vec<(Matrix, Vec<(u32, f64)>)>,
+}
+
+/// Returns the derivative of the matrix of an operation with respect to one of it's parameters.
+///
+/// The entries of the matrices of rotations, phase gates and `U3` are trigonometric
+/// polynomials of frequencies $1/2$ and $1$ in each parameter, whose derivatives are given
+/// exactly by the four-term shift rule.
+fn derivative(op: &OpKind<'_, '_>, parameters: &[f64], slot: usize) -> Option<Matrix> {
+    let (mut dim, mut data) = (0, Vec::new());
+
+    for &(shift, coefficient) in &FOUR_TERMS {
+        let mut shifted = parameters.to_vec();
+        shifted[slot] += shift;
+        let matrix = op.matrix(&shifted)?;
+
+        dim = matrix.dim();
+        data.resize(dim * dim, Complex::ZERO);
+        data.iter_mut().zip(matrix.as_slice()).for_each(|(x, &y)| *x += y * coefficient);
+    }
+
+    Matrix::from_vec(dim, data)
+}
+
+/// Returns the inner product $\langle x | y \rangle$ of two state vectors.
+#[inline]
+fn inner(x: &[Complex], y: &[Complex]) -> Complex {
+    x.iter().zip(y).map(|(x, y)| x.conj() * *y).sum()
+}
+
+impl StateVector {
+    /// Returns the gradient of the expectation value of the observable after running the
+    /// circuit, with respect to the formal parameters. The formal parameter of id `i` takes
+    /// the value `values[i]`, and the derivative with respect to it is at index `i` of the
+    /// result.
+    ///
+    /// Unlike [`gradient`](super::gradient()), which runs the circuit at least twice per
+    /// occurrence of a formal parameter, the gradient is computed in a single forward and
+    /// backward sweep over the circuit, at the cost of keeping three state vectors in memory.
+    ///
+    /// Fails if the circuit contains non unitary operations, or modifiers. Formal parameters
+    /// may be used by rotations, phase gates and `U3`, as well as in expressions.
+    pub fn adjoint_gradient(circ: &QuantumCircuit, values: &[f64], observable: &Observable) -> Result<Vec<f64>, SimulationError> {
+        let mut sim = Self::new(circ.qubit_count(), 0, 0)?;
+        let mut gates = Vec::new();
+        let mut iter = circ.instructions();
+
+        while let Some(instr) = iter.next() {
+            if let Some(modifier) = &instr.modifier {
+                return Err(SimulationError::UnsupportedModifier(modifier.label()));
+            }
+
+            if matches!(instr.op, OpKind::Nop | OpKind::Barrier) {
+                continue;
+            }
+
+            let unsupported = SimulationError::UnsupportedOperation(instr.op.label());
+            let parameters = resolve_all(circ, instr.parameters, values)?;
+            let mut derivatives = Vec::new();
+
+            for (slot, &parameter) in instr.parameters.iter().enumerate() {
+                let partials = partials(circ, parameter, values)?;
+
+                if !partials.is_empty() {
+                    let matrix = derivative(&instr.op, &parameters, slot).ok_or(unsupported.clone())?;
+                    derivatives.push((matrix, partials));
+                }
+            }
+
+            let gate = Gate {
+                matrix: instr.op.matrix(&parameters).ok_or(unsupported)?,
+                qubits: instr.qubits.iter().map(|qubit| qubit.id() as usize).collect(),
+                derivatives,
+            };
+
+            sim.apply_matrix(&gate.matrix, &gate.qubits);
+            gates.push(gate);
+        }
+
+        let mut res = vec![0.0; circ.parameter_count()];
+        let mut lambda = observable.apply(sim.amplitudes());
+        let mut phi = sim.amplitudes().to_vec();
+
+        for gate in gates.iter().rev() {
+            let adjoint = gate.matrix.adjoint();
+            apply_matrix(&mut phi, &adjoint, &gate.qubits);
+
+            for (matrix, partials) in &gate.derivatives {
+                let mut mu = phi.clone();
+                apply_matrix(&mut mu, matrix, &gate.qubits);
+                let derivative = 2.0 * inner(&lambda, &mu).re;
+
+                for &(id, partial) in partials {
+                    res[id as usize] += partial * derivative;
+                }
+            }
+
+            apply_matrix(&mut lambda, &adjoint, &gate.qubits);
+        }
+
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::expression::Expr;
+    use crate::circuit::instruction::{Instr, Modifier};
+    use crate::circuit::symbol::FormalParameter;
+    use crate::sim::{Pauli, gradient};
+
+    use super::*;
+
+    #[test]
+    fn adjoint_gradient() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [a, b, c] = circ.alloc_n()?;
+            let [theta, phi, lambda]: [FormalParameter; 3] = circ.alloc_n()?;
+            let product = circ.expr(Expr::from(theta) * Expr::from(phi) - Expr::from(lambda).cos())?;
+
+            circ.h(a).rx(theta, a).ry(phi, b).rz(product, c).h(c).cx(a, b).barrier(&[a, b, c]);
+            circ.phase(lambda, b).u3(theta, product, lambda, c).rxx(phi, a, c).rzz(lambda, b, c);
+            circ.crz(theta, c, a).cp(product, a, b).ry(0.4, c).ccx(a, b, c).t(b);
+            Ok(())
+        }).unwrap();
+
+        let mut observable = Observable::new();
+        observable.add(1.0, &[(0, Pauli::Z), (1, Pauli::X)]).add(-0.5, &[(1, Pauli::Y), (2, Pauli::Z)]).add(0.3, &[(0, Pauli::X)]);
+
+        let values = [0.3, -1.1, 2.0];
+        let adjoint = StateVector::adjoint_gradient(&circ, &values, &observable).unwrap();
+        let shifts = gradient(&circ, &values, &observable, || StateVector::new(3, 0, 0)).unwrap();
+
+        assert_eq!(adjoint.len(), 3);
+        assert!(adjoint.iter().zip(&shifts).all(|(x, y)| (x - y).abs() < 1e-10), "{:?} != {:?}", adjoint, shifts);
+    }
+
+    #[test]
+    fn errors() {
+        let circ = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            let theta: FormalParameter = circ.alloc()?;
+            circ.push(Instr { op: OpKind::RX, qubits: &[q], parameters: &[theta.into()], modifier: Some(Modifier::Inverse), ..Default::default() })?;
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(StateVector::adjoint_gradient(&circ, &[0.5], &Observable::z(0)), Err(SimulationError::UnsupportedModifier("inv")));
+    }
+}
@@ -0,0 +1,274 @@
+use crate::bitset::BitSet;
+use crate::circuit::QuantumCircuit;
+use crate::circuit::operation::OpKind;
+use crate::complex::Complex;
+use crate::matrix::Matrix;
+
+use super::noise::NoiseModel;
+use super::{Backend, Rng, SimulationError};
+
+/// A simulator storing the density matrix $\rho$ of the qubits, which allows it to
+/// simulate mixed states and noise. Operations are applied as $U \rho U^\dagger$, and
+/// noise channels as $\sum_k K_k \rho K_k^\dagger$.
+#[derive(Clone, Debug)]
+pub struct DensityMatrix {
+    qubit_count: usize,
+    /// The entries of $\rho$, the entry at row $r$ and column $c$ is at index $(r \ll n) | c$.
+    rho: Vec<Complex>,
+    register: BitSet,
+    noise: NoiseModel,
+    rng: Rng,
+}
+
+impl DensityMatrix {
+    /// The maximum number of qubits this simulator accepts.
+    pub const MAX_QUBITS: usize = 14;
+
+    /// Creates a new, noiseless, simulator with all qubits in the $|0\rangle$ state and
+    /// all bits set to `false`, with the given seed for measurements.
+    pub fn new(qubit_count: usize, bit_count: usize, seed: u64) -> Result<Self, SimulationError> {
+        if qubit_count > Self::MAX_QUBITS {
+            return Err(SimulationError::TooManyQubits(qubit_count));
+        }
+
+        let mut rho = vec![Complex::ZERO; 1 << (2 * qubit_count)];
+        rho[0] = Complex::ONE;
+
+        Ok(Self {
+            qubit_count,
+            rho,
+            register: BitSet::new(bit_count),
+            noise: NoiseModel::default(),
+            rng: Rng::new(seed),
+        })
+    }
+
+    /// Returns the simulator with the given noise model.
+    #[inline]
+    pub fn with_noise(mut self, noise: NoiseModel) -> Self {
+        self.noise = noise;
+        self
+    }
+
+    /// Creates a simulator sized for the circuit, with the given noise model, and
+    /// runs the circuit on it.
+    #[inline]
+    pub fn simulate(circ: &QuantumCircuit, noise: NoiseModel, seed: u64) -> Result<Self, SimulationError> {
+        let mut res = Self::new(circ.qubit_count(), circ.bit_count(), seed)?.with_noise(noise);
+        res.run(circ)?;
+        Ok(res)
+    }
+
+    #[inline]
+    pub fn qubit_count(&self) -> usize {
+        self.qubit_count
+    }
+
+    #[inline]
+    pub fn noise(&self) -> &NoiseModel {
+        &self.noise
+    }
+
+    /// Returns the entry of the density matrix at the given row and column.
+    #[inline]
+    pub fn get(&self, row: usize, col: usize) -> Complex {
+        self.rho[row << self.qubit_count | col]
+    }
+
+    /// Returns the density matrix.
+    #[inline]
+    pub fn to_matrix(&self) -> Matrix {
+        Matrix::from_vec(1 << self.qubit_count, self.rho.clone()).unwrap()
+    }
+
+    /// Returns the probability of each basis state, that is to say the diagonal of $\rho$.
+    #[inline]
+    pub fn probabilities(&self) -> Vec<f64> {
+        (0..1 << self.qubit_count).map(|i| self.get(i, i).re).collect()
+    }
+
+    /// Returns the probability of measuring the qubit in the $|1\rangle$ state.
+    pub fn probability_one(&self, qubit: usize) -> f64 {
+        (0..1 << self.qubit_count)
+            .filter(|i| i & (1 << qubit) != 0)
+            .map(|i| self.get(i, i).re)
+            .sum()
+    }
+
+    /// Returns the purity $\text{tr}(\rho^2)$ of the state, which is one for pure states.
+    pub fn purity(&self) -> f64 {
+        // Since rho is hermitian, tr(rho^2) is the sum of the squared modulus of its entries.
+        self.rho.iter().map(|z| z.norm_sqr()).sum()
+    }
+
+    /// Applies a matrix $U$ acting on the given qubits, as $U \rho U^\dagger$.
+    pub fn apply_matrix(&mut self, matrix: &Matrix, qubits: &[usize]) {
+        Self::conjugate(&mut self.rho, self.qubit_count, matrix, qubits);
+    }
+
+    /// Applies a channel given by it's Kraus operators on the given qubits.
+    pub fn apply_kraus(&mut self, kraus: &[Matrix], qubits: &[usize]) {
+        let mut res = vec![Complex::ZERO; self.rho.len()];
+
+        for k in kraus {
+            let mut term = self.rho.clone();
+            Self::conjugate(&mut term, self.qubit_count, k, qubits);
+            res.iter_mut().zip(term).for_each(|(x, y)| *x += y);
+        }
+
+        self.rho = res;
+    }
+
+    /// Computes $M \rho M^\dagger$ in place.
+    fn conjugate(rho: &mut [Complex], qubit_count: usize, matrix: &Matrix, qubits: &[usize]) {
+        let rows: Vec<_> = qubits.iter().map(|q| q + qubit_count).collect();
+        super::apply_matrix(rho, matrix, &rows);
+        super::apply_matrix(rho, &matrix.conj(), qubits);
+    }
+
+    /// Applies the noise attached to the given label on the qubit.
+    fn apply_noise(&mut self, label: &str, qubit: usize) {
+        if self.noise.is_ideal() {
+            return;
+        }
+
+        let kraus: Vec<_> = self.noise.channels(label, qubit).map(|channel| channel.kraus()).collect();
+        kraus.iter().for_each(|kraus| self.apply_kraus(kraus, &[qubit]));
+    }
+}
+
+impl Backend for DensityMatrix {
+    #[inline]
+    fn register(&self) -> &BitSet {
+        &self.register
+    }
+
+    #[inline]
+    fn register_mut(&mut self) -> &mut BitSet {
+        &mut self.register
+    }
+
+    fn apply(&mut self, op: &OpKind<'_, '_>, qubits: &[usize], parameters: &[f64]) -> Result<(), SimulationError> {
+        let matrix = op.matrix(parameters).ok_or(SimulationError::UnsupportedOperation(op.label()))?;
+        self.apply_matrix(&matrix, qubits);
+        qubits.iter().for_each(|&qubit| self.apply_noise(op.label(), qubit));
+        Ok(())
+    }
+
+    fn measure(&mut self, qubit: usize) -> bool {
+        self.apply_noise(OpKind::Measure.label(), qubit);
+
+        let one = self.probability_one(qubit);
+        let outcome = self.rng.next_f64() < one;
+        let norm = if outcome { one } else { 1.0 - one };
+        let n = self.qubit_count;
+
+        for (i, z) in self.rho.iter_mut().enumerate() {
+            let row = i >> n & (1 << qubit) != 0;
+            let col = i & (1 << qubit) != 0;
+
+            if row == outcome && col == outcome {
+                *z = *z / norm;
+            } else {
+                *z = Complex::ZERO;
+            }
+        }
+
+        match self.noise.readout(qubit) {
+            Some(error) => {
+                let flip = if outcome { error.p10 } else { error.p01 };
+                outcome ^ (self.rng.next_f64() < flip)
+            }
+            None => outcome,
+        }
+    }
+
+    fn reset(&mut self, qubit: usize) {
+        let mut k0 = Matrix::zeros(2);
+        let mut k1 = Matrix::zeros(2);
+        k0[(0, 0)] = Complex::ONE;
+        k1[(0, 1)] = Complex::ONE;
+
+        self.apply_kraus(&[k0, k1], &[qubit]);
+        self.apply_noise(OpKind::Reset.label(), qubit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sim::noise::{NoiseChannel, ReadoutError};
+    use crate::sim::StateVector;
+
+    use super::*;
+
+    const EPS: f64 = 1e-12;
+
+    fn bell() -> QuantumCircuit {
+        QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+            circ.h(q1).cx(q1, q2);
+            Ok(())
+        }).unwrap()
+    }
+
+    #[test]
+    fn ideal_matches_statevector() {
+        let circ = bell();
+        let rho = DensityMatrix::simulate(&circ, NoiseModel::new(), 0).unwrap();
+        let psi = StateVector::simulate(&circ, 0).unwrap();
+        let amplitudes = psi.amplitudes();
+
+        for i in 0..4 {
+            for j in 0..4 {
+                assert!(rho.get(i, j).approx_eq(amplitudes[i] * amplitudes[j].conj(), EPS));
+            }
+        }
+
+        assert!((rho.purity() - 1.0).abs() < EPS);
+    }
+
+    #[test]
+    fn noise() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+            circ.x(q1).x(q2);
+            Ok(())
+        }).unwrap();
+
+        let mut noise = NoiseModel::new();
+        noise.add_on("x", 0, NoiseChannel::AmplitudeDamping(1.0)).unwrap()
+            .add_on("x", 1, NoiseChannel::Depolarizing(0.5)).unwrap();
+
+        assert_eq!(noise.add("x", NoiseChannel::Depolarizing(1.5)).err(), Some(SimulationError::InvalidProbability));
+        assert_eq!(
+            noise.add("x", NoiseChannel::Pauli { x: 0.5, y: 0.5, z: 0.5 }).err(),
+            Some(SimulationError::InvalidProbability),
+        );
+
+        let rho = DensityMatrix::simulate(&circ, noise, 0).unwrap();
+
+        assert!(rho.probability_one(0) < EPS);
+        assert!((rho.probability_one(1) - 2.0 / 3.0).abs() < EPS);
+        assert!(rho.purity() < 1.0 - EPS);
+    }
+
+    #[test]
+    fn readout() {
+        let circ = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            let b = circ.alloc()?;
+            circ.measure(&[q], &[b]);
+            Ok(())
+        }).unwrap();
+
+        let mut noise = NoiseModel::new();
+        noise.set_readout(ReadoutError { p01: 1.0, p10: 0.0 }).unwrap();
+
+        assert!(noise.set_readout_on(0, ReadoutError { p01: -0.1, p10: 0.0 }).is_err());
+
+        let rho = DensityMatrix::simulate(&circ, noise, 0).unwrap();
+
+        assert_eq!(rho.register().get(0), Some(true));
+        assert!(rho.probability_one(0) < EPS);
+    }
+}
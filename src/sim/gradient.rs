@@ -0,0 +1,306 @@
+//! Gradients of expectation values with respect to formal parameters, computed with
+//! the parameter-shift rule of Schuld, Bergholm, Gogolin, Izaac and Killoran [10], and
+//! it's generalisation by Wierichs, Izaac, Wang and Lin [11], see `doc/biblio.md`.
+
+use std::f64::consts::{FRAC_PI_2, SQRT_2};
+
+use crate::circuit::QuantumCircuit;
+use crate::circuit::instruction::{Instr, Modifier};
+use crate::circuit::operation::OpKind;
+use crate::circuit::parameter::Parameter;
+use crate::circuit::symbol::Symbol;
+
+use super::observable::{Expectation, Observable};
+use super::{SimulationError, execute, resolve, resolve_all};
+
+/// The two-term shift rule of gates whose generator has two eigenvalues, $\pm 1/2$ for
+/// Pauli rotations, as pairs of a shift and a coefficient.
+const TWO_TERMS: [(f64, f64); 2] = [(FRAC_PI_2, 0.5), (-FRAC_PI_2, -0.5)];
+
+/// The four-term shift rule of controlled Pauli rotations, whose generator has the
+/// eigenvalues $0$ and $\pm 1/2$.
+pub(crate) const FOUR_TERMS: [(f64, f64); 4] = {
+    let plus = (SQRT_2 + 1.0) / (4.0 * SQRT_2);
+    let minus = (SQRT_2 - 1.0) / (4.0 * SQRT_2);
+    [(FRAC_PI_2, plus), (-FRAC_PI_2, -plus), (3.0 * FRAC_PI_2, -minus), (-3.0 * FRAC_PI_2, minus)]
+};
+
+/// Returns the shift rule of a parameter of an instruction, as pairs of a shift and a
+/// coefficient, such that the derivative of an expectation value with respect to the
+/// parameter is the sum of the coefficients times the shifted expectation values.
+fn shift_rule(circ: &QuantumCircuit, instr: &Instr<'_, '_>, slot: usize) -> Result<Vec<(f64, f64)>, SimulationError> {
+    // Rotations are powers of their generator, with angle the parameter.
+    let (pauli, rotation) = match instr.op {
+        OpKind::RX | OpKind::RY | OpKind::RZ | OpKind::RXX | OpKind::RZZ | OpKind::CRZ => (true, true),
+        // The generators of phase gates are projectors, with eigenvalues 0 and 1.
+        OpKind::Phase | OpKind::CP => (false, true),
+        // U3(theta, phi, lambda) is exactly P(phi) RY(theta) P(lambda).
+        OpKind::U3 => (slot == 0, false),
+        ref op => return Err(SimulationError::UnsupportedOperation(op.label())),
+    };
+
+    let (controlled, scale) = match &instr.modifier {
+        None => (false, 1.0),
+        Some(Modifier::Control(_) | Modifier::NegControl(_)) => (true, 1.0),
+        Some(Modifier::Inverse) => (false, -1.0),
+        // The power of a rotation is the rotation by a multiple of it's angle.
+        Some(Modifier::Pow(exponent)) if rotation && exponent.is_value() => (false, resolve(circ, *exponent, &[])?),
+        Some(modifier) => return Err(SimulationError::UnsupportedModifier(modifier.label())),
+    };
+
+    // CRZ is a controlled Pauli rotation, whatever it's modifier.
+    let controlled = controlled || matches!(instr.op, OpKind::CRZ);
+
+    if scale == 0.0 {
+        return Ok(Vec::new());
+    }
+
+    let rule: &[_] = if pauli && controlled { &FOUR_TERMS } else { &TWO_TERMS };
+    Ok(rule.iter().map(|&(shift, coefficient)| (shift / scale, coefficient * scale)).collect())
+}
+
+/// Returns the partial derivatives of a parameter with respect to the formal parameters
+/// it depends on, as pairs of an id and a derivative.
+pub(crate) fn partials(circ: &QuantumCircuit, parameter: Parameter<'_>, values: &[f64]) -> Result<Vec<(u32, f64)>, SimulationError> {
+    if let Some(formal) = parameter.as_formal() {
+        return Ok(vec![(formal.id(), 1.0)]);
+    }
+
+    let Some(expr) = circ.expression(parameter) else {
+        return Ok(Vec::new());
+    };
+
+    expr.formals()
+        .into_iter()
+        .map(|id| Ok((id, expr.derivative(id).eval(values).map_err(SimulationError::UnboundParameter)?)))
+        .collect()
+}
+
+/// Runs the circuit on the backend, shifting the value of a single parameter, given
+/// by the index of it's instruction and it's index in the instruction.
+fn run_shifted<B: Expectation>(backend: &mut B, circ: &QuantumCircuit, values: &[f64], (index, slot, shift): (usize, usize, f64)) -> Result<(), SimulationError> {
+    let mut iter = circ.instructions();
+    let mut i = 0;
+
+    while let Some(instr) = iter.next() {
+        let mut parameters = resolve_all(circ, instr.parameters, values)?;
+
+        if i == index {
+            parameters[slot] += shift;
+        }
+
+        execute(backend, circ, instr, &parameters, values)?;
+        i += 1;
+    }
+
+    Ok(())
+}
+
+/// Returns the expectation value of the observable after running the circuit, with the
+/// formal parameter of id `i` taking the value `values[i]`, on a fresh backend returned
+/// by `backend`.
+pub fn expectation<B, F>(circ: &QuantumCircuit, values: &[f64], observable: &Observable, mut backend: F) -> Result<f64, SimulationError>
+where
+    B: Expectation,
+    F: FnMut() -> Result<B, SimulationError>,
+{
+    let mut sim = backend()?;
+    sim.run_with_parameters(circ, values)?;
+    Ok(sim.expectation(observable))
+}
+
+/// Returns the gradient of the expectation value of the observable after running the
+/// circuit, with respect to the formal parameters. The formal parameter of id `i` takes
+/// the value `values[i]`, and the derivative with respect to it is at index `i` of the
+/// result.
+///
+/// The parameter-shift rule is applied to each occurrence of each formal parameter,
+/// including in expressions. Each shift runs the circuit on a fresh backend returned by
+/// `backend`: that is two runs per occurrence in rotations, and four in controlled Pauli
+/// rotations. The circuit should not contain measurements, whose outcomes would differ
+/// from one run to another.
+///
+/// Fails if a formal parameter is used by an operation other than rotations, phase gates
+/// and `U3`, by a classical modifier, or as the exponent of a power.
+pub fn gradient<B, F>(circ: &QuantumCircuit, values: &[f64], observable: &Observable, mut backend: F) -> Result<Vec<f64>, SimulationError>
+where
+    B: Expectation,
+    F: FnMut() -> Result<B, SimulationError>,
+{
+    let mut res = vec![0.0; circ.parameter_count()];
+    let mut iter = circ.instructions();
+    let mut index = 0;
+
+    while let Some(instr) = iter.next() {
+        if let Some(Modifier::Pow(exponent)) = &instr.modifier {
+            if !exponent.is_value() {
+                return Err(SimulationError::UnsupportedModifier("pow"));
+            }
+        }
+
+        for (slot, &parameter) in instr.parameters.iter().enumerate() {
+            let partials = partials(circ, parameter, values)?;
+
+            if partials.is_empty() {
+                continue;
+            }
+
+            let mut derivative = 0.0;
+
+            for (shift, coefficient) in shift_rule(circ, instr, slot)? {
+                let mut sim = backend()?;
+                run_shifted(&mut sim, circ, values, (index, slot, shift))?;
+                derivative += coefficient * sim.expectation(observable);
+            }
+
+            for (id, partial) in partials {
+                res[id as usize] += partial * derivative;
+            }
+        }
+
+        index += 1;
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::expression::Expr;
+    use crate::circuit::symbol::FormalParameter;
+    use crate::sim::{DensityMatrix, NoiseModel, Pauli, StateVector};
+
+    use super::*;
+
+    /// Returns the gradient computed with central finite differences.
+    fn finite_differences(circ: &QuantumCircuit, values: &[f64], observable: &Observable) -> Vec<f64> {
+        let h = 1e-5;
+        let backend = || StateVector::new(circ.qubit_count(), 0, 0);
+
+        (0..values.len())
+            .map(|i| {
+                let mut shifted = values.to_vec();
+                shifted[i] += h;
+                let forward = expectation(circ, &shifted, observable, backend).unwrap();
+                shifted[i] -= 2.0 * h;
+                let backward = expectation(circ, &shifted, observable, backend).unwrap();
+                (forward - backward) / (2.0 * h)
+            })
+            .collect()
+    }
+
+    fn check(circ: &QuantumCircuit, values: &[f64], observable: &Observable) {
+        let exact = gradient(circ, values, observable, || StateVector::new(circ.qubit_count(), 0, 0)).unwrap();
+        let approx = finite_differences(circ, values, observable);
+
+        assert_eq!(exact.len(), circ.parameter_count());
+        assert!(exact.iter().zip(&approx).all(|(x, y)| (x - y).abs() < 1e-6), "{:?} != {:?}", exact, approx);
+    }
+
+    fn observable() -> Observable {
+        let mut res = Observable::new();
+        res.add(1.0, &[(0, Pauli::Z), (1, Pauli::X)]).add(-0.5, &[(1, Pauli::Y), (2, Pauli::Z)]).add(0.3, &[(0, Pauli::X)]);
+        res
+    }
+
+    #[test]
+    fn rotations() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [a, b, c] = circ.alloc_n()?;
+            let [theta, phi, lambda]: [FormalParameter; 3] = circ.alloc_n()?;
+
+            circ.h(a).rx(theta, a).ry(phi, b).rz(theta, c).h(c).cx(a, b);
+            circ.phase(lambda, b).u3(theta, phi, lambda, c).rxx(phi, a, c).rzz(lambda, b, c);
+            circ.crz(theta, c, a).cp(phi, a, b).ry(0.4, c);
+            Ok(())
+        }).unwrap();
+
+        check(&circ, &[0.3, -1.1, 2.0], &observable());
+    }
+
+    #[test]
+    fn modifiers() {
+        let circ = QuantumCircuit::new(|circ| {
+            let controls = circ.alloc_list(2)?;
+            let target = circ.alloc_list(1)?;
+            let [a, b, c] = [controls.get(0).unwrap(), controls.get(1).unwrap(), target.get(0).unwrap()];
+            let [theta, phi]: [FormalParameter; 2] = circ.alloc_n()?;
+            let sum = circ.expr(Expr::from(theta) * 2.0 + Expr::from(phi).sin())?;
+
+            circ.h(a).h(b).ry(0.7, c);
+            circ.push(Instr { op: OpKind::RY, qubits: &[c], parameters: &[theta.into()], modifier: Some(Modifier::Control(controls)), ..Default::default() })?;
+            circ.push(Instr { op: OpKind::U3, qubits: &[b], parameters: &[phi.into(), theta.into(), phi.into()], modifier: Some(Modifier::NegControl(target)), ..Default::default() })?;
+            circ.push(Instr { op: OpKind::RX, qubits: &[a], parameters: &[sum], modifier: Some(Modifier::Pow(0.5.into())), ..Default::default() })?;
+            circ.push(Instr { op: OpKind::U3, qubits: &[c], parameters: &[sum, phi.into(), theta.into()], modifier: Some(Modifier::Inverse), ..Default::default() })?;
+            circ.push(Instr { op: OpKind::CRZ, qubits: &[a, c], parameters: &[theta.into()], modifier: Some(Modifier::Pow(1.5.into())), ..Default::default() })?;
+            circ.push(Instr { op: OpKind::CRZ, qubits: &[b, c], parameters: &[sum], modifier: Some(Modifier::Inverse), ..Default::default() })?;
+            Ok(())
+        }).unwrap();
+
+        check(&circ, &[0.9, 0.2], &observable());
+    }
+
+    #[test]
+    fn noisy() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [a, b] = circ.alloc_n()?;
+            let theta: FormalParameter = circ.alloc()?;
+            circ.h(a).rx(theta, b).cx(a, b).ry(theta, a);
+            Ok(())
+        }).unwrap();
+
+        let mut noise = NoiseModel::new();
+        noise.add("cx", crate::sim::NoiseChannel::Depolarizing(0.1)).unwrap();
+
+        let backend = || Ok(DensityMatrix::new(2, 0, 0)?.with_noise(noise.clone()));
+        let observable = Observable::z(0);
+        let exact = gradient(&circ, &[0.4], &observable, backend).unwrap();
+
+        let h = 1e-5;
+        let forward = expectation(&circ, &[0.4 + h], &observable, backend).unwrap();
+        let backward = expectation(&circ, &[0.4 - h], &observable, backend).unwrap();
+        assert!((exact[0] - (forward - backward) / (2.0 * h)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn errors() {
+        let backend = || StateVector::new(1, 0, 0);
+
+        let circ = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            let theta: FormalParameter = circ.alloc()?;
+            circ.push(Instr { op: OpKind::H, qubits: &[q], modifier: Some(Modifier::Pow(theta.into())), ..Default::default() })?;
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(gradient(&circ, &[0.5], &Observable::z(0), backend), Err(SimulationError::UnsupportedModifier("pow")));
+
+        let circ = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            let theta: FormalParameter = circ.alloc()?;
+            circ.push(Instr { op: OpKind::RX, qubits: &[q], parameters: &[theta.into()], modifier: Some(Modifier::ForConst(2)), ..Default::default() })?;
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(gradient(&circ, &[0.5], &Observable::z(0), backend), Err(SimulationError::UnsupportedModifier("for")));
+
+        let circ = QuantumCircuit::new(|circ| {
+            let [a, b] = circ.alloc_n()?;
+            let theta: FormalParameter = circ.alloc()?;
+            circ.push(Instr { op: OpKind::CRZ, qubits: &[a, b], parameters: &[theta.into()], modifier: Some(Modifier::ForConst(2)), ..Default::default() })?;
+            Ok(())
+        }).unwrap();
+
+        let result = gradient(&circ, &[0.5], &Observable::z(0), || StateVector::new(2, 0, 0));
+        assert_eq!(result, Err(SimulationError::UnsupportedModifier("for")));
+
+        let circ = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            let theta: FormalParameter = circ.alloc()?;
+            circ.rx(theta, q);
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(gradient(&circ, &[], &Observable::z(0), backend), Err(SimulationError::UnboundParameter(0)));
+    }
+}
@@ -0,0 +1,232 @@
+//! Simulators running quantum circuits on a classical computer.
+
+mod adjoint;
+mod gradient;
+mod rng;
+
+pub mod density;
+pub mod noise;
+pub mod observable;
+pub mod shots;
+pub mod statevector;
+pub mod tableau;
+mod unitary;
+
+use thiserror::Error;
+
+use crate::bitset::BitSet;
+use crate::circuit::QuantumCircuit;
+use crate::circuit::decompose::{Quantum, expand};
+use crate::circuit::instruction::{Compute, Instr, Modifier};
+use crate::circuit::operation::OpKind;
+use crate::circuit::parameter::Parameter;
+use crate::circuit::symbol::{Bit, Symbol};
+use crate::complex::Complex;
+use crate::matrix::Matrix;
+
+pub use density::DensityMatrix;
+pub use gradient::{expectation, gradient};
+pub use noise::{NoiseChannel, NoiseModel, ReadoutError};
+pub use observable::{Expectation, Observable, Pauli};
+pub use shots::{Counts, run, run_with};
+pub use statevector::StateVector;
+pub use tableau::Tableau;
+pub use unitary::{MAX_UNITARY_QUBITS, equivalent};
+
+pub(crate) use rng::Rng;
+
+#[derive(Clone, PartialEq, Eq, Debug, Error)]
+pub enum SimulationError {
+    #[error("formal parameter {0} is not bound to a value")]
+    UnboundParameter(u32),
+    #[error("operation `{0}` is not supported by this simulator")]
+    UnsupportedOperation(&'static str),
+    #[error("modifier `{0}` is not supported by this simulator")]
+    UnsupportedModifier(&'static str),
+    #[error("cannot simulate {0} qubits")]
+    TooManyQubits(usize),
+    #[error("noise probabilities must lie between 0 and 1")]
+    InvalidProbability,
+}
+
+/// The primitives a simulator must provide. Running a circuit, that is to say interpreting
+/// modifiers, compute nodes and the classical register, is shared by all backends through
+/// the provided methods.
+pub trait Backend {
+    /// Returns the classical register of the simulation.
+    fn register(&self) -> &BitSet;
+
+    /// Returns a mutable reference to the classical register of the simulation.
+    fn register_mut(&mut self) -> &mut BitSet;
+
+    /// Applies a quantum operation to the given qubits, with the given parameter values.
+    /// Measure, reset, barrier, no-op and compute operations are never passed to this method.
+    fn apply(&mut self, op: &OpKind<'_, '_>, qubits: &[usize], parameters: &[f64]) -> Result<(), SimulationError>;
+
+    /// Measures the qubit in the computational basis, collapsing the state.
+    fn measure(&mut self, qubit: usize) -> bool;
+
+    /// Resets the qubit to the $|0\rangle$ state.
+    fn reset(&mut self, qubit: usize);
+
+    /// Runs the circuit, which must not contain any formal parameter.
+    #[inline]
+    fn run(&mut self, circ: &QuantumCircuit) -> Result<(), SimulationError>
+    where
+        Self: Sized
+    {
+        self.run_with_parameters(circ, &[])
+    }
+
+    /// Runs the circuit, replacing each formal parameter by the value at the index
+    /// of it's id in `values`.
+    fn run_with_parameters(&mut self, circ: &QuantumCircuit, values: &[f64]) -> Result<(), SimulationError>
+    where
+        Self: Sized
+    {
+        let mut iter = circ.instructions();
+
+        while let Some(instr) = iter.next() {
+            let parameters = resolve_all(circ, instr.parameters, values)?;
+            execute(self, circ, instr, &parameters, values)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Applies a matrix acting on the given qubits to a vector of $2^n$ amplitudes. The first
+/// qubit is the least significant bit of the matrix indices.
+pub(crate) fn apply_matrix(amplitudes: &mut [Complex], matrix: &Matrix, qubits: &[usize]) {
+    let dim = 1 << qubits.len();
+    assert_eq!(matrix.dim(), dim, "matrix dimension does not match the number of qubits");
+
+    let mask = qubits.iter().fold(0, |acc, q| acc | 1 << q);
+    let offsets: Vec<usize> = (0..dim)
+        .map(|local| qubits.iter()
+            .enumerate()
+            .filter(|(j, _)| local >> j & 1 == 1)
+            .fold(0, |acc, (_, q)| acc | 1 << q))
+        .collect();
+
+    let mut buffer = vec![Complex::ZERO; dim];
+
+    for base in (0..amplitudes.len()).filter(|base| base & mask == 0) {
+        for (x, offset) in buffer.iter_mut().zip(&offsets) {
+            *x = amplitudes[base | offset];
+        }
+
+        for (i, offset) in offsets.iter().enumerate() {
+            amplitudes[base | offset] = (0..dim).map(|j| matrix[(i, j)] * buffer[j]).sum();
+        }
+    }
+}
+
+/// Returns the value of a parameter of the circuit, looking up formal parameters in `values`.
+#[inline]
+pub(crate) fn resolve(circ: &QuantumCircuit, parameter: Parameter<'_>, values: &[f64]) -> Result<f64, SimulationError> {
+    circ.evaluate(parameter, values).map_err(SimulationError::UnboundParameter)
+}
+
+/// Returns the values of parameters of the circuit, see [`resolve`].
+#[inline]
+pub(crate) fn resolve_all(circ: &QuantumCircuit, parameters: &[Parameter<'_>], values: &[f64]) -> Result<Vec<f64>, SimulationError> {
+    parameters.iter().map(|&parameter| resolve(circ, parameter, values)).collect()
+}
+
+/// Reads the given bits from the register, in order.
+#[inline]
+fn gather(register: &BitSet, bits: &[Bit<'_>]) -> BitSet {
+    bits.iter().map(|bit| register.get(bit.id() as usize).unwrap_or(false)).collect()
+}
+
+/// Evaluates a compute on the register.
+#[inline]
+fn eval<T>(register: &BitSet, compute: &Compute<'_, '_, T>) -> T {
+    (compute.func)(gather(register, compute.bits))
+}
+
+/// Executes a single instruction on the backend, honouring it's modifier. The values of
+/// the parameters of the instruction are given, those of the modifier are resolved.
+pub(crate) fn execute<B: Backend>(
+    backend: &mut B,
+    circ: &QuantumCircuit,
+    instr: &Instr<'_, '_>,
+    parameters: &[f64],
+    values: &[f64],
+) -> Result<(), SimulationError> {
+    let bit = |backend: &B, bit: &Bit<'_>| backend.register().get(bit.id() as usize).unwrap_or(false);
+
+    match &instr.modifier {
+        None => execute_op(backend, instr, parameters),
+        Some(Modifier::IfBit(b)) => {
+            if bit(backend, b) {
+                execute_op(backend, instr, parameters)?;
+            }
+            Ok(())
+        }
+        Some(Modifier::IfCompute(compute)) => {
+            if eval(backend.register(), compute) {
+                execute_op(backend, instr, parameters)?;
+            }
+            Ok(())
+        }
+        Some(Modifier::WhileBit(b)) => {
+            while bit(backend, b) {
+                execute_op(backend, instr, parameters)?;
+            }
+            Ok(())
+        }
+        Some(Modifier::WhileCompute(compute)) => {
+            while eval(backend.register(), compute) {
+                execute_op(backend, instr, parameters)?;
+            }
+            Ok(())
+        }
+        Some(Modifier::ForConst(n)) => {
+            (0..*n).try_for_each(|_| execute_op(backend, instr, parameters))
+        }
+        Some(Modifier::ForCompute(compute)) => {
+            let n = eval(backend.register(), compute);
+            (0..n).try_for_each(|_| execute_op(backend, instr, parameters))
+        }
+        // Quantum modifiers are expanded into basic gates, up to a global phase.
+        Some(modifier) => {
+            let quantum = Quantum::new(modifier, |parameter| resolve(circ, parameter, values))?.unwrap();
+            let qubits: Vec<_> = instr.qubits.iter().map(|qubit| qubit.id() as usize).collect();
+
+            expand(&instr.op, &qubits, parameters, &quantum)
+                .iter()
+                .try_for_each(|gate| backend.apply(&gate.op, &gate.qubits, &gate.parameters))
+        }
+    }
+}
+
+/// Executes the operation of a single instruction on the backend, ignoring it's modifier.
+fn execute_op<B: Backend>(backend: &mut B, instr: &Instr<'_, '_>, parameters: &[f64]) -> Result<(), SimulationError> {
+    match &instr.op {
+        OpKind::Nop | OpKind::Barrier => (),
+        OpKind::Measure => {
+            for (qubit, bit) in instr.qubits.iter().zip(instr.bits) {
+                let outcome = backend.measure(qubit.id() as usize);
+                backend.register_mut().set(bit.id() as usize, outcome);
+            }
+        }
+        OpKind::Reset => {
+            instr.qubits.iter().for_each(|qubit| backend.reset(qubit.id() as usize));
+        }
+        OpKind::Compute(compute) => {
+            let res = eval(backend.register(), compute);
+
+            for (i, bit) in instr.bits.iter().enumerate() {
+                backend.register_mut().set(bit.id() as usize, res.get(i).unwrap_or(false));
+            }
+        }
+        op => {
+            let qubits: Vec<_> = instr.qubits.iter().map(|qubit| qubit.id() as usize).collect();
+            backend.apply(op, &qubits, parameters)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+use crate::circuit::operation::OpKind;
+use crate::complex::Complex;
+use crate::matrix::Matrix;
+
+use super::SimulationError;
+
+/// Returns true if the value is a probability.
+#[inline]
+fn is_probability(p: f64) -> bool {
+    (0.0..=1.0).contains(&p)
+}
+
+/// A single-qubit noise channel, described by it's Kraus operators.
+#[derive(Clone, PartialEq, Debug)]
+pub enum NoiseChannel {
+    /// With probability $p$, applies one of the $X$, $Y$ or $Z$ gates, chosen uniformly.
+    Depolarizing(f64),
+    /// Energy relaxation towards $|0\rangle$, with probability $\gamma$ of decaying.
+    AmplitudeDamping(f64),
+    /// Loss of coherence without loss of energy, with parameter $\lambda$.
+    PhaseDamping(f64),
+    /// Applies $X$, $Y$ and $Z$ with the given probabilities, and nothing otherwise.
+    Pauli {
+        x: f64,
+        y: f64,
+        z: f64,
+    },
+}
+
+impl NoiseChannel {
+    /// Returns true if the parameters of the channel are probabilities, those of a Pauli
+    /// channel summing to at most one.
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            Self::Depolarizing(p) | Self::AmplitudeDamping(p) | Self::PhaseDamping(p) => is_probability(p),
+            Self::Pauli { x, y, z } => [x, y, z, x + y + z].into_iter().all(is_probability),
+        }
+    }
+
+    /// Returns the Kraus operators of the channel.
+    pub fn kraus(&self) -> Vec<Matrix> {
+        let pauli = |op: OpKind, p: f64| op.matrix(&[]).unwrap().scale(Complex::from(p.sqrt()));
+        let identity = |p: f64| Matrix::identity(2).scale(Complex::from(p.sqrt()));
+        let single = |i, j, x: f64| {
+            let mut res = Matrix::zeros(2);
+            res[(i, j)] = Complex::from(x);
+            res
+        };
+
+        match *self {
+            Self::Depolarizing(p) => vec![
+                identity(1.0 - p),
+                pauli(OpKind::X, p / 3.0),
+                pauli(OpKind::Y, p / 3.0),
+                pauli(OpKind::Z, p / 3.0),
+            ],
+            Self::AmplitudeDamping(gamma) => vec![
+                Matrix::diagonal(&[Complex::ONE, Complex::from((1.0 - gamma).sqrt())]),
+                single(0, 1, gamma.sqrt()),
+            ],
+            Self::PhaseDamping(lambda) => vec![
+                Matrix::diagonal(&[Complex::ONE, Complex::from((1.0 - lambda).sqrt())]),
+                single(1, 1, lambda.sqrt()),
+            ],
+            Self::Pauli { x, y, z } => vec![
+                identity(1.0 - x - y - z),
+                pauli(OpKind::X, x),
+                pauli(OpKind::Y, y),
+                pauli(OpKind::Z, z),
+            ],
+        }
+    }
+}
+
+/// Classical error on the outcome of a measurement.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct ReadoutError {
+    /// Probability of reading `true` when the qubit was measured in $|0\rangle$.
+    pub p01: f64,
+    /// Probability of reading `false` when the qubit was measured in $|1\rangle$.
+    pub p10: f64,
+}
+
+impl ReadoutError {
+    /// Returns true if both error rates are probabilities.
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        is_probability(self.p01) && is_probability(self.p10)
+    }
+}
+
+/// The noise attached to a single operation label.
+#[derive(Clone, Default, Debug)]
+struct LabelNoise {
+    all: Vec<NoiseChannel>,
+    qubits: HashMap<usize, Vec<NoiseChannel>>,
+}
+
+/// Describes the noise of a device. Channels are attached to operations through their
+/// label (see [`OpKind::label`]), either on every qubit or on a specific one, and are
+/// applied to each qubit the operation acts on, right after it. Channels attached to
+/// `"measure"` are applied right before measurements, and those attached to `"reset"`
+/// right after resets.
+#[derive(Clone, Default, Debug)]
+pub struct NoiseModel {
+    labels: HashMap<String, LabelNoise>,
+    readout: Option<ReadoutError>,
+    readout_qubits: HashMap<usize, ReadoutError>,
+}
+
+impl NoiseModel {
+    /// Returns a noise model without any noise.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attaches a channel to all operations with the given label, on all qubits.
+    /// 
+    /// Fails if the parameters of the channel are not valid probabilities.
+    #[inline]
+    pub fn add(&mut self, label: &str, channel: NoiseChannel) -> Result<&mut Self, SimulationError> {
+        if !channel.is_valid() {
+            return Err(SimulationError::InvalidProbability);
+        }
+
+        self.labels.entry(label.to_owned()).or_default().all.push(channel);
+        Ok(self)
+    }
+
+    /// Attaches a channel to all operations with the given label, when acting on the given qubit.
+    /// 
+    /// Fails if the parameters of the channel are not valid probabilities.
+    #[inline]
+    pub fn add_on(&mut self, label: &str, qubit: usize, channel: NoiseChannel) -> Result<&mut Self, SimulationError> {
+        if !channel.is_valid() {
+            return Err(SimulationError::InvalidProbability);
+        }
+
+        self.labels.entry(label.to_owned()).or_default().qubits.entry(qubit).or_default().push(channel);
+        Ok(self)
+    }
+
+    /// Sets the readout error of all qubits that don't have a specific one.
+    /// 
+    /// Fails if the error rates are not valid probabilities.
+    #[inline]
+    pub fn set_readout(&mut self, error: ReadoutError) -> Result<&mut Self, SimulationError> {
+        if !error.is_valid() {
+            return Err(SimulationError::InvalidProbability);
+        }
+
+        self.readout = Some(error);
+        Ok(self)
+    }
+
+    /// Sets the readout error of the given qubit.
+    /// 
+    /// Fails if the error rates are not valid probabilities.
+    #[inline]
+    pub fn set_readout_on(&mut self, qubit: usize, error: ReadoutError) -> Result<&mut Self, SimulationError> {
+        if !error.is_valid() {
+            return Err(SimulationError::InvalidProbability);
+        }
+
+        self.readout_qubits.insert(qubit, error);
+        Ok(self)
+    }
+
+    /// Returns the channels to apply to the given qubit after an operation with the given label.
+    pub fn channels<'a>(&'a self, label: &str, qubit: usize) -> impl Iterator<Item = &'a NoiseChannel> + 'a {
+        let noise = self.labels.get(label);
+
+        noise.into_iter()
+            .flat_map(|noise| noise.all.iter())
+            .chain(noise.and_then(|noise| noise.qubits.get(&qubit)).into_iter().flatten())
+    }
+
+    /// Returns the readout error of the given qubit, if any.
+    #[inline]
+    pub fn readout(&self, qubit: usize) -> Option<ReadoutError> {
+        self.readout_qubits.get(&qubit).copied().or(self.readout)
+    }
+
+    /// Returns true if the model has no noise at all.
+    #[inline]
+    pub fn is_ideal(&self) -> bool {
+        self.labels.is_empty() && self.readout.is_none() && self.readout_qubits.is_empty()
+    }
+}
@@ -0,0 +1,180 @@
+use crate::complex::Complex;
+
+use super::{Backend, DensityMatrix, StateVector};
+
+/// A single-qubit Pauli operator.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Pauli {
+    X,
+    Y,
+    Z,
+}
+
+/// A tensor product of Pauli operators, stored as the masks of the qubits on which it
+/// has an $X$ part and a $Z$ part, $Y$ being $iXZ$.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Term {
+    coefficient: f64,
+    x: usize,
+    z: usize,
+}
+
+impl Term {
+    /// Returns the image of the basis state $|k\rangle$, which is a single basis state
+    /// multiplied by a phase.
+    #[inline]
+    fn apply(&self, k: usize) -> (usize, Complex) {
+        // Each Y contributes a factor i, each Z part acting on a one a factor -1.
+        let exponent = (self.x & self.z).count_ones() + 2 * (k & self.z).count_ones();
+
+        let phase = match exponent % 4 {
+            0 => Complex::ONE,
+            1 => Complex::new(0.0, 1.0),
+            2 => Complex::from(-1.0),
+            _ => Complex::new(0.0, -1.0),
+        };
+
+        (k ^ self.x, phase)
+    }
+}
+
+/// A Hermitian observable, as a real linear combination of Pauli strings.
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct Observable {
+    terms: Vec<Term>,
+}
+
+impl Observable {
+    /// Returns the null observable.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the observable $Z$ acting on the given qubit.
+    #[inline]
+    pub fn z(qubit: usize) -> Self {
+        let mut res = Self::new();
+        res.add(1.0, &[(qubit, Pauli::Z)]);
+        res
+    }
+
+    /// Adds a Pauli string to the observable, given as the Pauli operator acting on each
+    /// qubit, the identity acting on the other ones. A qubit must appear at most once.
+    pub fn add(&mut self, coefficient: f64, paulis: &[(usize, Pauli)]) -> &mut Self {
+        let mut term = Term { coefficient, x: 0, z: 0 };
+
+        for &(qubit, pauli) in paulis {
+            assert!(qubit < usize::BITS as usize, "qubit {} is out of range", qubit);
+            assert_eq!((term.x | term.z) >> qubit & 1, 0, "qubit {} appears twice", qubit);
+
+            match pauli {
+                Pauli::X => term.x |= 1 << qubit,
+                Pauli::Y => {
+                    term.x |= 1 << qubit;
+                    term.z |= 1 << qubit;
+                }
+                Pauli::Z => term.z |= 1 << qubit,
+            }
+        }
+
+        self.terms.push(term);
+        self
+    }
+
+    /// Returns the image of a state vector by the observable.
+    pub(crate) fn apply(&self, amplitudes: &[Complex]) -> Vec<Complex> {
+        assert!(self.support() < amplitudes.len(), "the observable acts on too many qubits");
+        let mut res = vec![Complex::ZERO; amplitudes.len()];
+
+        for term in &self.terms {
+            for (k, &z) in amplitudes.iter().enumerate() {
+                let (j, phase) = term.apply(k);
+                res[j] += phase * z * term.coefficient;
+            }
+        }
+
+        res
+    }
+
+    /// Returns the mask of the qubits the observable acts on.
+    #[inline]
+    fn support(&self) -> usize {
+        self.terms.iter().fold(0, |acc, term| acc | term.x | term.z)
+    }
+}
+
+/// A simulator able to compute expectation values.
+pub trait Expectation: Backend {
+    /// Returns the expectation value of the observable in the current state.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the observable acts on qubits the simulator does not have.
+    fn expectation(&self, observable: &Observable) -> f64;
+}
+
+impl Expectation for StateVector {
+    fn expectation(&self, observable: &Observable) -> f64 {
+        let image = observable.apply(self.amplitudes());
+        self.amplitudes().iter().zip(&image).map(|(x, y)| x.conj() * *y).sum::<Complex>().re
+    }
+}
+
+impl Expectation for DensityMatrix {
+    fn expectation(&self, observable: &Observable) -> f64 {
+        let dim = 1 << self.qubit_count();
+        assert!(observable.support() < dim, "the observable acts on too many qubits");
+
+        // The trace of rho P, where P maps |k> to a single basis state |j>.
+        observable.terms.iter()
+            .map(|term| {
+                let value: Complex = (0..dim)
+                    .map(|k| {
+                        let (j, phase) = term.apply(k);
+                        self.get(k, j) * phase
+                    })
+                    .sum();
+
+                term.coefficient * value.re
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::QuantumCircuit;
+    use crate::sim::NoiseModel;
+
+    use super::*;
+
+    const EPS: f64 = 1e-12;
+
+    #[test]
+    fn expectation() {
+        // The state (|00> + i|11>) / sqrt(2).
+        let circ = QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+            circ.h(q1).cx(q1, q2).s(q1);
+            Ok(())
+        }).unwrap();
+
+        let mut observable = Observable::new();
+        observable
+            .add(0.5, &[(0, Pauli::Z), (1, Pauli::Z)])
+            .add(2.0, &[(0, Pauli::X), (1, Pauli::Y)])
+            .add(-1.0, &[(1, Pauli::Z)])
+            .add(3.0, &[]);
+
+        // <ZZ> = 1, <XY> = 1, <Z2> = 0 and the identity contributes it's coefficient.
+        let expected = 0.5 + 2.0 + 3.0;
+
+        let sv = StateVector::simulate(&circ, 0).unwrap();
+        let dm = DensityMatrix::simulate(&circ, NoiseModel::new(), 0).unwrap();
+
+        assert!((sv.expectation(&observable) - expected).abs() < EPS);
+        assert!((dm.expectation(&observable) - expected).abs() < EPS);
+        assert!(sv.expectation(&Observable::z(0)).abs() < EPS);
+    }
+}
@@ -0,0 +1,45 @@
+//! A small, seedable pseudo-random number generator, so that simulations are
+//! reproducible without depending on an external crate.
+
+/// The xoshiro256** generator, seeded through splitmix64.
+#[derive(Clone, Debug)]
+pub(crate) struct Rng {
+    state: [u64; 4],
+}
+
+impl Rng {
+    /// Creates a new generator from the given seed.
+    pub(crate) fn new(mut seed: u64) -> Self {
+        let mut splitmix = || {
+            seed = seed.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            z ^ (z >> 31)
+        };
+
+        Self { state: [(); 4].map(|_| splitmix()) }
+    }
+
+    /// Returns the next random 64 bits integer.
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        let [s0, s1, s2, s3] = &mut self.state;
+        let res = s1.wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = *s1 << 17;
+
+        *s2 ^= *s0;
+        *s3 ^= *s1;
+        *s1 ^= *s2;
+        *s0 ^= *s3;
+        *s2 ^= t;
+        *s3 = s3.rotate_left(45);
+
+        res
+    }
+
+    /// Returns a random float uniformly distributed in `[0, 1)`.
+    #[inline]
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+}
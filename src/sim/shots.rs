@@ -0,0 +1,172 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::bitset::BitSet;
+use crate::circuit::QuantumCircuit;
+
+use super::{Backend, Rng, SimulationError, StateVector, Tableau};
+
+/// The results of running a circuit several times: a histogram of the final
+/// classical registers, along with the register of each shot, in order.
+///
+/// See the [`bitset`](crate::bitset) module for the endianness of the conversions
+/// of registers to strings and integers.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct Counts {
+    histogram: HashMap<BitSet, usize>,
+    memory: Vec<BitSet>,
+}
+
+impl Counts {
+    /// Returns an empty histogram.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the register of a new shot.
+    #[inline]
+    pub fn insert(&mut self, register: BitSet) {
+        *self.histogram.entry(register.clone()).or_default() += 1;
+        self.memory.push(register);
+    }
+
+    /// Returns the number of shots.
+    #[inline]
+    pub fn shots(&self) -> usize {
+        self.memory.len()
+    }
+
+    /// Returns the number of shots whose final register was `register`.
+    #[inline]
+    pub fn get(&self, register: &BitSet) -> usize {
+        self.histogram.get(register).copied().unwrap_or(0)
+    }
+
+    /// Returns the frequency at which `register` was observed.
+    #[inline]
+    pub fn frequency(&self, register: &BitSet) -> f64 {
+        self.get(register) as f64 / self.shots() as f64
+    }
+
+    /// Returns an iterator over the distinct registers observed and their counts,
+    /// in no particular order.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&BitSet, usize)> + '_ {
+        self.histogram.iter().map(|(register, &count)| (register, count))
+    }
+
+    /// Returns the final register of each shot, in order.
+    #[inline]
+    pub fn memory(&self) -> &[BitSet] {
+        &self.memory
+    }
+
+    /// Returns the register that was observed the most, if any shot was run.
+    #[inline]
+    pub fn most_frequent(&self) -> Option<&BitSet> {
+        self.histogram.iter()
+            .max_by(|(r1, c1), (r2, c2)| c1.cmp(c2).then_with(|| r2.to_bitstring().cmp(&r1.to_bitstring())))
+            .map(|(register, _)| register)
+    }
+
+    /// Returns the histogram keyed by bitstrings, the bit at index 0 being the rightmost character.
+    #[inline]
+    pub fn to_bitstrings(&self) -> BTreeMap<String, usize> {
+        self.iter().map(|(register, count)| (register.to_bitstring(), count)).collect()
+    }
+
+    /// Returns the histogram keyed by integers, the bit at index $i$ having weight $2^i$.
+    /// Returns `None` if a register does not fit in 64 bits.
+    #[inline]
+    pub fn to_integers(&self) -> Option<BTreeMap<u64, usize>> {
+        self.iter().map(|(register, count)| register.to_integer().map(|n| (n, count))).collect()
+    }
+}
+
+/// Runs the circuit `shots` times and returns the histogram of the final classical
+/// registers. Clifford circuits are run on a [`Tableau`], other circuits on a [`StateVector`].
+/// The results only depend on the circuit, the number of shots and the seed.
+pub fn run(circ: &QuantumCircuit, shots: usize, seed: u64) -> Result<Counts, SimulationError> {
+    if circ.is_clifford() {
+        run_with(circ, shots, seed, |seed| Ok(Tableau::new(circ.qubit_count(), circ.bit_count(), seed)))
+    } else {
+        run_with(circ, shots, seed, |seed| StateVector::new(circ.qubit_count(), circ.bit_count(), seed))
+    }
+}
+
+/// Runs the circuit `shots` times and returns the histogram of the final classical
+/// registers. Each shot is run on a fresh backend returned by `backend`, which is
+/// given the seed of the shot.
+pub fn run_with<B, F>(circ: &QuantumCircuit, shots: usize, seed: u64, mut backend: F) -> Result<Counts, SimulationError>
+where
+    B: Backend,
+    F: FnMut(u64) -> Result<B, SimulationError>,
+{
+    let mut rng = Rng::new(seed);
+    let mut counts = Counts::new();
+
+    for _ in 0..shots {
+        let mut sim = backend(rng.next_u64())?;
+        sim.run(circ)?;
+        counts.insert(sim.register().clone());
+    }
+
+    Ok(counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sim::DensityMatrix;
+    use crate::sim::noise::NoiseModel;
+
+    use super::*;
+
+    fn bell() -> QuantumCircuit {
+        QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+            let [b1, b2, b3] = circ.alloc_n()?;
+            circ.h(q1).cx(q1, q2).measure(&[q1, q2], &[b1, b2]);
+            circ.x(q1).measure(&[q1], &[b3]);
+            Ok(())
+        }).unwrap()
+    }
+
+    #[test]
+    fn bell_counts() {
+        let counts = run(&bell(), 200, 42).unwrap();
+
+        assert_eq!(counts.shots(), 200);
+        assert_eq!(counts.memory().len(), 200);
+
+        // b3 is always the opposite of b1.
+        let bitstrings = counts.to_bitstrings();
+        assert_eq!(bitstrings.keys().collect::<Vec<_>>(), ["011", "100"]);
+        assert_eq!(bitstrings.values().sum::<usize>(), 200);
+
+        let integers = counts.to_integers().unwrap();
+        assert_eq!(integers.keys().collect::<Vec<_>>(), [&3, &4]);
+        assert!(counts.frequency(&BitSet::from_bitstring("011").unwrap()) > 0.3);
+    }
+
+    #[test]
+    fn reproducible() {
+        let circ = bell();
+        let a = run(&circ, 50, 7).unwrap();
+        let b = run_with(&circ, 50, 7, |seed| DensityMatrix::new(2, 3, seed).map(|sim| sim.with_noise(NoiseModel::new()))).unwrap();
+
+        assert_eq!(a, run(&circ, 50, 7).unwrap());
+        assert_eq!(b.shots(), 50);
+        assert_eq!(b.to_bitstrings().keys().collect::<Vec<_>>(), ["011", "100"]);
+    }
+
+    #[test]
+    fn bitset_conversions() {
+        let register = BitSet::from_bitstring("0110").unwrap();
+
+        assert_eq!(register.get(0), Some(false));
+        assert_eq!(register.get(1), Some(true));
+        assert_eq!(register.to_integer(), Some(6));
+        assert_eq!(BitSet::from_integer(4, 6), register);
+        assert_eq!(register.to_string(), "0110");
+    }
+}
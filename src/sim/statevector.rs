@@ -0,0 +1,254 @@
+use crate::bitset::BitSet;
+use crate::circuit::QuantumCircuit;
+use crate::circuit::operation::OpKind;
+use crate::complex::Complex;
+use crate::matrix::Matrix;
+
+use super::{Backend, Rng, SimulationError};
+
+/// A simulator storing the full state vector of the qubits, that is to say
+/// $2^n$ complex amplitudes for $n$ qubits. The $k$th qubit is the $k$th least
+/// significant bit of the indices of the amplitudes.
+#[derive(Clone, Debug)]
+pub struct StateVector {
+    amplitudes: Vec<Complex>,
+    register: BitSet,
+    rng: Rng,
+}
+
+impl StateVector {
+    /// The maximum number of qubits this simulator accepts.
+    pub const MAX_QUBITS: usize = 30;
+
+    /// Creates a new simulator with all qubits in the $|0\rangle$ state and all
+    /// bits set to `false`, with the given seed for measurements.
+    pub fn new(qubit_count: usize, bit_count: usize, seed: u64) -> Result<Self, SimulationError> {
+        if qubit_count > Self::MAX_QUBITS {
+            return Err(SimulationError::TooManyQubits(qubit_count));
+        }
+
+        let mut amplitudes = vec![Complex::ZERO; 1 << qubit_count];
+        amplitudes[0] = Complex::ONE;
+
+        Ok(Self {
+            amplitudes,
+            register: BitSet::new(bit_count),
+            rng: Rng::new(seed),
+        })
+    }
+
+    /// Creates a simulator sized for the circuit and runs the circuit on it.
+    #[inline]
+    pub fn simulate(circ: &QuantumCircuit, seed: u64) -> Result<Self, SimulationError> {
+        let mut res = Self::new(circ.qubit_count(), circ.bit_count(), seed)?;
+        res.run(circ)?;
+        Ok(res)
+    }
+
+    #[inline]
+    pub fn qubit_count(&self) -> usize {
+        self.amplitudes.len().trailing_zeros() as usize
+    }
+
+    #[inline]
+    pub fn amplitudes(&self) -> &[Complex] {
+        &self.amplitudes
+    }
+
+    /// Returns the probability of each basis state.
+    #[inline]
+    pub fn probabilities(&self) -> Vec<f64> {
+        self.amplitudes.iter().map(|z| z.norm_sqr()).collect()
+    }
+
+    /// Returns the probability of measuring the qubit in the $|1\rangle$ state.
+    pub fn probability_one(&self, qubit: usize) -> f64 {
+        self.amplitudes.iter()
+            .enumerate()
+            .filter(|(i, _)| i & (1 << qubit) != 0)
+            .map(|(_, z)| z.norm_sqr())
+            .sum()
+    }
+
+    /// Returns the inner product $\langle \text{self} | \text{rhs} \rangle$.
+    pub fn inner(&self, rhs: &Self) -> Complex {
+        self.amplitudes.iter().zip(&rhs.amplitudes).map(|(x, y)| x.conj() * *y).sum()
+    }
+
+    /// Applies a matrix acting on the given qubits to the state. The first qubit is
+    /// the least significant bit of the matrix indices.
+    #[inline]
+    pub fn apply_matrix(&mut self, matrix: &Matrix, qubits: &[usize]) {
+        super::apply_matrix(&mut self.amplitudes, matrix, qubits);
+    }
+
+    /// Projects the qubit on the given outcome, and renormalizes the state.
+    fn collapse(&mut self, qubit: usize, outcome: bool, probability: f64) {
+        let norm = probability.sqrt();
+
+        for (i, z) in self.amplitudes.iter_mut().enumerate() {
+            if (i & (1 << qubit) != 0) == outcome {
+                *z = *z / norm;
+            } else {
+                *z = Complex::ZERO;
+            }
+        }
+    }
+}
+
+impl Backend for StateVector {
+    #[inline]
+    fn register(&self) -> &BitSet {
+        &self.register
+    }
+
+    #[inline]
+    fn register_mut(&mut self) -> &mut BitSet {
+        &mut self.register
+    }
+
+    fn apply(&mut self, op: &OpKind<'_, '_>, qubits: &[usize], parameters: &[f64]) -> Result<(), SimulationError> {
+        let matrix = op.matrix(parameters).ok_or(SimulationError::UnsupportedOperation(op.label()))?;
+        self.apply_matrix(&matrix, qubits);
+        Ok(())
+    }
+
+    fn measure(&mut self, qubit: usize) -> bool {
+        let one = self.probability_one(qubit);
+        let outcome = self.rng.next_f64() < one;
+        self.collapse(qubit, outcome, if outcome { one } else { 1.0 - one });
+        outcome
+    }
+
+    fn reset(&mut self, qubit: usize) {
+        if self.measure(qubit) {
+            let mask = 1 << qubit;
+
+            for i in (0..self.amplitudes.len()).filter(|i| i & mask == 0) {
+                self.amplitudes.swap(i, i | mask);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_1_SQRT_2;
+
+    use crate::bitset::BitSet;
+    use crate::circuit::instruction::{Compute, Instr, Modifier};
+    use crate::circuit::operation::OpKind;
+
+    use super::*;
+
+    const EPS: f64 = 1e-12;
+
+    #[test]
+    fn bell() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+            circ.h(q1).cx(q1, q2);
+            Ok(())
+        }).unwrap();
+
+        let sim = StateVector::simulate(&circ, 0).unwrap();
+        let expected = [FRAC_1_SQRT_2, 0.0, 0.0, FRAC_1_SQRT_2];
+
+        for (z, x) in sim.amplitudes().iter().zip(expected) {
+            assert!(z.approx_eq(Complex::from(x), EPS));
+        }
+    }
+
+    #[test]
+    fn measure_correlations() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+            let [b1, b2] = circ.alloc_n()?;
+            circ.h(q1).cx(q1, q2).measure(&[q1, q2], &[b1, b2]);
+            Ok(())
+        }).unwrap();
+
+        for seed in 0..16 {
+            let sim = StateVector::simulate(&circ, seed).unwrap();
+            assert_eq!(sim.register().get(0), sim.register().get(1));
+        }
+    }
+
+    #[test]
+    fn classical_control() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+            let [b1, b2] = circ.alloc_n()?;
+
+            // Sets both qubits to one through conditional gates.
+            circ.x(q1).measure(&[q1], &[b1]);
+            circ.push(Instr {
+                op: OpKind::X,
+                qubits: &[q2],
+                modifier: Some(Modifier::IfBit(b1)),
+                ..Default::default()
+            })?;
+
+            // Three X gates in a row, the qubit ends up flipped.
+            circ.push(Instr {
+                op: OpKind::X,
+                qubits: &[q1],
+                modifier: Some(Modifier::ForConst(3)),
+                ..Default::default()
+            })?;
+
+            // Copies the negation of b1 into b2.
+            circ.compute(&[b2], Compute {
+                bits: &[b1],
+                func: |bits| [!bits.get(0).unwrap()].into_iter().collect::<BitSet>(),
+            });
+
+            Ok(())
+        }).unwrap();
+
+        let sim = StateVector::simulate(&circ, 0).unwrap();
+
+        assert!((sim.probability_one(0) - 0.0).abs() < EPS);
+        assert!((sim.probability_one(1) - 1.0).abs() < EPS);
+        assert_eq!(sim.register().get(0), Some(true));
+        assert_eq!(sim.register().get(1), Some(false));
+    }
+
+    #[test]
+    fn quantum_modifiers() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [t1, t2] = circ.alloc_n()?;
+            let controls = circ.alloc_list(2)?;
+
+            // Flips t1 since both controls are one, but not t2 which needs them to be zero.
+            controls.iter().for_each(|q| _ = circ.x(q));
+            circ.push(Instr { op: OpKind::X, qubits: &[t1], modifier: Some(Modifier::Control(controls.clone())), ..Default::default() })?;
+            circ.push(Instr { op: OpKind::X, qubits: &[t2], modifier: Some(Modifier::NegControl(controls)), ..Default::default() })?;
+
+            // Two square roots of X, then the inverse of X, leave t2 unchanged.
+            circ.push(Instr { op: OpKind::X, qubits: &[t2], modifier: Some(Modifier::Pow(0.5.into())), ..Default::default() })?;
+            circ.push(Instr { op: OpKind::SX, qubits: &[t2], ..Default::default() })?;
+            circ.push(Instr { op: OpKind::X, qubits: &[t2], modifier: Some(Modifier::Inverse), ..Default::default() })?;
+            Ok(())
+        }).unwrap();
+
+        let sim = StateVector::simulate(&circ, 0).unwrap();
+
+        assert!((sim.probability_one(0) - 1.0).abs() < 1e-6);
+        assert!(sim.probability_one(1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn reset() {
+        let circ = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            circ.h(q).reset(q);
+            Ok(())
+        }).unwrap();
+
+        for seed in 0..8 {
+            let sim = StateVector::simulate(&circ, seed).unwrap();
+            assert!(sim.probability_one(0) < EPS);
+        }
+    }
+}
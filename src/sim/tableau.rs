@@ -0,0 +1,443 @@
+use std::f64::consts::FRAC_PI_2;
+
+use crate::bitset::BitSet;
+use crate::circuit::QuantumCircuit;
+use crate::circuit::operation::OpKind;
+
+use super::{Backend, Rng, SimulationError};
+
+/// The tolerance under which the angle of a rotation is considered to be a multiple
+/// of $\pi/2$, so that it can be applied.
+const EPS: f64 = 1e-6;
+
+/// A stabilizer simulator for Clifford circuits, following the CHP algorithm of
+/// Aaronson and Gottesman. The state of $n$ qubits is stored as a tableau of $2n$
+/// Pauli operators (the destabilizers, then the stabilizers) in $O(n^2)$ bits, which
+/// makes it possible to simulate thousands of qubits.
+#[derive(Clone, Debug)]
+pub struct Tableau {
+    qubit_count: usize,
+    /// The X part of each row, plus a scratch row at the end.
+    xs: Vec<BitSet>,
+    /// The Z part of each row, plus a scratch row at the end.
+    zs: Vec<BitSet>,
+    /// The sign of each row, `true` meaning $-1$.
+    signs: BitSet,
+    register: BitSet,
+    rng: Rng,
+}
+
+/// Returns the exponent to which $i$ is raised when multiplying the Pauli operators
+/// given by $(x_1, z_1)$ and $(x_2, z_2)$, in this order. Each bit of the arguments
+/// stands for a different qubit, and the exponents of all qubits are summed.
+#[inline]
+fn phase_exponent(x1: u8, z1: u8, x2: u8, z2: u8) -> i32 {
+    let plus = (x1 & z1 & z2 & !x2) | (x1 & !z1 & z2 & x2) | (!x1 & z1 & x2 & !z2);
+    let minus = (x1 & z1 & x2 & !z2) | (x1 & !z1 & z2 & !x2) | (!x1 & z1 & x2 & z2);
+    plus.count_ones() as i32 - minus.count_ones() as i32
+}
+
+/// Xors row `i` into row `h`.
+#[inline]
+fn xor_rows(rows: &mut [BitSet], h: usize, i: usize) {
+    let (src, dest) = if i < h {
+        let (left, right) = rows.split_at_mut(h);
+        (&left[i], &mut right[0])
+    } else {
+        let (left, right) = rows.split_at_mut(i);
+        (&right[0], &mut left[h])
+    };
+
+    *dest ^= src;
+}
+
+impl Tableau {
+    /// Creates a new simulator with all qubits in the $|0\rangle$ state and all
+    /// bits set to `false`, with the given seed for measurements.
+    pub fn new(qubit_count: usize, bit_count: usize, seed: u64) -> Self {
+        let rows = 2 * qubit_count + 1;
+        let mut xs = vec![BitSet::new(qubit_count); rows];
+        let mut zs = vec![BitSet::new(qubit_count); rows];
+
+        for i in 0..qubit_count {
+            xs[i].set(i, true);
+            zs[i + qubit_count].set(i, true);
+        }
+
+        Self {
+            qubit_count,
+            xs,
+            zs,
+            signs: BitSet::new(rows),
+            register: BitSet::new(bit_count),
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// Creates a simulator sized for the circuit and runs the circuit on it.
+    /// Fails if the circuit is not a Clifford circuit.
+    pub fn simulate(circ: &QuantumCircuit, seed: u64) -> Result<Self, SimulationError> {
+        let mut iter = circ.instructions();
+
+        while let Some(instr) = iter.next() {
+            if !instr.is_clifford() {
+                return Err(SimulationError::UnsupportedOperation(instr.op.label()));
+            }
+        }
+
+        let mut res = Self::new(circ.qubit_count(), circ.bit_count(), seed);
+        res.run(circ)?;
+        Ok(res)
+    }
+
+    #[inline]
+    pub fn qubit_count(&self) -> usize {
+        self.qubit_count
+    }
+
+    /// Returns the `i`th stabilizer generator of the state, as a string such as `"+XZI"`,
+    /// where the first character after the sign is the first qubit.
+    pub fn stabilizer(&self, i: usize) -> Option<String> {
+        (i < self.qubit_count).then(|| self.row_string(i + self.qubit_count))
+    }
+
+    /// Returns a row of the tableau as a string.
+    fn row_string(&self, row: usize) -> String {
+        let sign = if self.sign(row) { '-' } else { '+' };
+
+        std::iter::once(sign)
+            .chain(self.xs[row].iter().zip(self.zs[row].iter()).map(|pauli| match pauli {
+                (false, false) => 'I',
+                (true, false) => 'X',
+                (true, true) => 'Y',
+                (false, true) => 'Z',
+            }))
+            .collect()
+    }
+
+    #[inline]
+    fn x(&self, row: usize, qubit: usize) -> bool {
+        self.xs[row].get(qubit).unwrap()
+    }
+
+    #[inline]
+    fn z(&self, row: usize, qubit: usize) -> bool {
+        self.zs[row].get(qubit).unwrap()
+    }
+
+    #[inline]
+    fn sign(&self, row: usize) -> bool {
+        self.signs.get(row).unwrap()
+    }
+
+    /// Applies the Hadamard gate.
+    pub fn h(&mut self, a: usize) {
+        for row in 0..self.xs.len() {
+            let (x, z) = (self.x(row, a), self.z(row, a));
+            self.signs.set(row, self.sign(row) ^ (x & z));
+            self.xs[row].set(a, z);
+            self.zs[row].set(a, x);
+        }
+    }
+
+    /// Applies the phase gate S.
+    pub fn s(&mut self, a: usize) {
+        for row in 0..self.xs.len() {
+            let (x, z) = (self.x(row, a), self.z(row, a));
+            self.signs.set(row, self.sign(row) ^ (x & z));
+            self.zs[row].set(a, z ^ x);
+        }
+    }
+
+    /// Applies the controlled X gate.
+    pub fn cx(&mut self, a: usize, b: usize) {
+        for row in 0..self.xs.len() {
+            let (xa, za) = (self.x(row, a), self.z(row, a));
+            let (xb, zb) = (self.x(row, b), self.z(row, b));
+            self.signs.set(row, self.sign(row) ^ (xa & zb & !(xb ^ za)));
+            self.xs[row].set(b, xb ^ xa);
+            self.zs[row].set(a, za ^ zb);
+        }
+    }
+
+    /// Left-multiplies row `h` by row `i`.
+    fn rowsum(&mut self, h: usize, i: usize) {
+        let words = self.xs[i].as_bytes().iter()
+            .zip(self.zs[i].as_bytes())
+            .zip(self.xs[h].as_bytes().iter().zip(self.zs[h].as_bytes()));
+
+        let exponent = words
+            .map(|((&x1, &z1), (&x2, &z2))| phase_exponent(x1, z1, x2, z2))
+            .sum::<i32>()
+            + 2 * self.sign(h) as i32
+            + 2 * self.sign(i) as i32;
+
+        self.signs.set(h, exponent.rem_euclid(4) == 2);
+
+        xor_rows(&mut self.xs, h, i);
+        xor_rows(&mut self.zs, h, i);
+    }
+}
+
+impl Backend for Tableau {
+    #[inline]
+    fn register(&self) -> &BitSet {
+        &self.register
+    }
+
+    #[inline]
+    fn register_mut(&mut self) -> &mut BitSet {
+        &mut self.register
+    }
+
+    fn apply(&mut self, op: &OpKind<'_, '_>, qubits: &[usize], parameters: &[f64]) -> Result<(), SimulationError> {
+        // Rotations by multiples of pi/2 are powers of S, up to a global phase.
+        let quarters = || {
+            let x = parameters[0] / FRAC_PI_2;
+            let rounded = x.round();
+
+            if (x - rounded).abs() > EPS {
+                return Err(SimulationError::UnsupportedOperation(op.label()));
+            }
+
+            Ok(rounded.rem_euclid(4.0) as usize)
+        };
+
+        // Every clifford gate is decomposed into H, S and CX, up to a global phase.
+        match (op, qubits) {
+            (OpKind::H, &[a]) => self.h(a),
+            (OpKind::X, &[a]) => {
+                self.h(a);
+                self.s(a);
+                self.s(a);
+                self.h(a);
+            }
+            (OpKind::Y, &[a]) => {
+                self.s(a);
+                self.s(a);
+                self.h(a);
+                self.s(a);
+                self.s(a);
+                self.h(a);
+            }
+            (OpKind::Z, &[a]) => {
+                self.s(a);
+                self.s(a);
+            }
+            (OpKind::S, &[a]) => self.s(a),
+            (OpKind::Sdg, &[a]) => {
+                self.s(a);
+                self.s(a);
+                self.s(a);
+            }
+            (OpKind::SX, &[a]) => {
+                self.h(a);
+                self.s(a);
+                self.h(a);
+            }
+            (OpKind::CX, &[a, b]) => self.cx(a, b),
+            (OpKind::CY, &[a, b]) => {
+                self.apply(&OpKind::Sdg, &[b], &[])?;
+                self.cx(a, b);
+                self.s(b);
+            }
+            (OpKind::CZ, &[a, b]) => {
+                self.h(b);
+                self.cx(a, b);
+                self.h(b);
+            }
+            (OpKind::Swap, &[a, b]) => {
+                self.cx(a, b);
+                self.cx(b, a);
+                self.cx(a, b);
+            }
+            (OpKind::ISwap, &[a, b]) => {
+                self.s(a);
+                self.s(b);
+                self.apply(&OpKind::CZ, &[a, b], &[])?;
+                self.apply(&OpKind::Swap, &[a, b], &[])?;
+            }
+            (OpKind::RZ | OpKind::Phase, &[a]) => (0..quarters()?).for_each(|_| self.s(a)),
+            (OpKind::RX, &[a]) => {
+                let k = quarters()?;
+                self.h(a);
+                (0..k).for_each(|_| self.s(a));
+                self.h(a);
+            }
+            (OpKind::RY, &[a]) => {
+                self.apply(&OpKind::Sdg, &[a], &[])?;
+                self.apply(&OpKind::RX, &[a], parameters)?;
+                self.s(a);
+            }
+            (OpKind::RZZ, &[a, b]) => {
+                self.cx(a, b);
+                self.apply(&OpKind::RZ, &[b], parameters)?;
+                self.cx(a, b);
+            }
+            (OpKind::RXX, &[a, b]) => {
+                self.h(a);
+                self.h(b);
+                self.apply(&OpKind::RZZ, &[a, b], parameters)?;
+                self.h(a);
+                self.h(b);
+            }
+            _ => return Err(SimulationError::UnsupportedOperation(op.label())),
+        }
+
+        Ok(())
+    }
+
+    fn measure(&mut self, qubit: usize) -> bool {
+        let n = self.qubit_count;
+
+        match (n..2 * n).find(|&p| self.x(p, qubit)) {
+            // The outcome is random: some stabilizer anticommutes with Z.
+            Some(p) => {
+                for i in 0..2 * n {
+                    if i != p && self.x(i, qubit) {
+                        self.rowsum(i, p);
+                    }
+                }
+
+                self.xs[p - n] = self.xs[p].clone();
+                self.zs[p - n] = self.zs[p].clone();
+                self.signs.set(p - n, self.sign(p));
+
+                let outcome = self.rng.next_u64() & 1 == 1;
+
+                self.xs[p].clear();
+                self.zs[p].clear();
+                self.zs[p].set(qubit, true);
+                self.signs.set(p, outcome);
+
+                outcome
+            }
+            // The outcome is determined, compute it in the scratch row.
+            None => {
+                let scratch = 2 * n;
+
+                self.xs[scratch].clear();
+                self.zs[scratch].clear();
+                self.signs.set(scratch, false);
+
+                for i in 0..n {
+                    if self.x(i, qubit) {
+                        self.rowsum(scratch, i + n);
+                    }
+                }
+
+                self.sign(scratch)
+            }
+        }
+    }
+
+    fn reset(&mut self, qubit: usize) {
+        if self.measure(qubit) {
+            self.apply(&OpKind::X, &[qubit], &[]).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::parameter::Parameter;
+
+    use super::*;
+
+    #[test]
+    fn bell() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [q1, q2] = circ.alloc_n()?;
+            let [b1, b2] = circ.alloc_n()?;
+            circ.h(q1).cx(q1, q2);
+            circ.measure(&[q1, q2], &[b1, b2]);
+            Ok(())
+        }).unwrap();
+
+        let mut outcomes = [0; 2];
+
+        for seed in 0..32 {
+            let sim = Tableau::simulate(&circ, seed).unwrap();
+            let (b1, b2) = (sim.register().get(0).unwrap(), sim.register().get(1).unwrap());
+            assert_eq!(b1, b2);
+            outcomes[b1 as usize] += 1;
+        }
+
+        assert!(outcomes[0] > 0 && outcomes[1] > 0);
+    }
+
+    #[test]
+    fn stabilizers() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [q1, q2, q3] = circ.alloc_n()?;
+            circ.h(q1).cx(q1, q2).x(q3).y(q3).s(q2).sdg(q2).cz(q1, q3);
+            Ok(())
+        }).unwrap();
+
+        let sim = Tableau::simulate(&circ, 0).unwrap();
+        let mut stabilizers: Vec<_> = (0..3).map(|i| sim.stabilizer(i).unwrap()).collect();
+        stabilizers.sort();
+
+        // After X then Y, the third qubit is back in |0>, the CZ only changes the generators.
+        assert_eq!(stabilizers, ["+IIZ", "+XXZ", "+ZZI"]);
+    }
+
+    #[test]
+    fn deterministic() {
+        let circ = QuantumCircuit::new(|circ| {
+            let qubits = circ.alloc_list(1000)?;
+            let bits = circ.alloc_list(1000)?;
+            let qubits: Vec<_> = qubits.iter().collect();
+            let bits: Vec<_> = bits.iter().collect();
+
+            circ.x(qubits[0]);
+
+            for pair in qubits.windows(2) {
+                circ.cx(pair[0], pair[1]);
+            }
+
+            circ.iswap(qubits[998], qubits[999]).measure(&qubits, &bits);
+            Ok(())
+        }).unwrap();
+
+        let sim = Tableau::simulate(&circ, 0).unwrap();
+        assert_eq!(sim.register().count_ones(), 1000);
+    }
+
+    #[test]
+    fn not_clifford() {
+        let circ = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            circ.t(q);
+            Ok(())
+        }).unwrap();
+
+        assert!(!circ.is_clifford());
+        assert_eq!(Tableau::simulate(&circ, 0).unwrap_err(), SimulationError::UnsupportedOperation("t"));
+    }
+
+    #[test]
+    fn rotations() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [q1, q2, q3, q4] = circ.alloc_n()?;
+            circ.rx(Parameter::pi(1, 2), q1).ry(Parameter::pi(1, 2), q2).h(q3).rz(Parameter::pi(5, 2), q3);
+            circ.rxx(Parameter::pi(1, 1), q4, q1).rzz(Parameter::pi(-1, 1), q1, q4).rx(Parameter::pi(-1, 1), q4);
+            Ok(())
+        }).unwrap();
+
+        let sim = Tableau::simulate(&circ, 0).unwrap();
+        let mut stabilizers: Vec<_> = (0..4).map(|i| sim.stabilizer(i).unwrap()).collect();
+        stabilizers.sort();
+
+        // RXX(pi) and RZZ(-pi) are XX and ZZ up to a phase, which cancel on the first qubit.
+        assert_eq!(stabilizers, ["+IIIZ", "+IIYI", "+IXII", "-YIII"]);
+
+        let circ = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            circ.rz(std::f32::consts::FRAC_PI_2, q);
+            Ok(())
+        }).unwrap();
+
+        assert!(!circ.is_clifford());
+    }
+}
@@ -0,0 +1,209 @@
+use crate::circuit::QuantumCircuit;
+use crate::circuit::decompose::{Quantum, expand};
+use crate::circuit::instruction::Modifier;
+use crate::circuit::operation::OpKind;
+use crate::circuit::symbol::Symbol;
+use crate::matrix::Matrix;
+
+use super::{SimulationError, apply_matrix, resolve};
+
+/// The maximum number of qubits of a circuit whose unitary can be computed.
+pub const MAX_UNITARY_QUBITS: usize = 12;
+
+/// The tolerance under which unitaries are considered equal. Since parameters are
+/// stored as `f32`, it is well above the precision of `f64`.
+const EPS: f64 = 1e-6;
+
+impl QuantumCircuit {
+    /// Returns the unitary matrix of the circuit. The $k$th qubit is the $k$th least
+    /// significant bit of the matrix indices.
+    ///
+    /// Quantum modifiers are applied exactly, except non-integer powers of operations other
+    /// than rotations, which are only exact up to a global phase.
+    ///
+    /// Fails if the circuit has more than [`MAX_UNITARY_QUBITS`] qubits, or if it contains
+    /// non-unitary operations, classical control or unbound formal parameters.
+    pub fn unitary(&self) -> Result<Matrix, SimulationError> {
+        let n = self.qubit_count();
+
+        if n > MAX_UNITARY_QUBITS {
+            return Err(SimulationError::TooManyQubits(n));
+        }
+
+        // The rows of the matrix are the high bits of the indices of it's entries,
+        // so applying an operation on the left acts on qubits shifted by n.
+        let dim = 1 << n;
+        let mut entries = Matrix::identity(dim).as_slice().to_vec();
+        let mut iter = self.instructions();
+
+        while let Some(instr) = iter.next() {
+            let (repeat, quantum) = match &instr.modifier {
+                None => (1, None),
+                Some(Modifier::ForConst(n)) => (*n, None),
+                Some(Modifier::IfBit(_) | Modifier::IfCompute(_)) => return Err(SimulationError::UnsupportedModifier("if")),
+                Some(Modifier::WhileBit(_) | Modifier::WhileCompute(_)) => return Err(SimulationError::UnsupportedModifier("while")),
+                Some(Modifier::ForCompute(_)) => return Err(SimulationError::UnsupportedModifier("for")),
+                Some(modifier) => (1, Quantum::new(modifier, |parameter| resolve(self, parameter, &[]))?),
+            };
+
+            if matches!(instr.op, OpKind::Nop | OpKind::Barrier) {
+                continue;
+            }
+
+            let parameters = instr.parameters.iter()
+                .map(|&parameter| resolve(self, parameter, &[]))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let matrix = instr.op.matrix(&parameters)
+                .ok_or(SimulationError::UnsupportedOperation(instr.op.label()))?;
+
+            let mut qubits: Vec<_> = instr.qubits.iter().map(|q| q.id() as usize).collect();
+            let mut apply = |matrix: &Matrix, qubits: &[usize]| {
+                let shifted: Vec<_> = qubits.iter().map(|q| q + n).collect();
+                apply_matrix(&mut entries, matrix, &shifted);
+            };
+
+            match quantum {
+                None => (0..repeat).for_each(|_| apply(&matrix, &qubits)),
+                Some(Quantum::Control(controls)) => {
+                    // The controls are the least significant qubits of the controlled matrix.
+                    let matrix = matrix.controlled(controls.len());
+                    qubits.splice(0..0, controls);
+                    apply(&matrix, &qubits);
+                }
+                Some(Quantum::NegControl(controls)) => {
+                    let x = OpKind::X.matrix(&[]).unwrap();
+                    let matrix = matrix.controlled(controls.len());
+
+                    controls.iter().for_each(|&c| apply(&x, &[c]));
+                    apply(&matrix, &[controls.as_slice(), &qubits].concat());
+                    controls.iter().for_each(|&c| apply(&x, &[c]));
+                }
+                Some(Quantum::Inverse) => apply(&matrix.adjoint(), &qubits),
+                Some(Quantum::Pow(x)) if x == x.round() => {
+                    let matrix = if x < 0.0 { matrix.adjoint() } else { matrix };
+                    (0..x.abs() as usize).for_each(|_| apply(&matrix, &qubits));
+                }
+                Some(quantum) => {
+                    for gate in expand(&instr.op, &qubits, &parameters, &quantum) {
+                        apply(&gate.op.matrix(&gate.parameters).unwrap(), &gate.qubits);
+                    }
+                }
+            }
+        }
+
+        Ok(Matrix::from_vec(dim, entries).unwrap())
+    }
+}
+
+/// Returns true if two circuits have the same unitary, optionally up to a global phase.
+/// Circuits with different numbers of qubits are never equivalent.
+///
+/// Fails if the unitary of one of the circuits cannot be computed, see [`QuantumCircuit::unitary`].
+pub fn equivalent(a: &QuantumCircuit, b: &QuantumCircuit, up_to_global_phase: bool) -> Result<bool, SimulationError> {
+    if a.qubit_count() != b.qubit_count() {
+        return Ok(false);
+    }
+
+    let (ua, ub) = (a.unitary()?, b.unitary()?);
+
+    if up_to_global_phase {
+        Ok(ua.approx_eq_up_to_phase(&ub, EPS))
+    } else {
+        Ok(ua.approx_eq(&ub, EPS))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::instruction::Instr;
+    use crate::sim::{Backend, StateVector};
+
+    use super::*;
+
+    #[test]
+    fn unitary() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [a, b] = circ.alloc_n()?;
+            circ.cx(a, b);
+            Ok(())
+        }).unwrap();
+
+        assert!(circ.unitary().unwrap().approx_eq(&OpKind::CX.matrix(&[]).unwrap(), EPS));
+
+        let circ = QuantumCircuit::new(|circ| {
+            let [a, b, c] = circ.alloc_n()?;
+            circ.h(a).x(b).cx(c, b).rz(0.4, b).swap(a, c).ccx(b, a, c).barrier(&[a, b]).sx(c);
+            Ok(())
+        }).unwrap();
+
+        let unitary = circ.unitary().unwrap();
+        assert!(unitary.is_unitary(EPS));
+
+        // Each column is the state obtained by running the circuit on a basis state.
+        for col in 0..8 {
+            let mut sim = StateVector::new(3, 0, 0).unwrap();
+
+            for q in (0..3).filter(|q| col >> q & 1 == 1) {
+                sim.apply(&OpKind::X, &[q], &[]).unwrap();
+            }
+
+            sim.run(&circ).unwrap();
+
+            for (row, &z) in sim.amplitudes().iter().enumerate() {
+                assert!(unitary[(row, col)].approx_eq(z, EPS));
+            }
+        }
+    }
+
+    #[test]
+    fn equivalence() {
+        let x = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            circ.x(q);
+            Ok(())
+        }).unwrap();
+
+        let hzh = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            circ.h(q).z(q).h(q);
+            Ok(())
+        }).unwrap();
+
+        let rx = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            circ.rx(std::f32::consts::PI, q);
+            Ok(())
+        }).unwrap();
+
+        let s2 = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            circ.push(Instr { op: OpKind::S, qubits: &[q], modifier: Some(Modifier::ForConst(2)), ..Default::default() })?;
+            Ok(())
+        }).unwrap();
+
+        let z = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            circ.z(q);
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(equivalent(&x, &hzh, false), Ok(true));
+        assert_eq!(equivalent(&x, &rx, false), Ok(false));
+        assert_eq!(equivalent(&x, &rx, true), Ok(true));
+        assert_eq!(equivalent(&s2, &z, false), Ok(true));
+        assert_eq!(equivalent(&x, &z, true), Ok(false));
+        assert_eq!(equivalent(&x, &QuantumCircuit::default(), true), Ok(false));
+    }
+
+    #[test]
+    fn errors() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [q, _, _, _, _, _, _, _, _, _, _, _, _] = circ.alloc_n()?;
+            circ.h(q);
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(circ.unitary(), Err(SimulationError::TooManyQubits(13)));
+    }
+}
@@ -0,0 +1,257 @@
+use std::f64::consts::PI;
+
+use crate::circuit::QuantumCircuit;
+use crate::circuit::decompose::{Quantum, expand};
+use crate::circuit::instruction::Modifier;
+use crate::circuit::operation::OpKind;
+use crate::circuit::symbol::Symbol;
+
+use super::{Diagram, EdgeKind, Phase, VertexKind, ZxError};
+
+/// The gates ZX diagrams are built from, up to a global phase.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) enum Gate {
+    /// A Z rotation, that is a Z spider of degree 2.
+    Z(usize, Phase),
+    /// An X rotation, that is an X spider of degree 2.
+    X(usize, Phase),
+    H(usize),
+    CX(usize, usize),
+    CZ(usize, usize),
+    Swap(usize, usize),
+}
+
+/// Decomposes a unitary operation into gates, up to a global phase. The parameters are
+/// given as multiples of $\pi$.
+pub(crate) fn decompose(op: &OpKind<'_, '_>, q: &[usize], p: &[f64], out: &mut Vec<Gate>) -> Result<(), ZxError> {
+    use Gate::*;
+
+    let phase = Phase::new;
+    let quarter = Phase::QUARTER_PI;
+
+    match op {
+        OpKind::Nop | OpKind::Barrier => (),
+        OpKind::H => out.push(H(q[0])),
+        OpKind::X => out.push(X(q[0], Phase::PI)),
+        OpKind::Y => out.extend([Z(q[0], Phase::PI), X(q[0], Phase::PI)]),
+        OpKind::Z => out.push(Z(q[0], Phase::PI)),
+        OpKind::S => out.push(Z(q[0], Phase::HALF_PI)),
+        OpKind::Sdg => out.push(Z(q[0], -Phase::HALF_PI)),
+        OpKind::T => out.push(Z(q[0], quarter)),
+        OpKind::Tdg => out.push(Z(q[0], -quarter)),
+        OpKind::SX => out.push(X(q[0], Phase::HALF_PI)),
+        OpKind::RX => out.push(X(q[0], phase(p[0]))),
+        OpKind::RY => out.extend([Z(q[0], -Phase::HALF_PI), X(q[0], phase(p[0])), Z(q[0], Phase::HALF_PI)]),
+        OpKind::RZ | OpKind::Phase => out.push(Z(q[0], phase(p[0]))),
+        OpKind::U3 => {
+            out.push(Z(q[0], phase(p[2])));
+            decompose(&OpKind::RY, q, &p[..1], out)?;
+            out.push(Z(q[0], phase(p[1])));
+        }
+        OpKind::CX => out.push(CX(q[0], q[1])),
+        OpKind::CY => out.extend([Z(q[1], -Phase::HALF_PI), CX(q[0], q[1]), Z(q[1], Phase::HALF_PI)]),
+        OpKind::CZ => out.push(CZ(q[0], q[1])),
+        OpKind::CH => out.extend([
+            H(q[1]), Z(q[1], -Phase::HALF_PI), CX(q[0], q[1]), H(q[1]), Z(q[1], quarter), CX(q[0], q[1]),
+            Z(q[1], quarter), H(q[1]), Z(q[1], Phase::HALF_PI), X(q[1], Phase::PI), Z(q[0], Phase::HALF_PI),
+        ]),
+        OpKind::Swap => out.push(Swap(q[0], q[1])),
+        OpKind::ISwap => out.extend([Z(q[0], Phase::HALF_PI), Z(q[1], Phase::HALF_PI), CZ(q[0], q[1]), Swap(q[0], q[1])]),
+        OpKind::CRZ => out.extend([Z(q[1], phase(p[0] / 2.0)), CX(q[0], q[1]), Z(q[1], phase(-p[0] / 2.0)), CX(q[0], q[1])]),
+        OpKind::CP => out.extend([
+            Z(q[0], phase(p[0] / 2.0)), CX(q[0], q[1]), Z(q[1], phase(-p[0] / 2.0)), CX(q[0], q[1]), Z(q[1], phase(p[0] / 2.0)),
+        ]),
+        OpKind::RXX => out.extend([H(q[0]), H(q[1]), CX(q[0], q[1]), Z(q[1], phase(p[0])), CX(q[0], q[1]), H(q[0]), H(q[1])]),
+        OpKind::RZZ => out.extend([CX(q[0], q[1]), Z(q[1], phase(p[0])), CX(q[0], q[1])]),
+        OpKind::CCX => {
+            let (a, b, c) = (q[0], q[1], q[2]);
+            out.extend([
+                H(c), CX(b, c), Z(c, -quarter), CX(a, c), Z(c, quarter), CX(b, c), Z(c, -quarter), CX(a, c),
+                Z(b, quarter), Z(c, quarter), H(c), CX(a, b), Z(a, quarter), Z(b, -quarter), CX(a, b),
+            ]);
+        }
+        OpKind::CSwap => {
+            out.push(CX(q[2], q[1]));
+            decompose(&OpKind::CCX, q, &[], out)?;
+            out.push(CX(q[2], q[1]));
+        }
+        op => return Err(ZxError::NonUnitary(op.label())),
+    }
+
+    Ok(())
+}
+
+/// The open ends of the wires of a diagram being built.
+struct Wires {
+    last: Vec<usize>,
+    hadamard: Vec<bool>,
+}
+
+impl Wires {
+    /// Connects a vertex at the end of a wire, with a Hadamard edge if one is pending.
+    fn connect(&mut self, diagram: &mut Diagram, q: usize, v: usize) {
+        let edge = if self.hadamard[q] { EdgeKind::Hadamard } else { EdgeKind::Simple };
+        diagram.add_edge(self.last[q], v, edge);
+        self.hadamard[q] = false;
+        self.last[q] = v;
+    }
+
+    /// Adds a spider at the end of a wire.
+    fn spider(&mut self, diagram: &mut Diagram, q: usize, kind: VertexKind, phase: Phase) -> usize {
+        let v = diagram.add_vertex(kind, phase);
+        self.connect(diagram, q, v);
+        v
+    }
+}
+
+impl Diagram {
+    /// Returns the diagram of a sequence of gates on `qubits` qubits.
+    pub(crate) fn from_gates(qubits: usize, gates: &[Gate]) -> Self {
+        let mut diagram = Self::new();
+        let mut wires = Wires {
+            last: (0..qubits).map(|_| diagram.add_input()).collect(),
+            hadamard: vec![false; qubits],
+        };
+
+        for &gate in gates {
+            match gate {
+                Gate::Z(q, phase) => _ = wires.spider(&mut diagram, q, VertexKind::Z, phase),
+                Gate::X(q, phase) => _ = wires.spider(&mut diagram, q, VertexKind::X, phase),
+                Gate::H(q) => wires.hadamard[q] ^= true,
+                Gate::CX(c, t) => {
+                    let z = wires.spider(&mut diagram, c, VertexKind::Z, Phase::ZERO);
+                    let x = wires.spider(&mut diagram, t, VertexKind::X, Phase::ZERO);
+                    diagram.add_edge(z, x, EdgeKind::Simple);
+                }
+                Gate::CZ(a, b) => {
+                    let z1 = wires.spider(&mut diagram, a, VertexKind::Z, Phase::ZERO);
+                    let z2 = wires.spider(&mut diagram, b, VertexKind::Z, Phase::ZERO);
+                    diagram.add_edge(z1, z2, EdgeKind::Hadamard);
+                }
+                Gate::Swap(a, b) => {
+                    wires.last.swap(a, b);
+                    wires.hadamard.swap(a, b);
+                }
+            }
+        }
+
+        for q in 0..qubits {
+            let v = diagram.add_output();
+            wires.connect(&mut diagram, q, v);
+        }
+
+        diagram
+    }
+}
+
+impl QuantumCircuit {
+    /// Returns the ZX diagram of a unitary circuit, up to a global phase. Each qubit
+    /// has an input and an output boundary, in the order of their ids.
+    ///
+    /// Gates are decomposed into Z and X spiders connected by plain and Hadamard edges.
+    /// Barriers are ignored, and quantum modifiers are expanded into basic gates. Fails on
+    /// non-unitary operations, classical modifiers and formal parameters.
+    pub fn to_zx(&self) -> Result<Diagram, ZxError> {
+        let mut gates = Vec::new();
+        let mut iter = self.instructions();
+        let value = |parameter| self.evaluate(parameter, &[]).map_err(ZxError::FormalParameter);
+
+        while let Some(instr) = iter.next() {
+            let quantum = match &instr.modifier {
+                None => None,
+                Some(Modifier::IfBit(_) | Modifier::IfCompute(_)) => return Err(ZxError::UnsupportedModifier("if")),
+                Some(Modifier::WhileBit(_) | Modifier::WhileCompute(_)) => return Err(ZxError::UnsupportedModifier("while")),
+                Some(Modifier::ForConst(_) | Modifier::ForCompute(_)) => return Err(ZxError::UnsupportedModifier("for")),
+                Some(modifier) => Quantum::new(modifier, value)?,
+            };
+
+            let qubits: Vec<_> = instr.qubits.iter().map(|q| q.id() as usize).collect();
+
+            // Angles are expressed as multiples of pi, so that exact angles give exact phases.
+            let params = instr.parameters.iter()
+                .map(|&p| match p.as_angle() {
+                    Some((numerator, denominator)) => Ok(numerator as f64 / denominator as f64),
+                    None => value(p).map(|value| value / PI),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            match quantum {
+                None => decompose(&instr.op, &qubits, &params, &mut gates)?,
+                Some(quantum) => {
+                    let radians: Vec<_> = params.iter().map(|p| p * PI).collect();
+
+                    for gate in expand(&instr.op, &qubits, &radians, &quantum) {
+                        let params: Vec<_> = gate.parameters.iter().map(|p| p / PI).collect();
+                        decompose(&gate.op, &gate.qubits, &params, &mut gates)?;
+                    }
+                }
+            }
+        }
+
+        Ok(Diagram::from_gates(self.qubit_count(), &gates))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::instruction::Instr;
+    use crate::circuit::parameter::Parameter;
+
+    use super::*;
+
+    #[test]
+    fn gates() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [a, b, c] = circ.alloc_n()?;
+            circ.h(a).x(b).y(c).z(a).s(b).sdg(c).t(a).tdg(b).sx(c);
+            circ.rx(0.3, a).ry(-1.2, b).rz(2.5, c).phase(0.7, a).u3(0.1, 0.2, 0.3, b);
+            circ.cx(a, b).cy(b, c).cz(c, a).ch(a, c).swap(a, b).iswap(b, c);
+            circ.crz(0.4, a, c).cp(-0.9, c, b).rxx(1.1, a, b).rzz(0.6, b, c);
+            circ.ccx(a, b, c).cswap(c, a, b).barrier(&[a, b, c]);
+            Ok(())
+        }).unwrap();
+
+        let diagram = circ.to_zx().unwrap();
+
+        assert_eq!(diagram.inputs().len(), 3);
+        assert_eq!(diagram.outputs().len(), 3);
+        assert!(diagram.to_matrix().unwrap().approx_eq_up_to_phase(&circ.unitary().unwrap(), 1e-5));
+    }
+
+    #[test]
+    fn angles() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [a, b] = circ.alloc_n()?;
+            circ.rz(Parameter::pi(1, 4), a).rx(Parameter::pi(-3, 2), b).rzz(Parameter::pi(7, 4), a, b).phase(Parameter::pi(11, 8), b);
+            Ok(())
+        }).unwrap();
+
+        let diagram = circ.to_zx().unwrap();
+
+        // Exact angles are turned into exact phases, only the T and 11 pi/8 phases are not Clifford.
+        assert_eq!(diagram.t_count(), 3);
+        assert!(diagram.vertices().any(|v| diagram.phase(v) == Phase::new(11.0 / 8.0)));
+        assert!(diagram.to_matrix().unwrap().approx_eq_up_to_phase(&circ.unitary().unwrap(), 1e-5));
+    }
+
+    #[test]
+    fn errors() {
+        let circ = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            let b = circ.alloc()?;
+            circ.measure(&[q], &[b]);
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(circ.to_zx(), Err(ZxError::NonUnitary("measure")));
+
+        let circ = QuantumCircuit::new(|circ| {
+            let q = circ.alloc()?;
+            let b = circ.alloc()?;
+            circ.push(Instr { op: OpKind::X, qubits: &[q], modifier: Some(Modifier::IfBit(b)), ..Default::default() })?;
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(circ.to_zx(), Err(ZxError::UnsupportedModifier("if")));
+    }
+}
@@ -0,0 +1,512 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use crate::complex::Complex;
+use crate::matrix::Matrix;
+
+use super::Phase;
+
+/// The kind of a vertex of a ZX diagram.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum VertexKind {
+    /// An input or output of the diagram.
+    Boundary,
+    /// A green spider.
+    Z,
+    /// A red spider.
+    X,
+}
+
+/// The kind of an edge of a ZX diagram.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum EdgeKind {
+    /// A plain wire.
+    Simple,
+    /// A wire carrying a Hadamard box.
+    Hadamard,
+}
+
+impl EdgeKind {
+    /// Returns the other kind of edge.
+    #[inline]
+    pub fn toggle(self) -> Self {
+        match self {
+            Self::Simple => Self::Hadamard,
+            Self::Hadamard => Self::Simple,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+struct Vertex {
+    kind: VertexKind,
+    phase: Phase,
+    neighbors: BTreeMap<usize, EdgeKind>,
+}
+
+/// A ZX diagram: an undirected graph of spiders, with at most one edge between two
+/// vertices and no self-loops, along with ordered lists of input and output boundaries.
+///
+/// Diagrams are only considered up to a non-zero scalar. Vertices are identified by
+/// integers, which are not reused when vertices are removed.
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct Diagram {
+    vertices: Vec<Option<Vertex>>,
+    inputs: Vec<usize>,
+    outputs: Vec<usize>,
+}
+
+impl Diagram {
+    /// Returns an empty diagram.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a vertex to the diagram and returns it.
+    #[inline]
+    pub fn add_vertex(&mut self, kind: VertexKind, phase: Phase) -> usize {
+        self.vertices.push(Some(Vertex { kind, phase, neighbors: BTreeMap::new() }));
+        self.vertices.len() - 1
+    }
+
+    /// Removes a vertex along with all of it's edges.
+    pub fn remove_vertex(&mut self, v: usize) {
+        let vertex = self.vertices[v].take().expect("vertex was removed");
+
+        for u in vertex.neighbors.keys() {
+            self.vertex_mut(*u).neighbors.remove(&v);
+        }
+
+        self.inputs.retain(|&u| u != v);
+        self.outputs.retain(|&u| u != v);
+    }
+
+    /// Adds a boundary vertex to the inputs and returns it.
+    #[inline]
+    pub fn add_input(&mut self) -> usize {
+        let v = self.add_vertex(VertexKind::Boundary, Phase::ZERO);
+        self.inputs.push(v);
+        v
+    }
+
+    /// Adds a boundary vertex to the outputs and returns it.
+    #[inline]
+    pub fn add_output(&mut self) -> usize {
+        let v = self.add_vertex(VertexKind::Boundary, Phase::ZERO);
+        self.outputs.push(v);
+        v
+    }
+
+    /// Returns the inputs, in order.
+    #[inline]
+    pub fn inputs(&self) -> &[usize] {
+        &self.inputs
+    }
+
+    /// Returns the outputs, in order.
+    #[inline]
+    pub fn outputs(&self) -> &[usize] {
+        &self.outputs
+    }
+
+    #[inline]
+    fn vertex(&self, v: usize) -> &Vertex {
+        self.vertices[v].as_ref().expect("vertex was removed")
+    }
+
+    #[inline]
+    fn vertex_mut(&mut self, v: usize) -> &mut Vertex {
+        self.vertices[v].as_mut().expect("vertex was removed")
+    }
+
+    /// Returns true if the vertex is in the diagram.
+    #[inline]
+    pub fn contains(&self, v: usize) -> bool {
+        self.vertices.get(v).is_some_and(Option::is_some)
+    }
+
+    /// Returns an iterator over the vertices, in increasing order.
+    #[inline]
+    pub fn vertices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.vertices.iter().enumerate().filter_map(|(v, vertex)| vertex.as_ref().map(|_| v))
+    }
+
+    /// Returns an iterator over the edges, each edge `(u, v, kind)` being given once with `u < v`.
+    #[inline]
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize, EdgeKind)> + '_ {
+        self.vertices().flat_map(move |u| {
+            self.vertex(u).neighbors.iter()
+                .filter(move |(&v, _)| u < v)
+                .map(move |(&v, &kind)| (u, v, kind))
+        })
+    }
+
+    #[inline]
+    pub fn vertex_count(&self) -> usize {
+        self.vertices().count()
+    }
+
+    #[inline]
+    pub fn edge_count(&self) -> usize {
+        self.vertices().map(|v| self.degree(v)).sum::<usize>() / 2
+    }
+
+    #[inline]
+    pub fn kind(&self, v: usize) -> VertexKind {
+        self.vertex(v).kind
+    }
+
+    #[inline]
+    pub fn set_kind(&mut self, v: usize, kind: VertexKind) {
+        self.vertex_mut(v).kind = kind;
+    }
+
+    #[inline]
+    pub fn phase(&self, v: usize) -> Phase {
+        self.vertex(v).phase
+    }
+
+    #[inline]
+    pub fn set_phase(&mut self, v: usize, phase: Phase) {
+        self.vertex_mut(v).phase = phase;
+    }
+
+    #[inline]
+    pub fn add_to_phase(&mut self, v: usize, phase: Phase) {
+        self.vertex_mut(v).phase += phase;
+    }
+
+    /// Returns the neighbors of a vertex, in increasing order.
+    #[inline]
+    pub fn neighbors(&self, v: usize) -> impl Iterator<Item = usize> + '_ {
+        self.vertex(v).neighbors.keys().copied()
+    }
+
+    /// Returns the neighbors of a vertex along with the kinds of the edges, in increasing order.
+    #[inline]
+    pub fn incident_edges(&self, v: usize) -> impl Iterator<Item = (usize, EdgeKind)> + '_ {
+        self.vertex(v).neighbors.iter().map(|(&u, &kind)| (u, kind))
+    }
+
+    #[inline]
+    pub fn degree(&self, v: usize) -> usize {
+        self.vertex(v).neighbors.len()
+    }
+
+    /// Returns the kind of the edge between two vertices, if any.
+    #[inline]
+    pub fn edge(&self, u: usize, v: usize) -> Option<EdgeKind> {
+        self.vertex(u).neighbors.get(&v).copied()
+    }
+
+    /// Sets the edge between two distinct vertices, removing it if `kind` is `None`.
+    pub fn set_edge(&mut self, u: usize, v: usize, kind: Option<EdgeKind>) {
+        assert_ne!(u, v, "self-loops are not allowed");
+
+        match kind {
+            Some(kind) => {
+                self.vertex_mut(u).neighbors.insert(v, kind);
+                self.vertex_mut(v).neighbors.insert(u, kind);
+            }
+            None => {
+                self.vertex_mut(u).neighbors.remove(&v);
+                self.vertex_mut(v).neighbors.remove(&u);
+            }
+        }
+    }
+
+    /// Removes the edge between two vertices, if any.
+    #[inline]
+    pub fn remove_edge(&mut self, u: usize, v: usize) {
+        self.set_edge(u, v, None);
+    }
+
+    /// Adds an edge between two distinct vertices. If the vertices are already connected
+    /// spiders, the parallel edges are merged using the rules of the ZX calculus, which
+    /// may remove the edge or change the phase of `u`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an edge to a boundary already exists.
+    pub fn add_edge(&mut self, u: usize, v: usize, kind: EdgeKind) {
+        let Some(old) = self.edge(u, v) else {
+            return self.set_edge(u, v, Some(kind));
+        };
+
+        let (ku, kv) = (self.kind(u), self.kind(v));
+        assert!(ku != VertexKind::Boundary && kv != VertexKind::Boundary, "boundaries have a single edge");
+
+        // Spiders of different colors are reduced to the case of same colors by a color change,
+        // which exchanges the kinds of the two edges.
+        let same = ku == kv;
+        let (a, b) = if same { (old, kind) } else { (old.toggle(), kind.toggle()) };
+
+        let merged = match (a, b) {
+            // The spiders fuse, and a second plain edge is a removable self-loop.
+            (EdgeKind::Simple, EdgeKind::Simple) => Some(EdgeKind::Simple),
+            // Hopf rule.
+            (EdgeKind::Hadamard, EdgeKind::Hadamard) => None,
+            // The spiders fuse, and the Hadamard self-loop adds a phase of π.
+            _ => {
+                self.add_to_phase(u, Phase::PI);
+                Some(EdgeKind::Simple)
+            }
+        };
+
+        self.set_edge(u, v, if same { merged } else { merged.map(EdgeKind::toggle) });
+    }
+
+    /// Returns the linear map of the diagram as a matrix, normalized so that it is unitary if
+    /// the map is proportional to a unitary. The input and output at position $i$ are the
+    /// qubit $i$, which is the bit $i$ of the indices of the matrix.
+    ///
+    /// Returns `None` if the numbers of inputs and outputs differ, or if the map is zero.
+    /// This contracts the diagram as a tensor network, and is only meant for small diagrams.
+    pub fn to_matrix(&self) -> Option<Matrix> {
+        let n = self.inputs.len();
+
+        if self.outputs.len() != n {
+            return None;
+        }
+
+        let mut tensor = Tensor::scalar(Complex::ONE);
+
+        for v in self.contraction_order() {
+            tensor = tensor.contract(&self.tensor(v));
+        }
+
+        // The free indices are the inputs and outputs.
+        let dim = 1 << n;
+        let mut matrix = Matrix::zeros(dim);
+
+        for (i, value) in tensor.data.iter().enumerate() {
+            let (mut row, mut col) = (0, 0);
+
+            for (k, index) in tensor.indices.iter().enumerate() {
+                let bit = i >> k & 1;
+
+                match *index {
+                    Index::Boundary(b) => {
+                        if let Some(q) = self.inputs.iter().position(|&v| v == b) {
+                            col |= bit << q;
+                        }
+
+                        if let Some(q) = self.outputs.iter().position(|&v| v == b) {
+                            row |= bit << q;
+                        }
+                    }
+                    Index::Edge(..) => unreachable!(),
+                }
+            }
+
+            matrix[(row, col)] = *value;
+        }
+
+        let norm = matrix.as_slice().iter().map(|z| z.norm_sqr()).sum::<f64>().sqrt();
+
+        (norm > 1e-12).then(|| matrix.scale(Complex::from((dim as f64).sqrt() / norm)))
+    }
+
+    /// Returns the vertices in an order keeping the intermediate tensors small: breadth
+    /// first from the inputs.
+    fn contraction_order(&self) -> Vec<usize> {
+        let mut seen = vec![false; self.vertices.len()];
+        let mut order = Vec::new();
+        let mut queue: VecDeque<_> = self.inputs.iter().copied().collect();
+        let mut roots = self.vertices();
+
+        loop {
+            while let Some(v) = queue.pop_front() {
+                if !seen[v] {
+                    seen[v] = true;
+                    order.push(v);
+                    queue.extend(self.neighbors(v).filter(|&u| !seen[u]));
+                }
+            }
+
+            match roots.find(|&v| !seen[v]) {
+                Some(v) => queue.push_back(v),
+                None => return order,
+            }
+        }
+    }
+
+    /// Returns the tensor of a single vertex. A Hadamard edge is attached to the tensor of
+    /// it's endpoint of greatest id.
+    fn tensor(&self, v: usize) -> Tensor {
+        let vertex = self.vertex(v);
+        let mut indices: Vec<_> = vertex.neighbors.keys().map(|&u| Index::Edge(u.min(v), u.max(v))).collect();
+        let degree = indices.len();
+
+        let mut tensor = match vertex.kind {
+            VertexKind::Boundary => {
+                indices.push(Index::Boundary(v));
+                let mut data = vec![Complex::ZERO; 1 << indices.len()];
+
+                for i in 0..1 << degree {
+                    // A boundary of degree 1 is an identity between it's edge and it's free index.
+                    let value = i & 1;
+                    data[i | value << degree] = Complex::ONE;
+                }
+
+                Tensor { indices, data }
+            }
+            VertexKind::Z => {
+                let mut data = vec![Complex::ZERO; 1 << degree];
+                data[0] = Complex::ONE;
+                data[(1 << degree) - 1] += Complex::cis(vertex.phase.to_radians());
+                Tensor { indices, data }
+            }
+            VertexKind::X => {
+                let scale = 0.5f64.powi(degree as i32).sqrt();
+                let data = (0..1usize << degree)
+                    .map(|i| {
+                        let sign = if i.count_ones() % 2 == 0 { 1.0 } else { -1.0 };
+                        (Complex::ONE + Complex::cis(vertex.phase.to_radians()) * sign) * scale
+                    })
+                    .collect();
+                Tensor { indices, data }
+            }
+        };
+
+        for (k, (&u, &kind)) in vertex.neighbors.iter().enumerate() {
+            if kind == EdgeKind::Hadamard && u < v {
+                tensor.hadamard(k);
+            }
+        }
+
+        tensor
+    }
+}
+
+/// An index of a tensor of the network of a diagram.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+enum Index {
+    Edge(usize, usize),
+    Boundary(usize),
+}
+
+/// A tensor of qubit dimensions, the bit $k$ of the position of an element in `data`
+/// being the value of the index `indices[k]`.
+#[derive(Clone, Debug)]
+struct Tensor {
+    indices: Vec<Index>,
+    data: Vec<Complex>,
+}
+
+impl Tensor {
+    #[inline]
+    fn scalar(z: Complex) -> Self {
+        Self { indices: Vec::new(), data: vec![z] }
+    }
+
+    /// Applies a Hadamard matrix to the index at position `k`.
+    fn hadamard(&mut self, k: usize) {
+        let s = std::f64::consts::FRAC_1_SQRT_2;
+
+        for i in 0..self.data.len() {
+            if i >> k & 1 == 0 {
+                let j = i | 1 << k;
+                let (a, b) = (self.data[i], self.data[j]);
+                self.data[i] = (a + b) * s;
+                self.data[j] = (a - b) * s;
+            }
+        }
+    }
+
+    /// Contracts the indices shared by two tensors.
+    fn contract(&self, rhs: &Self) -> Self {
+        let positions: HashMap<_, _> = rhs.indices.iter().enumerate().map(|(k, &index)| (index, k)).collect();
+
+        let shared: Vec<_> = self.indices.iter()
+            .enumerate()
+            .filter_map(|(k, index)| positions.get(index).map(|&l| (k, l)))
+            .collect();
+        let lhs_free: Vec<_> = (0..self.indices.len()).filter(|k| shared.iter().all(|&(s, _)| s != *k)).collect();
+        let rhs_free: Vec<_> = (0..rhs.indices.len()).filter(|l| shared.iter().all(|&(_, s)| s != *l)).collect();
+
+        let indices = lhs_free.iter().map(|&k| self.indices[k])
+            .chain(rhs_free.iter().map(|&l| rhs.indices[l]))
+            .collect::<Vec<_>>();
+
+        let mut data = vec![Complex::ZERO; 1 << indices.len()];
+
+        for (i, value) in data.iter_mut().enumerate() {
+            let mut a = 0;
+            let mut b = 0;
+
+            for (bit, &k) in lhs_free.iter().enumerate() {
+                a |= (i >> bit & 1) << k;
+            }
+
+            for (bit, &l) in rhs_free.iter().enumerate() {
+                b |= (i >> (lhs_free.len() + bit) & 1) << l;
+            }
+
+            for s in 0..1usize << shared.len() {
+                let (mut a, mut b) = (a, b);
+
+                for (bit, &(k, l)) in shared.iter().enumerate() {
+                    a |= (s >> bit & 1) << k;
+                    b |= (s >> bit & 1) << l;
+                }
+
+                *value += self.data[a] * rhs.data[b];
+            }
+        }
+
+        Self { indices, data }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edges() {
+        let mut diagram = Diagram::new();
+        let z1 = diagram.add_vertex(VertexKind::Z, Phase::ZERO);
+        let z2 = diagram.add_vertex(VertexKind::Z, Phase::HALF_PI);
+        let x = diagram.add_vertex(VertexKind::X, Phase::ZERO);
+
+        diagram.add_edge(z1, z2, EdgeKind::Hadamard);
+        diagram.add_edge(z1, x, EdgeKind::Simple);
+        assert_eq!(diagram.edge_count(), 2);
+
+        diagram.add_edge(z1, z2, EdgeKind::Hadamard);
+        diagram.add_edge(z1, x, EdgeKind::Simple);
+        assert_eq!(diagram.edge_count(), 0);
+
+        diagram.add_edge(z2, z1, EdgeKind::Simple);
+        diagram.add_edge(z2, z1, EdgeKind::Hadamard);
+        assert_eq!(diagram.edge(z1, z2), Some(EdgeKind::Simple));
+        assert_eq!(diagram.phase(z2), Phase::new(1.5));
+
+        diagram.remove_vertex(z1);
+        assert_eq!(diagram.vertices().collect::<Vec<_>>(), [z2, x]);
+        assert_eq!(diagram.degree(z2), 0);
+    }
+
+    #[test]
+    fn matrix() {
+        // A Hadamard edge between two boundaries.
+        let mut diagram = Diagram::new();
+        let (i, o) = (diagram.add_input(), diagram.add_output());
+        diagram.add_edge(i, o, EdgeKind::Hadamard);
+
+        let s = std::f64::consts::FRAC_1_SQRT_2;
+        let h = Matrix::from_vec(2, vec![s.into(), s.into(), s.into(), (-s).into()]).unwrap();
+        assert!(diagram.to_matrix().unwrap().approx_eq_up_to_phase(&h, 1e-9));
+
+        // An X spider of phase π is the X gate.
+        let mut diagram = Diagram::new();
+        let (i, o) = (diagram.add_input(), diagram.add_output());
+        let x = diagram.add_vertex(VertexKind::X, Phase::PI);
+        diagram.add_edge(i, x, EdgeKind::Simple);
+        diagram.add_edge(x, o, EdgeKind::Simple);
+
+        let not = Matrix::from_vec(2, vec![0.0.into(), 1.0.into(), 1.0.into(), 0.0.into()]).unwrap();
+        assert!(diagram.to_matrix().unwrap().approx_eq_up_to_phase(&not, 1e-9));
+    }
+}
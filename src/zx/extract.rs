@@ -0,0 +1,315 @@
+//! Extraction of circuits from graph-like diagrams with a gflow, following Backens,
+//! Miller-Bakewell, de Felice, Lobski and van de Wetering [8], see `doc/biblio.md`.
+//!
+//! Gates are extracted from the outputs towards the inputs. The spiders connected to
+//! the outputs form the frontier: their phases become phase gates, the edges between
+//! them become CZ gates, and Gaussian elimination of their biadjacency matrix with the
+//! rest of the diagram produces CX gates until a frontier spider has a single neighbor,
+//! which then replaces it. Phase gadgets left by [`Diagram::full_reduce`] are removed by
+//! pivoting them with a frontier spider.
+
+use crate::circuit::QuantumCircuit;
+use crate::circuit::symbol::Qubit;
+
+use super::circuit::Gate;
+use super::{Diagram, EdgeKind, Phase, VertexKind, ZxError};
+
+/// Reduces a matrix over $\mathbb{F}_2$ so that each pivot column contains a single one,
+/// and returns the row additions performed, as pairs `(target, source)` of rows.
+fn gauss(matrix: &mut [Vec<bool>]) -> Vec<(usize, usize)> {
+    let mut ops = Vec::new();
+    let mut used = vec![false; matrix.len()];
+    let cols = matrix.first().map_or(0, Vec::len);
+
+    for col in 0..cols {
+        let Some(pivot) = (0..matrix.len()).find(|&r| !used[r] && matrix[r][col]) else {
+            continue;
+        };
+
+        used[pivot] = true;
+
+        for row in 0..matrix.len() {
+            if row != pivot && matrix[row][col] {
+                let source = matrix[pivot].clone();
+
+                for (x, y) in matrix[row].iter_mut().zip(source) {
+                    *x ^= y;
+                }
+
+                ops.push((row, pivot));
+            }
+        }
+    }
+
+    ops
+}
+
+impl Diagram {
+    /// Extracts the gates of the diagram, which must be graph-like, in reverse order. Returns
+    /// the gates along with the input connected to each output once all gates are extracted.
+    fn extract(&mut self) -> Result<(Vec<Gate>, Vec<usize>), ZxError> {
+        let inputs = self.inputs().to_vec();
+        let outputs = self.outputs().to_vec();
+        let n = outputs.len();
+
+        let mut gates = Vec::new();
+        let mut frontier = Vec::with_capacity(n);
+        let mut sources = vec![0; n];
+
+        for (q, &o) in outputs.iter().enumerate() {
+            let (v, kind) = self.incident_edges(o).next().expect("output is not connected");
+
+            if kind == EdgeKind::Hadamard {
+                gates.push(Gate::H(q));
+                self.set_edge(o, v, Some(EdgeKind::Simple));
+            }
+
+            frontier.push(Some(v));
+        }
+
+        loop {
+            for (q, &f) in frontier.iter().enumerate() {
+                if let Some(f) = f.filter(|&f| !self.phase(f).is_zero()) {
+                    gates.push(Gate::Z(q, self.phase(f)));
+                    self.set_phase(f, Phase::ZERO);
+                }
+            }
+
+            for q1 in 0..n {
+                for q2 in q1 + 1..n {
+                    if let (Some(f1), Some(f2)) = (frontier[q1], frontier[q2]) {
+                        if self.edge(f1, f2).is_some() {
+                            gates.push(Gate::CZ(q1, q2));
+                            self.remove_edge(f1, f2);
+                        }
+                    }
+                }
+            }
+
+            // The frontier spiders only connected to an input are done, the others are
+            // detached from their input so that they are only connected to spiders.
+            for q in 0..n {
+                let Some(f) = frontier[q] else { continue };
+                let rest: Vec<_> = self.neighbors(f).filter(|&w| w != outputs[q]).collect();
+
+                if let Some(&b) = rest.iter().find(|&&w| self.kind(w) == VertexKind::Boundary) {
+                    if rest.len() > 1 {
+                        self.detach(b);
+                        continue;
+                    }
+
+                    if self.edge(f, b) == Some(EdgeKind::Hadamard) {
+                        gates.push(Gate::H(q));
+                    }
+
+                    sources[q] = inputs.iter().position(|&i| i == b).expect("boundary is not an input");
+                    frontier[q] = None;
+                }
+            }
+
+            let active: Vec<(usize, usize)> = frontier.iter()
+                .enumerate()
+                .filter_map(|(q, f)| f.map(|f| (q, f)))
+                .collect();
+
+            if active.is_empty() {
+                break;
+            }
+
+            let mut neighbors: Vec<_> = active.iter()
+                .flat_map(|&(q, f)| {
+                    let o = outputs[q];
+                    self.neighbors(f).filter(move |&w| w != o)
+                })
+                .collect();
+
+            neighbors.sort_unstable();
+            neighbors.dedup();
+
+            let mut matrix: Vec<Vec<bool>> = active.iter()
+                .map(|&(_, f)| neighbors.iter().map(|&w| self.edge(f, w).is_some()).collect())
+                .collect();
+
+            // Adding the row of `source` to the row of `target` is a CX controlled by `target`.
+            for (target, source) in gauss(&mut matrix) {
+                let ((qt, ft), (qs, fs)) = (active[target], active[source]);
+                let edges: Vec<_> = self.neighbors(fs).filter(|&w| w != outputs[qs]).collect();
+
+                for w in edges {
+                    self.add_edge(ft, w, EdgeKind::Hadamard);
+                }
+
+                gates.push(Gate::CX(qt, qs));
+            }
+
+            let mut progress = false;
+
+            for (row, &(q, f)) in matrix.iter().zip(&active) {
+                if row.iter().filter(|&&x| x).count() == 1 {
+                    let w = neighbors[row.iter().position(|&x| x).unwrap()];
+
+                    gates.push(Gate::H(q));
+                    self.remove_vertex(f);
+                    self.set_edge(w, outputs[q], Some(EdgeKind::Simple));
+                    frontier[q] = Some(w);
+                    progress = true;
+                }
+            }
+
+            if progress {
+                continue;
+            }
+
+            let gadget = active.iter()
+                .flat_map(|&(q, f)| self.neighbors(f).map(move |a| (q, f, a)))
+                .find(|&(_, _, a)| self.is_axle(a) && self.is_interior(a) && self.phase(a).is_pauli());
+
+            let Some((q, f, axle)) = gadget else {
+                return Err(ZxError::NoGflow);
+            };
+
+            // Detaches the frontier spider from its output, and pivots it with the axle.
+            let z = self.detach(outputs[q]);
+
+            self.set_edge(outputs[q], z, Some(EdgeKind::Simple));
+            gates.push(Gate::H(q));
+            self.pivot(f, axle);
+            frontier[q] = Some(z);
+        }
+
+        Ok((gates, sources))
+    }
+
+    /// Extracts a circuit from the diagram, made of CX, CZ, H, phase and swap gates. The
+    /// diagram is first made graph-like, see [`to_graph_like`](Self::to_graph_like).
+    ///
+    /// Fails if the numbers of inputs and outputs differ, or if the diagram has no gflow,
+    /// as only diagrams with a gflow are known to be efficiently extractable. Diagrams
+    /// obtained from circuits and simplified with the rules of this module always have one.
+    pub fn to_circuit(&self) -> Result<QuantumCircuit, ZxError> {
+        let n = self.outputs().len();
+
+        if self.inputs().len() != n {
+            return Err(ZxError::BoundaryMismatch {
+                inputs: self.inputs().len(),
+                outputs: n,
+            });
+        }
+
+        let mut diagram = self.clone();
+        diagram.to_graph_like();
+
+        let (gates, sources) = diagram.extract()?;
+
+        // The wire of input `sources[q]` ends at output `q`, which is realized with swaps.
+        let mut wires: Vec<_> = (0..n).collect();
+        let mut swaps = Vec::new();
+
+        for (q, &source) in sources.iter().enumerate() {
+            let pos = wires.iter().position(|&w| w == source).unwrap();
+
+            if pos != q {
+                wires.swap(q, pos);
+                swaps.push(Gate::Swap(q, pos));
+            }
+        }
+
+        let circ = QuantumCircuit::new(|circ| {
+            let qubits: Vec<Qubit> = circ.alloc_list(n)?.into_iter().collect();
+
+            for gate in swaps.into_iter().chain(gates.into_iter().rev()) {
+                match gate {
+                    Gate::Z(q, phase) => {
+                        let quarters = phase.value() * 4.0;
+
+                        if quarters.fract() != 0.0 {
+                            circ.phase(phase.to_radians() as f32, qubits[q]);
+                            continue;
+                        }
+
+                        match quarters as u32 {
+                            1 => circ.t(qubits[q]),
+                            2 => circ.s(qubits[q]),
+                            3 => circ.s(qubits[q]).t(qubits[q]),
+                            4 => circ.z(qubits[q]),
+                            5 => circ.z(qubits[q]).t(qubits[q]),
+                            6 => circ.sdg(qubits[q]),
+                            _ => circ.tdg(qubits[q]),
+                        };
+                    }
+                    Gate::H(q) => _ = circ.h(qubits[q]),
+                    Gate::CX(c, t) => _ = circ.cx(qubits[c], qubits[t]),
+                    Gate::CZ(a, b) => _ = circ.cz(qubits[a], qubits[b]),
+                    Gate::Swap(a, b) => _ = circ.swap(qubits[a], qubits[b]),
+                    Gate::X(..) => unreachable!("extraction only produces Z spiders"),
+                }
+            }
+
+            Ok(())
+        })?;
+
+        Ok(circ)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(circ: &QuantumCircuit, simplify: impl Fn(&mut Diagram)) -> QuantumCircuit {
+        let mut diagram = circ.to_zx().unwrap();
+        simplify(&mut diagram);
+
+        let extracted = diagram.to_circuit().unwrap();
+        assert!(extracted.unitary().unwrap().approx_eq_up_to_phase(&circ.unitary().unwrap(), 1e-5));
+        extracted
+    }
+
+    #[test]
+    fn extract() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [a, b, c] = circ.alloc_n()?;
+            circ.h(a).cx(a, b).t(b).cx(b, c).s(c).tdg(a).cx(c, a).t(c).h(b).swap(a, b);
+            circ.ccx(a, b, c).t(c).h(a).cx(a, b).sdg(b).rz(0.3, a).cx(b, a).tdg(a).h(c);
+            Ok(())
+        }).unwrap();
+
+        check(&circ, |_| ());
+        check(&circ, Diagram::clifford_simp);
+
+        let extracted = check(&circ, Diagram::full_reduce);
+        let t_count = |circ: &QuantumCircuit| circ.to_zx().unwrap().t_count();
+
+        assert!(t_count(&extracted) < t_count(&circ));
+    }
+
+    #[test]
+    fn permutation() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [a, b, c] = circ.alloc_n()?;
+            circ.swap(a, b).h(c).swap(b, c).cx(a, b).cx(b, a).cx(a, b);
+            Ok(())
+        }).unwrap();
+
+        let extracted = check(&circ, Diagram::full_reduce);
+        assert!(extracted.is_clifford());
+    }
+
+    #[test]
+    fn errors() {
+        // A spider plugged with the |0> state is a projection, not a unitary.
+        let mut diagram = Diagram::new();
+        let (i, o) = (diagram.add_input(), diagram.add_output());
+        let [x, f, w] = [(); 3].map(|_| diagram.add_vertex(VertexKind::Z, Phase::ZERO));
+
+        diagram.add_edge(i, x, EdgeKind::Simple);
+        diagram.add_edge(x, f, EdgeKind::Hadamard);
+        diagram.add_edge(f, w, EdgeKind::Hadamard);
+        diagram.add_edge(f, o, EdgeKind::Simple);
+
+        assert_eq!(diagram.to_circuit().err(), Some(ZxError::NoGflow));
+
+        diagram.add_input();
+        assert_eq!(diagram.to_circuit().err(), Some(ZxError::BoundaryMismatch { inputs: 2, outputs: 1 }));
+    }
+}
@@ -0,0 +1,38 @@
+//! ZX diagrams, a graphical language for linear maps between qubits.
+//!
+//! A diagram is a graph of Z (green) and X (red) spiders carrying phases, connected by
+//! plain or Hadamard edges, along with input and output boundaries. See the references
+//! listed in `doc/biblio.md` for an introduction to the ZX calculus.
+//!
+//! Diagrams of circuits, see [`QuantumCircuit::to_zx`](crate::circuit::QuantumCircuit::to_zx),
+//! can be simplified with [`Diagram::full_reduce`] to reduce their number of non-Clifford
+//! spiders, and turned back into circuits with [`Diagram::to_circuit`].
+
+mod circuit;
+mod diagram;
+mod extract;
+mod phase;
+mod simplify;
+
+use thiserror::Error;
+
+use crate::circuit::QuantumCircuitError;
+
+pub use diagram::{Diagram, EdgeKind, VertexKind};
+pub use phase::Phase;
+
+#[derive(Clone, PartialEq, Eq, Debug, Error)]
+pub enum ZxError {
+    #[error("operation `{0}` is not unitary")]
+    NonUnitary(&'static str),
+    #[error("modifier `{0}` cannot be represented in a ZX diagram")]
+    UnsupportedModifier(&'static str),
+    #[error("formal parameter {0} is not bound")]
+    FormalParameter(u32),
+    #[error("diagram has {inputs} inputs but {outputs} outputs")]
+    BoundaryMismatch { inputs: usize, outputs: usize },
+    #[error("diagram has no gflow")]
+    NoGflow,
+    #[error(transparent)]
+    Circuit(#[from] QuantumCircuitError),
+}
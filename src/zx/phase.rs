@@ -0,0 +1,124 @@
+use std::f64::consts::PI;
+use std::ops::{Add, AddAssign, Neg, Sub};
+
+/// The tolerance under which phases are considered to be multiples of $\pi/4$.
+const EPS: f64 = 1e-6;
+
+/// The phase of a spider, stored as a multiple of $\pi$ in $[0, 2)$.
+///
+/// Since the parameters of circuits are floats, phases within a small tolerance of a
+/// multiple of $\pi/4$ are rounded to it, so that Clifford phases can be recognized.
+#[derive(Copy, Clone, PartialEq, PartialOrd, Default, Debug)]
+pub struct Phase(f64);
+
+impl Phase {
+    pub const ZERO: Self = Self(0.0);
+    pub const PI: Self = Self(1.0);
+    pub const HALF_PI: Self = Self(0.5);
+    pub const QUARTER_PI: Self = Self(0.25);
+
+    /// Returns the phase $x \pi$.
+    #[inline]
+    pub fn new(x: f64) -> Self {
+        let x = x.rem_euclid(2.0);
+        let rounded = (x * 4.0).round() / 4.0;
+
+        if (x - rounded).abs() < EPS {
+            Self(rounded % 2.0)
+        } else {
+            Self(x)
+        }
+    }
+
+    /// Returns the phase $\theta$, given in radians.
+    #[inline]
+    pub fn from_radians(theta: f64) -> Self {
+        Self::new(theta / PI)
+    }
+
+    /// Returns the phase as a multiple of $\pi$, in $[0, 2)$.
+    #[inline]
+    pub fn value(self) -> f64 {
+        self.0
+    }
+
+    /// Returns the phase in radians, in $[0, 2\pi)$.
+    #[inline]
+    pub fn to_radians(self) -> f64 {
+        self.0 * PI
+    }
+
+    #[inline]
+    pub fn is_zero(self) -> bool {
+        self.0 == 0.0
+    }
+
+    /// Returns true if the phase is $0$ or $\pi$.
+    #[inline]
+    pub fn is_pauli(self) -> bool {
+        self.0 == 0.0 || self.0 == 1.0
+    }
+
+    /// Returns true if the phase is $\pm \pi/2$.
+    #[inline]
+    pub fn is_proper_clifford(self) -> bool {
+        self.0 == 0.5 || self.0 == 1.5
+    }
+
+    /// Returns true if the phase is a multiple of $\pi/2$.
+    #[inline]
+    pub fn is_clifford(self) -> bool {
+        self.is_pauli() || self.is_proper_clifford()
+    }
+}
+
+impl Add for Phase {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.0 + rhs.0)
+    }
+}
+
+impl AddAssign for Phase {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Phase {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.0 - rhs.0)
+    }
+}
+
+impl Neg for Phase {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self {
+        Self::new(-self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phases() {
+        assert_eq!(Phase::new(-0.5), Phase::new(1.5));
+        assert_eq!(Phase::from_radians(std::f32::consts::FRAC_PI_4 as f64), Phase::QUARTER_PI);
+        assert_eq!(Phase::new(1.9999999), Phase::ZERO);
+        assert_eq!(Phase::PI + Phase::PI, Phase::ZERO);
+        assert_eq!(-Phase::HALF_PI, Phase::new(1.5));
+        assert!(Phase::new(1.5).is_proper_clifford());
+        assert!(!Phase::QUARTER_PI.is_clifford());
+        assert!(!Phase::new(0.3).is_clifford());
+    }
+}
@@ -0,0 +1,472 @@
+//! The simplification strategy of Duncan, Kissinger, Perdrix and van de Wetering [4],
+//! see `doc/biblio.md`. Rules are applied on graph-like diagrams, whose spiders are all
+//! Z spiders connected by Hadamard edges, with every boundary connected to a spider and
+//! every spider connected to at most one boundary.
+
+use super::{Diagram, EdgeKind, Phase, VertexKind};
+
+impl Diagram {
+    /// Returns the number of spiders whose phase is not a multiple of $\pi/2$.
+    pub fn t_count(&self) -> usize {
+        self.vertices()
+            .filter(|&v| self.kind(v) != VertexKind::Boundary && !self.phase(v).is_clifford())
+            .count()
+    }
+
+    /// Returns true if the vertex is a spider none of whose neighbors is a boundary.
+    #[inline]
+    pub(crate) fn is_interior(&self, v: usize) -> bool {
+        self.kind(v) == VertexKind::Z && self.neighbors(v).all(|u| self.kind(u) != VertexKind::Boundary)
+    }
+
+    /// Returns the number of boundaries adjacent to a vertex.
+    #[inline]
+    fn boundaries(&self, v: usize) -> usize {
+        self.neighbors(v).filter(|&u| self.kind(u) == VertexKind::Boundary).count()
+    }
+
+    /// Returns true if the vertex is the leaf of a phase gadget: a spider of degree 1 connected to a spider.
+    #[inline]
+    pub(crate) fn is_leaf(&self, v: usize) -> bool {
+        self.kind(v) == VertexKind::Z
+            && self.degree(v) == 1
+            && self.neighbors(v).all(|u| self.kind(u) == VertexKind::Z)
+    }
+
+    /// Returns true if the vertex is the axle of a phase gadget, that is connected to a leaf.
+    #[inline]
+    pub(crate) fn is_axle(&self, v: usize) -> bool {
+        self.kind(v) == VertexKind::Z && self.degree(v) > 1 && self.neighbors(v).any(|u| self.is_leaf(u))
+    }
+
+    /// Returns true if the diagram is graph-like.
+    pub fn is_graph_like(&self) -> bool {
+        self.vertices().all(|v| match self.kind(v) {
+            VertexKind::Boundary => self.degree(v) == 1 && self.neighbors(v).all(|u| self.kind(u) == VertexKind::Z),
+            VertexKind::Z => {
+                self.boundaries(v) <= 1
+                    && self.incident_edges(v).all(|(u, kind)| self.kind(u) == VertexKind::Boundary || kind == EdgeKind::Hadamard)
+            }
+            VertexKind::X => false,
+        })
+    }
+
+    /// Fuses the spider `u` into its neighbor `v`, which must be of the same color.
+    fn fuse(&mut self, v: usize, u: usize) {
+        let phase = self.phase(u);
+        self.add_to_phase(v, phase);
+
+        let edges: Vec<_> = self.incident_edges(u).filter(|&(w, _)| w != v).collect();
+        self.remove_vertex(u);
+
+        for (w, kind) in edges {
+            self.add_edge(v, w, kind);
+        }
+    }
+
+    /// Applies a rule as long as it matches, and returns the number of applications.
+    fn repeat(&mut self, mut rule: impl FnMut(&mut Self) -> bool) -> usize {
+        let mut count = 0;
+
+        while rule(self) {
+            count += 1;
+        }
+
+        count
+    }
+
+    /// Fuses the spiders of the same color connected by plain edges, unless the fused
+    /// spider would be connected to two boundaries.
+    pub fn spider_simp(&mut self) -> usize {
+        self.repeat(|diagram| {
+            let matched = diagram.edges().find(|&(u, v, kind)| {
+                kind == EdgeKind::Simple
+                    && diagram.kind(u) != VertexKind::Boundary
+                    && diagram.kind(u) == diagram.kind(v)
+                    && diagram.boundaries(u) + diagram.boundaries(v) <= 1
+            });
+
+            matched.map(|(u, v, _)| diagram.fuse(u, v)).is_some()
+        })
+    }
+
+    /// Turns all X spiders into Z spiders by changing the kinds of their edges.
+    pub fn color_change(&mut self) {
+        let xs: Vec<_> = self.vertices().filter(|&v| self.kind(v) == VertexKind::X).collect();
+
+        for v in xs {
+            self.set_kind(v, VertexKind::Z);
+
+            for (u, kind) in self.incident_edges(v).collect::<Vec<_>>() {
+                self.set_edge(u, v, Some(kind.toggle()));
+            }
+        }
+    }
+
+    /// Inserts spiders between a boundary and its neighbor, so that the boundary is
+    /// connected to a new spider whose other edge is a Hadamard edge.
+    pub(crate) fn detach(&mut self, b: usize) -> usize {
+        let (n, kind) = self.incident_edges(b).next().expect("boundary is not connected");
+        let z = self.add_vertex(VertexKind::Z, Phase::ZERO);
+
+        self.remove_edge(b, n);
+
+        if self.kind(n) == VertexKind::Boundary {
+            self.set_edge(b, z, Some(EdgeKind::Simple));
+            self.set_edge(z, n, Some(kind));
+            self.detach(n);
+        } else {
+            self.set_edge(b, z, Some(kind.toggle()));
+            self.set_edge(z, n, Some(EdgeKind::Hadamard));
+        }
+
+        z
+    }
+
+    /// Turns the diagram into a graph-like diagram.
+    pub fn to_graph_like(&mut self) {
+        self.color_change();
+
+        loop {
+            self.spider_simp();
+
+            // The spiders still connected by plain edges are both connected to boundaries.
+            let blocked = self.edges().find(|&(u, v, kind)| {
+                kind == EdgeKind::Simple && self.kind(u) == VertexKind::Z && self.kind(v) == VertexKind::Z
+            });
+
+            let Some((_, v, _)) = blocked else { break };
+            let b = self.neighbors(v).find(|&u| self.kind(u) == VertexKind::Boundary).unwrap();
+            self.detach(b);
+        }
+
+        let boundaries: Vec<_> = self.inputs().iter().chain(self.outputs()).copied().collect();
+
+        for b in boundaries {
+            let n = self.neighbors(b).next().expect("boundary is not connected");
+
+            if self.kind(n) == VertexKind::Boundary || self.boundaries(n) > 1 {
+                self.detach(b);
+            }
+        }
+    }
+
+    /// Removes the spiders of phase zero and degree 2, unless this would connect two
+    /// boundaries or a spider to a second boundary.
+    pub fn id_simp(&mut self) -> usize {
+        self.repeat(|diagram| {
+            let matched = diagram.vertices().find_map(|v| {
+                if diagram.kind(v) != VertexKind::Z || !diagram.phase(v).is_zero() || diagram.degree(v) != 2 {
+                    return None;
+                }
+
+                let mut edges = diagram.incident_edges(v);
+                let ((a, ka), (b, kb)) = (edges.next()?, edges.next()?);
+                let kind = if ka == kb { EdgeKind::Simple } else { EdgeKind::Hadamard };
+
+                let allowed = match (diagram.kind(a), diagram.kind(b)) {
+                    (VertexKind::Boundary, VertexKind::Boundary) => false,
+                    (VertexKind::Boundary, _) => diagram.boundaries(b) == 0,
+                    (_, VertexKind::Boundary) => diagram.boundaries(a) == 0,
+                    // The two spiders will be fused.
+                    _ => kind == EdgeKind::Hadamard || diagram.boundaries(a) + diagram.boundaries(b) <= 1,
+                };
+
+                allowed.then_some((v, a, b, kind))
+            });
+
+            matched.map(|(v, a, b, kind)| {
+                diagram.remove_vertex(v);
+                diagram.add_edge(a, b, kind);
+            }).is_some()
+        })
+    }
+
+    /// Removes an interior spider of phase $\pm \pi/2$ by local complementation.
+    fn lcomp(&mut self, v: usize) {
+        let phase = self.phase(v);
+        let neighbors: Vec<_> = self.neighbors(v).collect();
+        self.remove_vertex(v);
+
+        for (i, &a) in neighbors.iter().enumerate() {
+            self.add_to_phase(a, -phase);
+
+            for &b in &neighbors[i + 1..] {
+                self.add_edge(a, b, EdgeKind::Hadamard);
+            }
+        }
+    }
+
+    /// Removes the interior spiders of phase $\pm \pi/2$ by local complementation.
+    pub fn lcomp_simp(&mut self) -> usize {
+        self.repeat(|diagram| {
+            let matched = diagram.vertices().find(|&v| {
+                diagram.is_interior(v)
+                    && diagram.phase(v).is_proper_clifford()
+                    && diagram.incident_edges(v).all(|(_, kind)| kind == EdgeKind::Hadamard)
+            });
+
+            matched.map(|v| diagram.lcomp(v)).is_some()
+        })
+    }
+
+    /// Removes two adjacent interior spiders of Pauli phases by pivoting along their edge.
+    pub(crate) fn pivot(&mut self, u: usize, v: usize) {
+        let (pu, pv) = (self.phase(u), self.phase(v));
+        let nu: Vec<_> = self.neighbors(u).filter(|&w| w != v).collect();
+        let nv: Vec<_> = self.neighbors(v).filter(|&w| w != u).collect();
+
+        let shared: Vec<_> = nu.iter().copied().filter(|w| nv.contains(w)).collect();
+        let only_u: Vec<_> = nu.iter().copied().filter(|w| !shared.contains(w)).collect();
+        let only_v: Vec<_> = nv.iter().copied().filter(|w| !shared.contains(w)).collect();
+
+        self.remove_vertex(u);
+        self.remove_vertex(v);
+
+        for (xs, ys) in [(&only_u, &only_v), (&only_u, &shared), (&only_v, &shared)] {
+            for &x in xs {
+                for &y in ys {
+                    self.add_edge(x, y, EdgeKind::Hadamard);
+                }
+            }
+        }
+
+        only_u.iter().for_each(|&w| self.add_to_phase(w, pv));
+        only_v.iter().for_each(|&w| self.add_to_phase(w, pu));
+        shared.iter().for_each(|&w| self.add_to_phase(w, pu + pv + Phase::PI));
+    }
+
+    /// Returns true if the vertex can be used by a pivot: it is a spider of Pauli phase
+    /// connected to spiders by Hadamard edges, and is not part of a phase gadget.
+    fn is_pivotable(&self, v: usize) -> bool {
+        self.kind(v) == VertexKind::Z
+            && self.phase(v).is_pauli()
+            && !self.is_leaf(v)
+            && !self.is_axle(v)
+            && self.incident_edges(v).all(|(u, kind)| self.kind(u) == VertexKind::Boundary || kind == EdgeKind::Hadamard)
+    }
+
+    /// Removes pairs of adjacent interior spiders of Pauli phases by pivoting.
+    pub fn pivot_simp(&mut self) -> usize {
+        self.repeat(|diagram| {
+            let matched = diagram.edges().find(|&(u, v, _)| {
+                diagram.is_pivotable(u) && diagram.is_pivotable(v) && diagram.is_interior(u) && diagram.is_interior(v)
+            });
+
+            matched.map(|(u, v, _)| diagram.pivot(u, v)).is_some()
+        })
+    }
+
+    /// Moves the phase of a spider to a new phase gadget connected to it.
+    fn unfuse_phase(&mut self, v: usize) {
+        let axle = self.add_vertex(VertexKind::Z, Phase::ZERO);
+        let leaf = self.add_vertex(VertexKind::Z, self.phase(v));
+
+        self.set_phase(v, Phase::ZERO);
+        self.set_edge(v, axle, Some(EdgeKind::Hadamard));
+        self.set_edge(axle, leaf, Some(EdgeKind::Hadamard));
+    }
+
+    /// Removes the interior spiders of Pauli phases adjacent to boundary spiders, by
+    /// detaching the boundaries and moving the phases of the boundary spiders to gadgets.
+    pub fn pivot_boundary_simp(&mut self) -> usize {
+        self.repeat(|diagram| {
+            let matched = diagram.edges()
+                .flat_map(|(u, v, _)| [(u, v), (v, u)])
+                .find(|&(u, v)| {
+                    diagram.is_pivotable(u)
+                        && diagram.is_interior(u)
+                        && diagram.kind(v) == VertexKind::Z
+                        && !diagram.is_interior(v)
+                        && !diagram.is_axle(v)
+                        && diagram.incident_edges(v).all(|(w, kind)| diagram.kind(w) == VertexKind::Boundary || kind == EdgeKind::Hadamard)
+                });
+
+            matched.map(|(u, v)| {
+                let boundary = diagram.neighbors(v).find(|&w| diagram.kind(w) == VertexKind::Boundary).unwrap();
+                diagram.detach(boundary);
+
+                if !diagram.phase(v).is_pauli() {
+                    diagram.unfuse_phase(v);
+                }
+
+                diagram.pivot(u, v);
+            }).is_some()
+        })
+    }
+
+    /// Removes the interior spiders of Pauli phases adjacent to interior spiders of
+    /// non-Pauli phases, by moving the non-Pauli phases to gadgets.
+    pub fn pivot_gadget_simp(&mut self) -> usize {
+        self.repeat(|diagram| {
+            let matched = diagram.edges()
+                .flat_map(|(u, v, _)| [(u, v), (v, u)])
+                .find(|&(u, v)| {
+                    diagram.is_pivotable(u)
+                        && diagram.is_interior(u)
+                        && diagram.is_interior(v)
+                        && !diagram.phase(v).is_pauli()
+                        && !diagram.is_leaf(v)
+                        && !diagram.is_axle(v)
+                        && diagram.incident_edges(v).all(|(_, kind)| kind == EdgeKind::Hadamard)
+                });
+
+            matched.map(|(u, v)| {
+                diagram.unfuse_phase(v);
+                diagram.pivot(u, v);
+            }).is_some()
+        })
+    }
+
+    /// Fuses the phase gadgets connected to the same spiders, and removes the gadgets of phase zero.
+    pub fn gadget_simp(&mut self) -> usize {
+        let mut count = 0;
+
+        // Normalizes the gadgets so that axles have a phase of zero.
+        let axles: Vec<_> = self.vertices().filter(|&v| self.is_axle(v)).collect();
+        let mut gadgets = Vec::new();
+
+        for axle in axles {
+            let leaf = self.neighbors(axle).find(|&u| self.is_leaf(u)).unwrap();
+
+            if self.phase(axle) == Phase::PI {
+                self.set_phase(axle, Phase::ZERO);
+                self.set_phase(leaf, -self.phase(leaf));
+            }
+
+            if self.phase(axle).is_zero() {
+                let support: Vec<_> = self.neighbors(axle).filter(|&u| u != leaf).collect();
+                gadgets.push((support, axle, leaf));
+            }
+        }
+
+        gadgets.sort();
+
+        for pair in gadgets.windows(2) {
+            let [(s1, a1, l1), (s2, a2, l2)] = pair else { unreachable!() };
+
+            if s1 == s2 && self.contains(*a1) {
+                let phase = self.phase(*l1);
+                self.add_to_phase(*l2, phase);
+                self.remove_vertex(*a1);
+                self.remove_vertex(*l1);
+                count += 1;
+            }
+        }
+
+        for (_, axle, leaf) in gadgets {
+            if self.contains(leaf) && self.phase(leaf).is_zero() {
+                self.remove_vertex(axle);
+                self.remove_vertex(leaf);
+                count += 1;
+            }
+        }
+
+        count
+    }
+
+    /// Turns the diagram into a graph-like diagram, and removes as many interior Clifford
+    /// spiders as possible with spider fusion, identity removal, local complementation and pivoting.
+    pub fn interior_clifford_simp(&mut self) {
+        self.to_graph_like();
+
+        loop {
+            let count = self.id_simp() + self.spider_simp() + self.pivot_simp() + self.lcomp_simp();
+
+            if count == 0 {
+                break;
+            }
+        }
+    }
+
+    /// Applies [`interior_clifford_simp`](Self::interior_clifford_simp) and pivots along boundaries,
+    /// which leaves a graph-like diagram with no interior Clifford spider, apart from gadgets.
+    pub fn clifford_simp(&mut self) {
+        loop {
+            self.interior_clifford_simp();
+
+            if self.pivot_boundary_simp() == 0 {
+                break;
+            }
+        }
+    }
+
+    /// Simplifies the diagram as much as possible with the rules of the strategy, including
+    /// phase gadgets, which may reduce the number of non-Clifford spiders.
+    pub fn full_reduce(&mut self) {
+        self.interior_clifford_simp();
+        self.pivot_gadget_simp();
+
+        loop {
+            self.clifford_simp();
+            let count = self.gadget_simp();
+            self.interior_clifford_simp();
+
+            if count + self.pivot_gadget_simp() == 0 {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::QuantumCircuit;
+
+    use super::*;
+
+    fn circuit() -> QuantumCircuit {
+        QuantumCircuit::new(|circ| {
+            let [a, b, c] = circ.alloc_n()?;
+            circ.h(a).cx(a, b).t(b).cx(b, c).s(c).tdg(a).cx(c, a).t(c).h(b);
+            circ.ccx(a, b, c).t(c).h(a).cx(a, b).sdg(b).t(a).cx(b, a).tdg(a);
+            Ok(())
+        }).unwrap()
+    }
+
+    #[test]
+    fn clifford() {
+        let circ = QuantumCircuit::new(|circ| {
+            let [a, b, c] = circ.alloc_n()?;
+            circ.h(a).cx(a, b).s(b).cz(b, c).h(c).sdg(a).cx(c, a).h(b).swap(a, c).x(b).cx(b, c).s(a);
+            Ok(())
+        }).unwrap();
+
+        let mut diagram = circ.to_zx().unwrap();
+        diagram.clifford_simp();
+
+        assert!(diagram.is_graph_like());
+        assert!(diagram.vertices().all(|v| diagram.kind(v) == VertexKind::Boundary || !diagram.is_interior(v)));
+        assert!(diagram.to_matrix().unwrap().approx_eq_up_to_phase(&circ.unitary().unwrap(), 1e-6));
+    }
+
+    #[test]
+    fn reduce() {
+        let circ = circuit();
+        let mut diagram = circ.to_zx().unwrap();
+        let (vertices, t_count) = (diagram.vertex_count(), diagram.t_count());
+
+        diagram.full_reduce();
+
+        assert!(diagram.is_graph_like());
+        assert!(diagram.vertex_count() < vertices);
+        assert!(diagram.t_count() < t_count);
+        assert!(diagram.to_matrix().unwrap().approx_eq_up_to_phase(&circ.unitary().unwrap(), 1e-6));
+    }
+
+    #[test]
+    fn cancel() {
+        // T and T† cancel out, along with the CNOTs.
+        let circ = QuantumCircuit::new(|circ| {
+            let [a, b] = circ.alloc_n()?;
+            circ.cx(a, b).t(b).h(a).h(a).tdg(b).cx(a, b);
+            Ok(())
+        }).unwrap();
+
+        let mut diagram = circ.to_zx().unwrap();
+        diagram.full_reduce();
+
+        assert_eq!(diagram.t_count(), 0);
+        assert!(diagram.to_matrix().unwrap().approx_eq_up_to_phase(&circ.unitary().unwrap(), 1e-6));
+    }
+}